@@ -1,7 +1,10 @@
-use crate::engine::Orby;
+use crate::engine::{LaneOptions, Orby};
 use crate::error::OrbyError;
+use crate::filter::Filter;
+use crate::logic::unique::DuplicatePolicy;
 use crate::types::{LogicMode, SaveMode};
 use std::path::PathBuf;
+use std::time::Duration;
 
 /// Builder for creating flexible `Orby` instances.
 pub struct OrbyBuilder {
@@ -10,6 +13,11 @@ pub struct OrbyBuilder {
     pub(crate) ring_buffer_lane_count: usize,
     pub(crate) storage_mode: SaveMode,
     pub(crate) logic_mode: LogicMode,
+    pub(crate) key_lane: usize,
+    pub(crate) retention: Option<Duration>,
+    pub(crate) lane_options: LaneOptions,
+    pub(crate) shards: usize,
+    pub(crate) max_groups: usize,
     pub(crate) compaction: bool,
     pub(crate) aof_enabled: bool,
     pub(crate) restore_path: Option<PathBuf>,
@@ -27,6 +35,11 @@ impl OrbyBuilder {
             ring_buffer_lane_count: 2,
             storage_mode: SaveMode::Vault(None),
             logic_mode: LogicMode::RingBuffer,
+            key_lane: 0,
+            retention: None,
+            lane_options: LaneOptions::default(),
+            shards: 1,
+            max_groups: crate::types::DEFAULT_MAX_GROUPS,
             compaction: false,
             aof_enabled: false,
             restore_path: None,
@@ -60,11 +73,183 @@ impl OrbyBuilder {
 
     /// Sets the logic mode of Orby.
     /// - `LogicMode::RingBuffer`: For time-series logs (automatically overwrites old data).
+    /// - `LogicMode::Table`: For audit logs (never overwrites; fails with `StorageFull` when full).
+    /// - `LogicMode::StaticSlot`: For inventories (stable slot IDs; freed slots are reused).
+    /// - `LogicMode::Sorted`: For range lookups (rows are kept ordered by the key lane).
+    /// - `LogicMode::HashSlot`: For dictionaries (O(1) get/upsert/delete by the key lane).
+    /// - `LogicMode::TopK`: For leaderboards (keeps the best rows by the key lane; evicts the worst).
     pub fn logic_mode(mut self, mode: LogicMode) -> Self {
         self.logic_mode = mode;
         self
     }
 
+    /// Sets the key lane used by key-based logic modes (default: lane 0).
+    /// - `LogicMode::Sorted`: Rows are kept in ascending order of this lane.
+    /// - `LogicMode::HashSlot`: Rows are placed by hashing this lane.
+    /// - `LogicMode::TopK`: Rows are ranked (and kept in ascending order) by this lane.
+    /// - `retention`: The UUIDv7 timestamp is read from this lane.
+    pub fn key_lane(mut self, lane: usize) -> Self {
+        self.key_lane = lane;
+        self
+    }
+
+    /// Expires rows older than `period`, judged by the 48-bit millisecond timestamp
+    /// of the UUIDv7 stored in the key lane.
+    /// Expired rows are hidden from queries and removed by `purge_expired`.
+    /// Only `LogicMode::RingBuffer` and `LogicMode::Table` support retention.
+    pub fn retention(mut self, period: Duration) -> Self {
+        self.retention = Some(period);
+        self
+    }
+
+    /// Keeps the values of `lane` unique among live rows, skipping duplicates on insert.
+    /// `insert_batch` / `insert_fixed` skip rows whose value already exists in the live window
+    /// and report them as `InsertOutcome::Duplicate`; `insert` fails with `OrbyError::DuplicateKey`.
+    /// Writes that cannot skip a row (`insert_lane_batch`, `update_by_id`, `upsert`, `purge_all_data`)
+    /// fail with the same error, as with `unique`.
+    /// Once a row is overwritten by wraparound or removed, its value becomes insertable again.
+    /// Only `LogicMode::RingBuffer` and `LogicMode::Table` maintain unique lanes: with
+    /// `StaticSlot`, `Sorted`, `HashSlot` or `TopK`, `build()` fails with `OrbyError::ConfigMismatch`
+    /// (`HashSlot` already keeps its `key_lane` unique). A sharded pool additionally requires
+    /// `lane` to be the `key_lane`.
+    pub fn unique_lane(mut self, lane: usize) -> Self {
+        self.lane_options.set_unique(lane, DuplicatePolicy::Skip);
+        self
+    }
+
+    /// Enforces that no two live rows share a value in `lane`.
+    /// Unlike `unique_lane`, which skips duplicate rows on insert, every write path
+    /// (`insert*`, `insert_lane_batch`, `update_by_id`, `upsert` and `purge_all_data`)
+    /// fails with `OrbyError::DuplicateKey` and leaves memory, Vault and AOF unchanged.
+    /// Rows overwritten by ring wraparound in the same write no longer count as live.
+    /// Both options share one index per lane; declaring a lane again replaces its policy.
+    /// Call repeatedly to constrain several lanes.
+    /// Only `LogicMode::RingBuffer` and `LogicMode::Table` maintain unique constraints: with
+    /// `StaticSlot`, `Sorted`, `HashSlot` or `TopK`, `build()` fails with `OrbyError::ConfigMismatch`
+    /// (`HashSlot` already keeps its `key_lane` unique). A sharded pool additionally requires
+    /// `lane` to be the `key_lane`.
+    pub fn unique(mut self, lane: usize) -> Self {
+        self.lane_options.set_unique(lane, DuplicatePolicy::Reject);
+        self
+    }
+
+    /// Maintains a hash index from the values of `lane` to the slots holding them.
+    /// `find_by`, `get_by_key`, `update_by_id` and `purge_by_id` on that lane look up
+    /// the index instead of scanning the whole ring. Call repeatedly to index several lanes.
+    /// Only `LogicMode::RingBuffer` and `LogicMode::Table` support index lanes;
+    /// in `StaticSlot`, `Sorted`, `HashSlot` and `TopK` pools `build()` fails with `OrbyError::ConfigMismatch`
+    /// (`Sorted` and `HashSlot` already look up their `key_lane` without an index).
+    pub fn index_lane(mut self, lane: usize) -> Self {
+        if !self.lane_options.index_lanes.contains(&lane) {
+            self.lane_options.index_lanes.push(lane);
+        }
+        self
+    }
+
+    /// Maintains a hash index keyed on the bits of `lane` selected by `mask`
+    /// (e.g. an entity-type prefix packed into the top bits).
+    /// `query` and `find_indices_where` use it for `Filter::Mask` conditions whose mask
+    /// contains every bit of `mask`, instead of testing `value & mask` on every row.
+    /// Only `LogicMode::RingBuffer` and `LogicMode::Table` support mask indexes;
+    /// in `StaticSlot`, `Sorted`, `HashSlot` and `TopK` pools `build()` fails with `OrbyError::ConfigMismatch`.
+    pub fn mask_index(mut self, lane: usize, mask: u128) -> Self {
+        if !self.lane_options.mask_indexes.contains(&(lane, mask)) {
+            self.lane_options.mask_indexes.push((lane, mask));
+        }
+        self
+    }
+
+    /// Maintains a `mask_index` on the top `bits` bits of `lane`.
+    /// Pair it with `Filter::prefix(lane, bits, prefix)` in `query`.
+    /// Like `mask_index`, it makes `build()` fail with `OrbyError::ConfigMismatch` outside
+    /// `LogicMode::RingBuffer` and `LogicMode::Table`.
+    pub fn prefix_index(self, lane: usize, bits: u32) -> Self {
+        self.mask_index(lane, Filter::prefix_mask(bits))
+    }
+
+    /// Keeps a Bloom filter of the values of `lane` for every block of 4096 slots.
+    /// `find_by`, `update_by_id` and `purge_by_id` on that lane skip blocks that
+    /// definitely do not contain the value, which makes lookups for absent keys cheap.
+    /// A block's filter is rebuilt once ring wraparound finishes overwriting it, and after compaction.
+    /// Only `LogicMode::RingBuffer` and `LogicMode::Table` support Bloom lanes;
+    /// in `StaticSlot`, `Sorted`, `HashSlot` and `TopK` pools `build()` fails with `OrbyError::ConfigMismatch`.
+    pub fn bloom_lane(mut self, lane: usize) -> Self {
+        self.lane_options.bloom_lane = Some(lane);
+        self
+    }
+
+    /// Keeps a HyperLogLog sketch of the values of `lane` (1 KiB per block of 4096 slots),
+    /// updated on every insert, so `approx_distinct(lane)` can estimate the number of
+    /// distinct live values (about 3% standard error) without scanning the rows.
+    /// Like `bloom_lane`, a block's sketch is rebuilt once ring wraparound finishes
+    /// overwriting it, and after compaction; until then removed values may still be counted.
+    /// Call repeatedly to sketch several lanes.
+    /// Only `LogicMode::RingBuffer` and `LogicMode::Table` support sketched lanes;
+    /// in `StaticSlot`, `Sorted`, `HashSlot` and `TopK` pools `build()` fails with `OrbyError::ConfigMismatch`.
+    pub fn approx_distinct_lane(mut self, lane: usize) -> Self {
+        if !self.lane_options.sketch_lanes.contains(&lane) {
+            self.lane_options.sketch_lanes.push(lane);
+        }
+        self
+    }
+
+    /// Maintains a bitmap index on a low-cardinality `lane` (status or type codes):
+    /// one bitset of slots per distinct value, costing `capacity / 8` bytes per value.
+    /// `query` answers `Filter::Eq` / `Filter::In` on such lanes, and `And` / `Or`
+    /// combinations of them, with bitset AND/OR instead of a row-by-row scan.
+    /// Once a lane holds more than `MAX_BITMAP_VALUES` (256) distinct values the index
+    /// drops its bitsets and queries fall back to a scan; it is rebuilt, and used
+    /// again if back under the limit, on compaction, re-initialization or vault load.
+    /// Call repeatedly to index several lanes.
+    /// Only `LogicMode::RingBuffer` and `LogicMode::Table` support bitmap lanes;
+    /// in `StaticSlot`, `Sorted`, `HashSlot` and `TopK` pools `build()` fails with `OrbyError::ConfigMismatch`.
+    pub fn bitmap_lane(mut self, lane: usize) -> Self {
+        if !self.lane_options.bitmap_lanes.contains(&lane) {
+            self.lane_options.bitmap_lanes.push(lane);
+        }
+        self
+    }
+
+    /// Maintains a composite index over `lanes`, keyed on their values in the given order
+    /// (e.g. `&[tenant_lane, user_lane]`).
+    /// `query` and `find_indices_where` use it for `Filter::Eq` conditions (alone or in a
+    /// `Filter::And`) that cover the full key or a prefix of its leading lanes.
+    /// Only `LogicMode::RingBuffer` and `LogicMode::Table` support composite indexes;
+    /// in `StaticSlot`, `Sorted`, `HashSlot` and `TopK` pools `build()` fails with `OrbyError::ConfigMismatch`.
+    pub fn composite_index(mut self, lanes: &[usize]) -> Self {
+        if !self
+            .lane_options
+            .composite_indexes
+            .iter()
+            .any(|l| l == lanes)
+        {
+            self.lane_options.composite_indexes.push(lanes.to_vec());
+        }
+        self
+    }
+
+    /// Splits the pool into `n` independent shards, routing rows by hashing the key lane.
+    /// Each shard has its own lock, cursor and Vault subdirectory, and holds
+    /// `ring_buffer_lane_item_count / n` rows (rounded up).
+    /// `query_raw`, `find_by` and `take` fan out across shards and merge results newest-first.
+    /// `get_at`, `find_indices` and `find_indices_where` count live rows in that merged order,
+    /// and `zone_map` lists each shard's blocks one after another in shard order.
+    /// APIs that address a physical slot or a single store are not available on a sharded pool:
+    /// `pop_*`, `insert_lane_batch` and `delete` fail with `ConfigMismatch`, and `query_iter` panics.
+    /// Only `LogicMode::RingBuffer` and `LogicMode::Table` support shards.
+    pub fn shards(mut self, n: usize) -> Self {
+        self.shards = n.max(1);
+        self
+    }
+
+    /// Caps the number of distinct keys one `group_by` call may build
+    /// (default: `DEFAULT_MAX_GROUPS`). A query that would exceed it fails with
+    /// `OrbyError::TooManyGroups` instead of growing without bound.
+    pub fn max_groups(mut self, n: usize) -> Self {
+        self.max_groups = n;
+        self
+    }
+
     /// Sets the compaction behavior upon deletion.
     /// - `true`: Slide data to fill gaps (Packed Mode).
     /// - `false`: Zero out the deleted slot (Sparse Mode / Ring).
@@ -115,6 +300,10 @@ impl OrbyBuilder {
         // 1. Memory Safety Guard
         self.check_memory_safety()?;
 
+        if self.shards > 1 {
+            return self.build_sharded().await;
+        }
+
         let ring_name = self.ring_name.clone();
         let storage_mode = self.storage_mode.clone();
         let autoload = self.autoload;
@@ -128,11 +317,15 @@ impl OrbyBuilder {
             self.ring_buffer_lane_count,
             storage_mode.clone(),
             self.logic_mode,
+            self.key_lane,
+            self.retention,
+            &self.lane_options,
             self.compaction,
             self.aof_enabled,
             self.capacity_usage_ratio,
         )
         .await?;
+        engine.inner.write().max_groups = self.max_groups;
 
         if let Some(path) = restore_path {
             OrbyBuilder::handle_restore_static(&engine, path).await?;
@@ -161,6 +354,95 @@ impl OrbyBuilder {
         Ok(engine)
     }
 
+    /// Builds one `Orby` per shard plus a routing handle that owns them.
+    /// Shards get an extra trailing lane holding the global sequence number.
+    async fn build_sharded(self) -> Result<Orby, OrbyError> {
+        let name = self.ring_name.clone();
+        let mismatch = |reason: &str| OrbyError::ConfigMismatch {
+            name: name.clone(),
+            reason: reason.into(),
+        };
+        if !matches!(self.logic_mode, LogicMode::RingBuffer | LogicMode::Table) {
+            return Err(mismatch(
+                "shards require LogicMode::RingBuffer or LogicMode::Table",
+            ));
+        }
+        if let Some(&(_, policy)) = self
+            .lane_options
+            .unique_lanes
+            .iter()
+            .find(|&&(lane, _)| lane != self.key_lane)
+        {
+            return Err(mismatch(match policy {
+                DuplicatePolicy::Skip => "unique_lane must equal key_lane on a sharded pool",
+                DuplicatePolicy::Reject => "unique must be the key_lane on a sharded pool",
+            }));
+        }
+        if self.restore_path.is_some() {
+            return Err(mismatch("from_file is not supported on a sharded pool"));
+        }
+
+        let mut router = Orby::try_new(
+            &name,
+            0,
+            self.ring_buffer_lane_count,
+            SaveMode::MemoryOnly,
+            self.logic_mode,
+            self.key_lane,
+            self.retention,
+            &self.lane_options,
+            self.compaction,
+            false,
+            self.capacity_usage_ratio,
+        )
+        .await?;
+        router.inner.write().max_groups = self.max_groups;
+
+        // 各シャードは `<vault>/<name>/` 配下に独自のサブディレクトリを持つ
+        let storage_mode = match &self.storage_mode {
+            SaveMode::Vault(opt_path) => {
+                let base_path = opt_path
+                    .as_ref()
+                    .cloned()
+                    .unwrap_or_else(|| PathBuf::from(crate::types::DEFAULT_VAULT_DIR));
+                SaveMode::Vault(Some(base_path.join(&name)))
+            }
+            SaveMode::MemoryOnly => SaveMode::MemoryOnly,
+        };
+        let per_shard = self.ring_buffer_lane_item_count.div_ceil(self.shards);
+
+        let mut shards = Vec::with_capacity(self.shards);
+        for i in 0..self.shards {
+            let builder = OrbyBuilder {
+                ring_name: format!("{}_shard_{}", name, i),
+                ring_buffer_lane_item_count: per_shard,
+                ring_buffer_lane_count: self.ring_buffer_lane_count + 1,
+                storage_mode: storage_mode.clone(),
+                logic_mode: self.logic_mode,
+                key_lane: self.key_lane,
+                retention: self.retention,
+                lane_options: self.lane_options.clone(),
+                shards: 1,
+                max_groups: self.max_groups,
+                compaction: self.compaction,
+                aof_enabled: self.aof_enabled,
+                restore_path: None,
+                capacity_usage_ratio: self.capacity_usage_ratio,
+                autoload: self.autoload,
+                strict_check: self.strict_check,
+                memory_limit: self.memory_limit,
+            };
+            shards.push(Box::pin(builder.build()).await?);
+        }
+
+        router.shards = Some(std::sync::Arc::new(crate::engine::shard::ShardSet::new(
+            shards,
+            self.key_lane,
+            self.ring_buffer_lane_count,
+        )));
+        Ok(router)
+    }
+
     fn check_memory_safety(&self) -> Result<(), OrbyError> {
         let required_bytes =
             (self.ring_buffer_lane_count as u64) * (self.ring_buffer_lane_item_count as u64) * 16;
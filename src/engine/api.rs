@@ -1,15 +1,21 @@
 use crate::engine::iter::OrbyIterator;
 use crate::engine::Orby;
 use crate::error::OrbyError;
-use crate::logic::ring;
+use crate::filter::Filter;
+use crate::logic::planner::{self, Plan};
+use crate::logic::{aggregate, hash, ring, sketch, slot, sorted, topk, unique};
 use crate::row::PulseCellPack;
-use crate::types::{LogicMode, PulseCell};
-use std::collections::HashSet;
+use crate::types::{
+    Agg, AggregateResult, InsertOutcome, LaneZone, LogicMode, PulseCell, QueryPlan,
+};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 impl Orby {
-    /// 128-bit値のバッチを追加します。
-    pub async fn insert_batch<I, T>(&self, items: I) -> Result<(), OrbyError>
+    /// 128-bit値のバッチを追加し、各行の結果を挿入順に返します。
+    /// `unique_lane` が設定されている場合、既存の値と重複する行は書き込まれず `InsertOutcome::Duplicate` となります。
+    /// `LogicMode::TopK` で順位に入らない行は書き込まれず `InsertOutcome::RankTooLow` となります。
+    pub async fn insert_batch<I, T>(&self, items: I) -> Result<Vec<InsertOutcome>, OrbyError>
     where
         I: IntoIterator<Item = T>,
         T: AsRef<[u128]>,
@@ -18,36 +24,182 @@ impl Orby {
             .into_iter()
             .map(|item| item.as_ref().to_vec())
             .collect();
+        self.insert_rows(raw_items).await
+    }
+
+    /// 1 行を追加し、書き込まれた物理スロット番号を返します。
+    /// `LogicMode::StaticSlot` では、このスロット番号が不変の ID として機能します。
+    /// `unique_lane` の値が重複する場合は `OrbyError::DuplicateKey` を、
+    /// `LogicMode::TopK` で順位に入らない場合は `OrbyError::RankTooLow` を返します。
+    pub async fn insert(&self, row: &[u128]) -> Result<usize, OrbyError> {
+        match self.insert_rows(vec![row.to_vec()]).await?[0] {
+            InsertOutcome::Inserted(slot) => Ok(slot),
+            InsertOutcome::Duplicate => {
+                if let Some(shards) = &self.shards {
+                    // シャード構成で除外されるのは、一意性のレーンに指定したキーレーンの重複のみ
+                    return Err(shards.duplicate_key(row[shards.key_lane]));
+                }
+                let store = self.inner.read();
+                let lane = unique::skipping(&store)
+                    .find(|keys| keys.contains(row[keys.lane]))
+                    .map_or(0, |keys| keys.lane);
+                Err(OrbyError::DuplicateKey {
+                    pool_name: store.name.clone(),
+                    lane,
+                    value: row[lane],
+                })
+            }
+            InsertOutcome::RankTooLow => {
+                let store = self.inner.read();
+                Err(topk::rank_too_low(&store, row[store.key_lane]))
+            }
+        }
+    }
+
+    /// 行データを論理モードに応じて書き込み、各行の結果（使用した物理スロット番号）を挿入順に返します。
+    pub(crate) async fn insert_rows(
+        &self,
+        raw_items: Vec<Vec<u128>>,
+    ) -> Result<Vec<InsertOutcome>, OrbyError> {
         if raw_items.is_empty() {
-            return Ok(());
+            return Ok(Vec::new());
+        }
+        if let Some(shards) = &self.shards {
+            return shards.insert_rows(raw_items).await;
         }
 
-        let (aof_sender, mirror_sender, has_vault, start_idx, changes, lane_count) = {
+        let (
+            aof_sender,
+            mirror_sender,
+            has_vault,
+            start_idx,
+            slots,
+            outcomes,
+            raw_items,
+            changes,
+            lane_count,
+        ) = {
             let mut store = self.inner.write();
             let mode = store.logic_mode;
             let has_vault = store.vault_path.is_some();
             let start_idx = store.cursor;
             let lane_count = store.ring_buffer_lane_count;
+            let cap = store.capacity;
 
-            let changes = match mode {
-                LogicMode::RingBuffer => ring::insert_batch(&mut store, raw_items.iter())?,
+            // 一意性レーンの値が重複する行や、TopK で順位に入らない行は書き込まずに除外する
+            let skipped: Vec<Option<InsertOutcome>> = match mode {
+                LogicMode::TopK(_) => topk::admit(&store, &raw_items)
+                    .into_iter()
+                    .map(|ok| (!ok).then_some(InsertOutcome::RankTooLow))
+                    .collect(),
+                _ => unique::admit(&store, &raw_items)
+                    .into_iter()
+                    .map(|ok| (!ok).then_some(InsertOutcome::Duplicate))
+                    .collect(),
             };
+            let raw_items: Vec<Vec<u128>> = raw_items
+                .into_iter()
+                .zip(&skipped)
+                .filter_map(|(row, skip)| skip.is_none().then_some(row))
+                .collect();
+
+            let (slots, changes) = match mode {
+                LogicMode::RingBuffer | LogicMode::Table => {
+                    let changes = ring::insert_batch(&mut store, raw_items.iter())?;
+                    let slots = (0..raw_items.len())
+                        .map(|i| (start_idx + i) % cap)
+                        .collect();
+                    (slots, changes)
+                }
+                LogicMode::StaticSlot => slot::insert_batch(&mut store, raw_items.iter())?,
+                LogicMode::Sorted => sorted::insert_batch(&mut store, raw_items.iter())?,
+                LogicMode::HashSlot => hash::insert_batch(&mut store, raw_items.iter())?,
+                LogicMode::TopK(_) => topk::insert_batch(&mut store, raw_items.iter())?,
+            };
+            let mut accepted = slots.iter().copied();
+            let outcomes = skipped
+                .into_iter()
+                .map(|skip| {
+                    skip.unwrap_or_else(|| {
+                        accepted
+                            .next()
+                            .map_or(InsertOutcome::Duplicate, InsertOutcome::Inserted)
+                    })
+                })
+                .collect::<Vec<_>>();
             (
                 store.aof_sender.clone(),
                 store.mirror_sender.clone(),
                 has_vault,
                 start_idx,
+                slots,
+                outcomes,
+                raw_items,
                 changes,
                 lane_count,
             )
         };
 
-        if has_vault {
-            self.commit_vault_batch(start_idx, raw_items).await?;
+        if has_vault && !raw_items.is_empty() {
+            match self.logic_mode() {
+                LogicMode::RingBuffer | LogicMode::Table => {
+                    self.commit_vault_batch(start_idx, raw_items).await?
+                }
+                LogicMode::StaticSlot | LogicMode::HashSlot => {
+                    let rows = slots.iter().copied().zip(raw_items).collect();
+                    self.commit_vault_rows(rows).await?
+                }
+                LogicMode::Sorted | LogicMode::TopK(_) => {
+                    if let Some(range) = changes.shifted_range() {
+                        self.commit_vault_range(range).await?
+                    }
+                }
+            }
         }
 
         let (aof_data, mirror_data) = changes.flatten(lane_count);
 
+        if let Some(sender) = aof_sender {
+            if !aof_data.is_empty() {
+                let _ = sender.send(aof_data).await;
+            }
+        }
+        if let Some(sender) = mirror_sender {
+            if !mirror_data.is_empty() {
+                let _ = sender.send(mirror_data).await;
+            }
+        }
+        Ok(outcomes)
+    }
+
+    /// 指定したスロットへ行データを直接書き込みます（`LogicMode::StaticSlot` 専用）。
+    /// 使用中のスロットは上書きされます。
+    pub async fn insert_at(&self, slot: usize, row: &[u128]) -> Result<(), OrbyError> {
+        let (aof_sender, mirror_sender, has_vault, changes, lane_count) = {
+            let mut store = self.inner.write();
+            if store.logic_mode != LogicMode::StaticSlot {
+                return Err(OrbyError::ConfigMismatch {
+                    name: store.name.clone(),
+                    reason: "insert_at requires LogicMode::StaticSlot".into(),
+                });
+            }
+            let has_vault = store.vault_path.is_some();
+            let lane_count = store.ring_buffer_lane_count;
+            let changes = slot::insert_at(&mut store, slot, row)?;
+            (
+                store.aof_sender.clone(),
+                store.mirror_sender.clone(),
+                has_vault,
+                changes,
+                lane_count,
+            )
+        };
+
+        if has_vault {
+            self.commit_vault_rows(vec![(slot, row.to_vec())]).await?;
+        }
+
+        let (aof_data, mirror_data) = changes.flatten(lane_count);
         if let Some(sender) = aof_sender {
             if !aof_data.is_empty() {
                 let _ = sender.send(aof_data).await;
@@ -61,25 +213,71 @@ impl Orby {
         Ok(())
     }
 
-    /// 固定次元の行構造体を使用した高速なバッチ挿入を提供します。
+    /// 物理スロット番号を指定してデータを取得します。空きスロットの場合は `None` を返します。
+    pub fn get_slot(&self, slot: usize) -> Option<Arc<[u128]>> {
+        let store = self.inner.read();
+        slot::get_slot(&store, slot)
+    }
+
+    /// キーレーンの値が `key` に一致する行を取得します。
+    /// `LogicMode::HashSlot` ではハッシュ探索により O(1) で取得し、それ以外のモードでは最新の一件を返します。
+    pub fn get_by_key(&self, key: u128) -> Option<Arc<[u128]>> {
+        let key_lane = {
+            let store = self.inner.read();
+            if store.logic_mode == LogicMode::HashSlot {
+                return hash::get(&store, key);
+            }
+            store.key_lane
+        };
+        let targets = HashSet::from([key]);
+        self.find_by(key_lane, &targets, 1).into_iter().next()
+    }
+
+    /// 固定次元の行構造体を使用した高速なバッチ挿入を提供し、各行の結果を挿入順に返します。
     pub async fn insert_fixed<const N: usize>(
         &self,
         items: Vec<PulseCellPack<N>>,
-    ) -> Result<(), OrbyError> {
+    ) -> Result<Vec<InsertOutcome>, OrbyError> {
+        let row_path = {
+            let store = self.inner.read();
+            // 重複判定やシャードへの振り分けが必要な場合も行単位の経路で処理する
+            self.shards.is_some()
+                || unique::skipping(&store).next().is_some()
+                || matches!(
+                    store.logic_mode,
+                    LogicMode::StaticSlot
+                        | LogicMode::Sorted
+                        | LogicMode::HashSlot
+                        | LogicMode::TopK(_)
+                )
+        };
+        if row_path {
+            let rows = items
+                .iter()
+                .map(|pack| pack.values.iter().map(|pc| pc.as_u128()).collect())
+                .collect();
+            return self.insert_rows(rows).await;
+        }
+
         // Convert to Vec<Vec<u128>> for Vault if needed
-        let (aof_sender, mirror_sender, has_vault, start_idx, changes, lane_count) = {
+        let (aof_sender, mirror_sender, has_vault, start_idx, outcomes, changes, lane_count) = {
             let mut store = self.inner.write();
             let has_vault = store.vault_path.is_some();
             let start_idx = store.cursor;
             let lane_count = store.ring_buffer_lane_count;
+            let cap = store.capacity;
 
             let changes = ring::insert_fixed(&mut store, items.clone())?;
+            let outcomes = (0..items.len())
+                .map(|i| InsertOutcome::Inserted((start_idx + i) % cap))
+                .collect::<Vec<_>>();
 
             (
                 store.aof_sender.clone(),
                 store.mirror_sender.clone(),
                 has_vault,
                 start_idx,
+                outcomes,
                 changes,
                 lane_count,
             )
@@ -105,19 +303,34 @@ impl Orby {
                 let _ = sender.send(mirror_data).await;
             }
         }
-        Ok(())
+        Ok(outcomes)
     }
 
     /// 条件に一致するデータを一件ずつ取得するためのイテレータを生成します。
+    ///
+    /// # Panics
+    /// イテレータは単一のストアの読み取りロックを保持するため、シャード構成のプールでは使用できず、パニックします
+    /// （`query_raw` / `query` を使用してください）。
     pub fn query_iter<'a, F>(&'a self, filter: F) -> OrbyIterator<'a, F>
     where
         F: Fn(&[PulseCell]) -> bool,
     {
+        // ルーターのストアは行を持たないため、空のイテレータを返すと一致なしと区別できない
+        assert!(
+            self.shards.is_none(),
+            "query_iter is not available on a sharded pool; use query_raw or query"
+        );
         let store = self.inner.read();
         let logic_mode = store.logic_mode;
         let cursor = store.cursor;
         let cap = store.capacity;
-        let len = store.len;
+        // 墓標を含むウィンドウ全体を走査対象とする
+        let len = if ring::is_wrapped(&store) {
+            cap
+        } else {
+            cursor
+        };
+        let cutoff = ring::expiry_cutoff(&store);
 
         let file = if store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
             store
@@ -136,6 +349,7 @@ impl Orby {
             cursor,
             cap,
             len,
+            cutoff,
             file,
         }
     }
@@ -145,30 +359,184 @@ impl Orby {
     where
         F: Fn(&[PulseCell]) -> bool + Sync + Send,
     {
+        if let Some(shards) = &self.shards {
+            return shards.query_raw(filter, limit);
+        }
         let store = self.inner.read();
         match store.logic_mode {
-            LogicMode::RingBuffer => ring::query_raw(&store, filter, limit),
+            LogicMode::RingBuffer
+            | LogicMode::Table
+            | LogicMode::StaticSlot
+            | LogicMode::Sorted
+            | LogicMode::HashSlot
+            | LogicMode::TopK(_) => ring::query_raw(&store, filter, limit),
+        }
+    }
+
+    /// `lanes` に束縛した述語で検索し、一致したデータを最新順に最大 `limit` 件返します。
+    /// 述語には `lanes` の順に並べた値のみが渡され、走査では指定したレーンのバッファだけを読みます。
+    /// 行の全レーンは一致した行についてのみ集めるため、走査時間はプールのレーン数に依存しません。
+    /// 範囲外のレーンを指定した場合は空の結果を返します。
+    pub fn query_lanes<P>(&self, lanes: &[usize], predicate: P, limit: usize) -> Vec<Arc<[u128]>>
+    where
+        P: Fn(&[u128]) -> bool + Sync + Send,
+    {
+        if let Some(shards) = &self.shards {
+            return shards.query_lanes(lanes, predicate, limit);
+        }
+        let store = self.inner.read();
+        if lanes
+            .iter()
+            .any(|&lane| lane >= store.ring_buffer_lane_count)
+        {
+            return Vec::new();
+        }
+        ring::query_lanes(&store, lanes, predicate, limit)
+    }
+
+    /// `Filter` に一致するデータを最新順に最大 `limit` 件検索します。
+    /// `bitmap_lane` の索引で評価できる条件はビットマップの論理積・論理和で、
+    /// `composite_index` の先頭レーンを覆う等値条件は複合索引で、
+    /// `index_lane` の等値条件と `mask_index` / `prefix_index` のマスク条件はレーン索引で絞り込みます。
+    /// 索引の候補がブロック走査より多い場合や索引が使えない場合は、ゾーンマップとブルームフィルタで
+    /// 一致し得ないブロックを読み飛ばして走査します。選ばれる経路は `explain` で確認できます。
+    pub fn query(&self, filter: &Filter, limit: usize) -> Vec<Arc<[u128]>> {
+        if let Some(shards) = &self.shards {
+            return shards.query(filter, limit);
+        }
+        let store = self.inner.read();
+        match planner::plan(&store, filter) {
+            Plan::Candidates { slots, exact, .. } => {
+                ring::collect_candidates(&store, slots, (!exact).then_some(filter), limit)
+            }
+            Plan::Blocks { keep } => ring::query_blocks(&store, |block| keep[block], filter, limit),
+            Plan::FullScan => ring::query_blocks(&store, |_| true, filter, limit),
+        }
+    }
+
+    /// `Filter` に一致する論理インデックスの一覧を返します。
+    /// `query` と同じ経路で検索するため、使用可能な索引があれば全件走査を行いません。
+    /// シャード構成のプールでは、`find_indices` と同じく統合した順序での論理インデックスを返します。
+    pub fn find_indices_where(&self, filter: &Filter, limit: usize) -> Vec<usize> {
+        if let Some(shards) = &self.shards {
+            if filter.lanes().iter().any(|&lane| lane >= shards.lane_count) {
+                return Vec::new();
+            }
+            return shards.find_indices(|row| filter.matches(row), limit);
+        }
+        let store = self.inner.read();
+        match planner::plan(&store, filter) {
+            Plan::Candidates { slots, exact, .. } => {
+                ring::candidate_indices(&store, slots, (!exact).then_some(filter), limit)
+            }
+            Plan::Blocks { keep } => {
+                ring::find_indices_in_blocks(&store, |block| keep[block], filter, limit)
+            }
+            Plan::FullScan => ring::find_indices_in_blocks(&store, |_| true, filter, limit),
+        }
+    }
+
+    /// `Filter` に一致する行について、`aggs` の集計（件数・最小・最大・合計・ヒストグラム）を計算します。
+    /// 結果は `aggs` と同じ順に並びます。行を `Arc<[u128]>` として取り出さず、集計対象のレーンのみを並列に読みます。
+    /// 範囲外のレーンを指定した場合は `LaneCountMismatch`、ヒストグラムの幅またはバケット数が 0 の場合や
+    /// バケット数が `MAX_HISTOGRAM_BUCKETS`（4096）を超える場合は `ConfigMismatch` を返します。
+    pub fn aggregate(&self, filter: &Filter, aggs: &[Agg]) -> Result<AggregateResult, OrbyError> {
+        if let Some(shards) = &self.shards {
+            return shards.aggregate(filter, aggs);
+        }
+        let store = self.inner.read();
+        aggregate::validate(&store.name, store.ring_buffer_lane_count, aggs)?;
+        Ok(aggregate::aggregate(&store, filter, aggs))
+    }
+
+    /// `Filter` に一致する行を `key_lane` の値ごとにまとめ、キー → 集計結果（`aggs` と同じ順）の表を返します。
+    /// 各スレッドの部分的な表を最後に統合して並列に計算します。
+    /// グループ数が `max_groups`（既定値 `DEFAULT_MAX_GROUPS`）を超える場合は、途中で打ち切り
+    /// `TooManyGroups` を返します。範囲外のレーンやヒストグラムの設定は `aggregate` と同様に検証します。
+    pub fn group_by(
+        &self,
+        key_lane: usize,
+        filter: &Filter,
+        aggs: &[Agg],
+    ) -> Result<HashMap<u128, AggregateResult>, OrbyError> {
+        let max_groups = self.inner.read().max_groups;
+        if let Some(shards) = &self.shards {
+            return shards.group_by(key_lane, filter, aggs, max_groups);
+        }
+        let store = self.inner.read();
+        aggregate::check_lane(&store.name, store.ring_buffer_lane_count, key_lane)?;
+        aggregate::validate(&store.name, store.ring_buffer_lane_count, aggs)?;
+        aggregate::group_by(&store, key_lane, filter, aggs, max_groups).ok_or_else(|| {
+            OrbyError::TooManyGroups {
+                pool_name: store.name.clone(),
+                max_groups,
+            }
+        })
+    }
+
+    /// `Filter` に一致する行の `lane` の異なる値を、小さい順に最大 `limit` 件返します（正確な値）。
+    /// 各スレッドが小さい方から `limit` 件のみを保持して並列に計算するため、メモリは `limit` に比例します。
+    /// 範囲外のレーンを指定した場合は `LaneCountMismatch` を返します。
+    pub fn distinct(
+        &self,
+        lane: usize,
+        filter: &Filter,
+        limit: usize,
+    ) -> Result<Vec<u128>, OrbyError> {
+        if let Some(shards) = &self.shards {
+            return shards.distinct(lane, filter, limit);
+        }
+        let store = self.inner.read();
+        aggregate::check_lane(&store.name, store.ring_buffer_lane_count, lane)?;
+        Ok(aggregate::distinct(&store, lane, filter, limit))
+    }
+
+    /// `approx_distinct_lane` で宣言したレーンについて、生存行の異なる値の数を HyperLogLog で推定します。
+    /// 行を走査せず、ブロックごとのスケッチを統合するだけなので、ヘルスチェックなどから頻繁に呼び出せます。
+    /// 標準誤差は約 3% で、上書き・削除された値はブロックが作り直されるまで数えられ得ます。
+    /// スケッチのないレーンを指定した場合は `ConfigMismatch` を返します。
+    pub fn approx_distinct(&self, lane: usize) -> Result<u64, OrbyError> {
+        if let Some(shards) = &self.shards {
+            return shards.approx_distinct(lane);
+        }
+        Ok(sketch::estimate(&self.sketch_registers(lane)?))
+    }
+
+    /// `lane` のスケッチの、全ブロックを統合したレジスタを返します。
+    pub(crate) fn sketch_registers(&self, lane: usize) -> Result<Vec<u8>, OrbyError> {
+        let store = self.inner.read();
+        aggregate::check_lane(&store.name, store.ring_buffer_lane_count, lane)?;
+        sketch::registers(&store, lane).ok_or_else(|| OrbyError::ConfigMismatch {
+            name: store.name.clone(),
+            reason: format!("lane {lane} has no approx_distinct_lane sketch"),
+        })
+    }
+
+    /// `query` がフィルタの検索に選ぶ経路と、読む行数・読み飛ばすブロック数の見積もりを返します。
+    /// シャード構成では全シャードの見積もりを合計し、経路は最もコストの高いシャードのものを返します。
+    pub fn explain(&self, filter: &Filter) -> QueryPlan {
+        if let Some(shards) = &self.shards {
+            return shards.explain(filter);
         }
+        let store = self.inner.read();
+        planner::explain(&store, filter)
     }
 
     /// 特定のカラム（index）の値が `targets` のいずれかに一致するデータを最新順に検索します。
+    /// `Filter::In` による `query` の薄いラッパーで、索引・キーレーン・ブルームフィルタは `query` と同様に使用されます。
     pub fn find_by(&self, index: usize, targets: &HashSet<u128>, limit: usize) -> Vec<Arc<[u128]>> {
         if targets.is_empty() {
             return Vec::new();
         }
-        self.query_raw(
-            |row| {
-                if let Some(val) = row.get(index) {
-                    targets.contains(&val.as_u128())
-                } else {
-                    false
-                }
-            },
-            limit,
-        )
+        let filter = Filter::In {
+            lane: index,
+            values: targets.iter().copied().collect(),
+        };
+        self.query(&filter, limit)
     }
 
     /// 特定のカラム（index）の値が `min` 以上 `max` 以下であるデータを最新順に検索します。
+    /// `Filter::Range` による `query` の薄いラッパーで、範囲と重なり得ないブロックは走査しません。
     pub fn find_custom(
         &self,
         index: usize,
@@ -176,30 +544,87 @@ impl Orby {
         max: u128,
         limit: usize,
     ) -> Vec<Arc<[u128]>> {
-        self.query_raw(
-            |row| {
-                if let Some(val) = row.get(index) {
-                    let v = val.as_u128();
-                    v >= min && v <= max
-                } else {
-                    false
-                }
+        self.query(
+            &Filter::Range {
+                lane: index,
+                min,
+                max,
             },
             limit,
         )
     }
 
-    /// 特定の ID (u128) に一致するデータをプールから削除（ゼロ埋め）します。
+    /// 挿入順に単調増加するレーン（UUIDv7 やタイムスタンプ等）について、値が `min` 以上 `max` 以下のデータを
+    /// 全件走査せずに二分探索で最新順に取得します。
+    /// 探索中にレーンが単調でないことが判明した場合は `OrbyError::ConfigMismatch` を返します。
+    /// `LogicMode::Sorted` / `LogicMode::TopK` のキーレーンではキー順の二分探索を使用します。
+    pub fn find_range_monotonic(
+        &self,
+        lane: usize,
+        min: u128,
+        max: u128,
+    ) -> Result<Vec<Arc<[u128]>>, OrbyError> {
+        if let Some(shards) = &self.shards {
+            return shards.find_range_monotonic(lane, min, max);
+        }
+        let store = self.inner.read();
+        match store.logic_mode {
+            LogicMode::RingBuffer | LogicMode::Table => {
+                ring::find_range_monotonic(&store, lane, min, max)
+            }
+            LogicMode::Sorted | LogicMode::TopK(_) if store.key_lane == lane => {
+                Ok(sorted::find_range(&store, min, max, usize::MAX))
+            }
+            mode => Err(OrbyError::ConfigMismatch {
+                name: store.name.clone(),
+                reason: format!(
+                    "find_range_monotonic on lane {} is not supported by {:?}",
+                    lane, mode
+                ),
+            }),
+        }
+    }
+
+    /// レーンのゾーンマップ（物理ブロックごとの値の最小・最大）を返します。行を含まないブロックは除きます。
+    /// シャード構成のプールでは、各シャードのブロックをシャードの番号順に連結した物理位置で返します。
+    pub fn zone_map(&self, lane: usize) -> Vec<LaneZone> {
+        if let Some(shards) = &self.shards {
+            return shards.zone_map(lane);
+        }
+        let store = self.inner.read();
+        store.zones.lane_zones(lane, store.capacity)
+    }
+
+    /// ゾーンマップから求めたレーン全体の値の範囲 `(min, max)` を返します。行がない場合は `None` です。
+    /// 削除された行の値を含む場合があり、実際の範囲より広くなることがあります。
+    pub fn lane_range(&self, lane: usize) -> Option<(u128, u128)> {
+        if let Some(shards) = &self.shards {
+            return shards.lane_range(lane);
+        }
+        self.zone_map(lane)
+            .iter()
+            .map(|zone| (zone.min, zone.max))
+            .reduce(|(lo, hi), (min, max)| (lo.min(min), hi.max(max)))
+    }
+
+    /// 特定の ID (u128) に一致するデータをプールから削除（墓標化）します。
     pub async fn purge_by_id(&self, index: usize, id: u128) {
-        if id == 0 {
-            return;
+        if let Some(shards) = &self.shards {
+            return shards.purge_by_id(index, id).await;
         }
         let (aof_sender, mirror_sender, changes, lane_count) = {
             let mut store = self.inner.write();
             let lane_count = store.ring_buffer_lane_count;
             let logic_mode = store.logic_mode;
             let changes = match logic_mode {
-                LogicMode::RingBuffer => ring::purge_by_id(&mut store, index, id),
+                LogicMode::RingBuffer | LogicMode::Table => {
+                    ring::purge_by_id(&mut store, index, id)
+                }
+                LogicMode::StaticSlot => slot::purge_by_id(&mut store, index, id),
+                LogicMode::Sorted | LogicMode::TopK(_) => {
+                    sorted::purge_by_id(&mut store, index, id)
+                }
+                LogicMode::HashSlot => hash::purge_by_id(&mut store, index, id),
             };
             (
                 store.aof_sender.clone(),
@@ -208,6 +633,19 @@ impl Orby {
                 lane_count,
             )
         };
+        if self.inner.read().vault_path.is_some() {
+            // ソート済みでは再配置された連続範囲を、それ以外では書き換えたスロットのみを反映
+            match changes.shifted_range() {
+                Some(range)
+                    if matches!(self.logic_mode(), LogicMode::Sorted | LogicMode::TopK(_)) =>
+                {
+                    let _ = self.commit_vault_range(range).await;
+                }
+                _ => {
+                    let _ = self.commit_vault_slots(changes.touched_slots()).await;
+                }
+            }
+        }
         let (aof_data, mirror_data) = changes.flatten(lane_count);
         if let Some(sender) = aof_sender {
             if !aof_data.is_empty() {
@@ -221,14 +659,31 @@ impl Orby {
         }
     }
 
-    /// 指定した ID を持つデータをその場で更新します。
-    pub async fn update_by_id(&self, index: usize, id: u128, new_data: &[u128]) -> bool {
+    /// 指定した ID を持つデータをその場で更新し、一致する行があったかを返します。
+    /// 一意性のレーン（`unique_lane` / `unique`）の値が他の生存行と重複する場合は
+    /// `OrbyError::DuplicateKey` を返し、メモリ・Vault・AOF のいずれも変更しません。
+    /// 満杯の `LogicMode::TopK` でキーを最下位の行より下位へ変更する場合も、同様に `OrbyError::RankTooLow` を返します。
+    pub async fn update_by_id(
+        &self,
+        index: usize,
+        id: u128,
+        new_data: &[u128],
+    ) -> Result<bool, OrbyError> {
+        if let Some(shards) = &self.shards {
+            return shards.update_by_id(index, id, new_data).await;
+        }
         let (found, aof_sender, mirror_sender, changes, lane_count) = {
             let mut store = self.inner.write();
             let lane_count = store.ring_buffer_lane_count;
             let logic_mode = store.logic_mode;
             let (found, changes) = match logic_mode {
-                LogicMode::RingBuffer => ring::update_by_id(&mut store, index, id, new_data),
+                LogicMode::RingBuffer | LogicMode::Table | LogicMode::StaticSlot => {
+                    ring::check_update(&store, index, id, new_data)?;
+                    ring::update_by_id(&mut store, index, id, new_data)
+                }
+                LogicMode::Sorted => sorted::update_by_id(&mut store, index, id, new_data),
+                LogicMode::TopK(_) => topk::update_by_id(&mut store, index, id, new_data)?,
+                LogicMode::HashSlot => hash::update_by_id(&mut store, index, id, new_data),
             };
             (
                 found,
@@ -238,6 +693,19 @@ impl Orby {
                 lane_count,
             )
         };
+        if self.inner.read().vault_path.is_some() {
+            // ソート済みでは再配置された連続範囲を、それ以外では書き換えたスロットのみを反映
+            match changes.shifted_range() {
+                Some(range)
+                    if matches!(self.logic_mode(), LogicMode::Sorted | LogicMode::TopK(_)) =>
+                {
+                    self.commit_vault_range(range).await?;
+                }
+                _ => {
+                    self.commit_vault_slots(changes.touched_slots()).await?;
+                }
+            }
+        }
         let (aof_data, mirror_data) = changes.flatten(lane_count);
         if let Some(sender) = aof_sender {
             if !aof_data.is_empty() {
@@ -249,7 +717,7 @@ impl Orby {
                 let _ = sender.send(mirror_data).await;
             }
         }
-        found
+        Ok(found)
     }
 
     /// 特定のレーン（次元）に対して、複数のパルスを一括で流し込みます。
@@ -259,10 +727,35 @@ impl Orby {
         lane_idx: usize,
         values: &[u128],
     ) -> Result<(), OrbyError> {
+        self.reject_sharded("insert_lane_batch")?;
         if values.is_empty() {
             return Ok(());
         }
 
+        if matches!(
+            self.logic_mode(),
+            LogicMode::StaticSlot | LogicMode::Sorted | LogicMode::HashSlot | LogicMode::TopK(_)
+        ) {
+            // 固定スロット・ソート済み・ハッシュ配置では連続領域を前提とできないため、行単位で書き込む
+            let lane_count = self.meta().2;
+            if lane_idx >= lane_count {
+                return Err(OrbyError::LaneCountMismatch {
+                    pool_name: self.name(),
+                    expected: lane_count,
+                    found: lane_idx + 1,
+                });
+            }
+            let rows = values
+                .iter()
+                .map(|&val| {
+                    let mut row = vec![0u128; lane_count];
+                    row[lane_idx] = val;
+                    row
+                })
+                .collect();
+            return self.insert_rows(rows).await.map(|_| ());
+        }
+
         let (aof_sender, mirror_sender, has_vault, start_idx, changes, lane_count) = {
             let mut store = self.inner.write();
             let start_idx = store.cursor;
@@ -301,13 +794,24 @@ impl Orby {
     }
 
     /// ID が存在すれば更新、なければ新規挿入します。
+    /// 一意性のレーン（`unique_lane` / `unique`）に違反する場合は `OrbyError::DuplicateKey` を返し、何も書き込みません。
+    /// シャード構成では、対象のシャードの書き込みロックを更新と挿入の間も保持します。
     pub async fn upsert(&self, index: usize, id: u128, data: &[u128]) -> Result<(), OrbyError> {
+        if let Some(shards) = &self.shards {
+            return shards.upsert(index, id, data).await;
+        }
         let (aof_sender, mirror_sender, changes, lane_count) = {
             let mut store = self.inner.write();
             let lane_count = store.ring_buffer_lane_count;
             let logic_mode = store.logic_mode;
             let changes = match logic_mode {
-                LogicMode::RingBuffer => ring::upsert(&mut store, index, id, data)?,
+                LogicMode::RingBuffer | LogicMode::Table => {
+                    ring::upsert(&mut store, index, id, data)?
+                }
+                LogicMode::StaticSlot => slot::upsert(&mut store, index, id, data)?,
+                LogicMode::Sorted => sorted::upsert(&mut store, index, id, data)?,
+                LogicMode::HashSlot => hash::upsert(&mut store, index, id, data)?,
+                LogicMode::TopK(_) => topk::upsert(&mut store, index, id, data)?,
             };
             (
                 store.aof_sender.clone(),
@@ -316,6 +820,23 @@ impl Orby {
                 lane_count,
             )
         };
+
+        // 固定スロットへ新規挿入された行は、スロット番号を維持したまま Vault へ反映
+        // ソート済みで再配置された範囲も同様に反映
+        // ハッシュ配置では書き換えられたスロットのみを反映
+        let has_vault = self.inner.read().vault_path.is_some();
+        if has_vault && self.logic_mode() == LogicMode::HashSlot {
+            self.commit_vault_slots(changes.touched_slots()).await?;
+        } else if has_vault {
+            let inserted = changes.inserted_rows();
+            if !inserted.is_empty() {
+                self.commit_vault_rows(inserted).await?;
+            }
+            if let Some(range) = changes.shifted_range() {
+                self.commit_vault_range(range).await?;
+            }
+        }
+
         let (aof_data, mirror_data) = changes.flatten(lane_count);
         if let Some(sender) = aof_sender {
             if !aof_data.is_empty() {
@@ -331,20 +852,44 @@ impl Orby {
     }
 
     /// 条件に一致する論理インデックスの一覧を返します。
+    /// シャード構成のプールでは、`get_at` と同じく全シャードの生存行を挿入順で統合した順序での位置を返します。
+    /// 統合のために全シャードの生存行を読むため、`limit` によらず全件走査のコストがかかります。
     pub fn find_indices<F>(&self, filter: F, limit: usize) -> Vec<usize>
     where
         F: Fn(&[PulseCell]) -> bool + Sync + Send,
     {
+        if let Some(shards) = &self.shards {
+            return shards.find_indices(filter, limit);
+        }
         let store = self.inner.read();
         match store.logic_mode {
-            LogicMode::RingBuffer => ring::find_indices(&store, filter, limit),
+            LogicMode::RingBuffer
+            | LogicMode::Table
+            | LogicMode::StaticSlot
+            | LogicMode::Sorted
+            | LogicMode::HashSlot
+            | LogicMode::TopK(_) => ring::find_indices(&store, filter, limit),
         }
     }
 
+    /// 論理インデックス（0 が最新）の行を返します。
+    /// シャード構成のプールでは、全シャードの生存行を挿入順（sequence 番号）で統合した順序の
+    /// `logical_index` 番目を返します。墓標は数えず、先頭から読み進めるため `O(logical_index)` のコストがかかります。
     pub fn get_at(&self, logical_index: usize) -> Option<Arc<[u128]>> {
+        if let Some(shards) = &self.shards {
+            return shards
+                .query_raw(|_| true, logical_index.saturating_add(1))
+                .into_iter()
+                .nth(logical_index);
+        }
         let store = self.inner.read();
         match store.logic_mode {
-            LogicMode::RingBuffer => ring::get_at(&store, logical_index),
+            LogicMode::RingBuffer
+            | LogicMode::Table
+            | LogicMode::StaticSlot
+            | LogicMode::Sorted
+            | LogicMode::HashSlot
+            | LogicMode::TopK(_) => ring::get_at(&store, logical_index),
         }
     }
 
@@ -353,18 +898,130 @@ impl Orby {
         self.query_raw(|_| true, limit)
     }
 
+    /// 最古の行から最大 `n` 件を取り除き、古い順に返します（FIFO キュー）。
+    pub async fn pop_oldest(&self, n: usize) -> Result<Vec<Arc<[u128]>>, OrbyError> {
+        self.pop_rows(n, true).await
+    }
+
+    /// 最新の行から最大 `n` 件を取り除き、新しい順に返します（LIFO スタック）。
+    pub async fn pop_newest(&self, n: usize) -> Result<Vec<Arc<[u128]>>, OrbyError> {
+        self.pop_rows(n, false).await
+    }
+
+    /// リングの両端から行を取り除きます（`LogicMode::RingBuffer` / `LogicMode::Table` 専用）。
+    async fn pop_rows(&self, n: usize, oldest: bool) -> Result<Vec<Arc<[u128]>>, OrbyError> {
+        self.reject_sharded("pop_oldest/pop_newest")?;
+        let (rows, aof_sender, mirror_sender, has_vault, changes, lane_count) = {
+            let mut store = self.inner.write();
+            let (rows, changes) = match store.logic_mode {
+                LogicMode::RingBuffer | LogicMode::Table => {
+                    if oldest {
+                        ring::pop_oldest(&mut store, n)
+                    } else {
+                        ring::pop_newest(&mut store, n)
+                    }
+                }
+                LogicMode::StaticSlot
+                | LogicMode::Sorted
+                | LogicMode::HashSlot
+                | LogicMode::TopK(_) => {
+                    return Err(OrbyError::ConfigMismatch {
+                        name: store.name.clone(),
+                        reason: "pop_oldest/pop_newest require LogicMode::RingBuffer or LogicMode::Table"
+                            .into(),
+                    });
+                }
+            };
+            (
+                rows,
+                store.aof_sender.clone(),
+                store.mirror_sender.clone(),
+                store.vault_path.is_some(),
+                changes,
+                store.ring_buffer_lane_count,
+            )
+        };
+
+        if has_vault && !changes.is_empty() {
+            self.commit_vault_slots(changes.touched_slots()).await?;
+        }
+
+        let (aof_data, mirror_data) = changes.flatten(lane_count);
+        if let Some(sender) = aof_sender {
+            if !aof_data.is_empty() {
+                let _ = sender.send(aof_data).await;
+            }
+        }
+        if let Some(sender) = mirror_sender {
+            if !mirror_data.is_empty() {
+                let _ = sender.send(mirror_data).await;
+            }
+        }
+        Ok(rows)
+    }
+
+    /// 保持期間（`retention`）を過ぎた行を最古の行から取り除き、tail を前進させます。
+    /// 取り除いた行数を返します。保持期間が設定されていない場合は何もしません。
+    pub async fn purge_expired(&self) -> Result<usize, OrbyError> {
+        if let Some(shards) = &self.shards {
+            return shards.purge_expired().await;
+        }
+        let (count, aof_sender, mirror_sender, has_vault, changes, lane_count) = {
+            let mut store = self.inner.write();
+            let Some(cutoff) = ring::expiry_cutoff(&store) else {
+                return Ok(0);
+            };
+            let (count, changes) = ring::purge_expired(&mut store, cutoff);
+            (
+                count,
+                store.aof_sender.clone(),
+                store.mirror_sender.clone(),
+                store.vault_path.is_some(),
+                changes,
+                store.ring_buffer_lane_count,
+            )
+        };
+
+        // 取り除いたスロットと前進した tail を Vault へ反映
+        if has_vault && !changes.is_empty() {
+            self.commit_vault_slots(changes.touched_slots()).await?;
+        }
+
+        let (aof_data, mirror_data) = changes.flatten(lane_count);
+        if let Some(sender) = aof_sender {
+            if !aof_data.is_empty() {
+                let _ = sender.send(aof_data).await;
+            }
+        }
+        if let Some(sender) = mirror_sender {
+            if !mirror_data.is_empty() {
+                let _ = sender.send(mirror_data).await;
+            }
+        }
+        Ok(count)
+    }
+
     /// ストアの内容をすべて破棄し、指定された新しいデータでメモリをリセットします。
     pub async fn purge_all_data<T>(&self, rows: Vec<T>) -> Result<(), OrbyError>
     where
         T: AsRef<[u128]>,
     {
+        if let Some(shards) = &self.shards {
+            let rows = rows.iter().map(|row| row.as_ref().to_vec()).collect();
+            return shards.purge_all_data(rows).await;
+        }
         let (aof_sender, mirror_sender, changes, lane_count) = {
             let mut store = self.inner.write();
             let lane_count = store.ring_buffer_lane_count;
             let logic_mode = store.logic_mode;
 
             let changes = match logic_mode {
-                LogicMode::RingBuffer => ring::truncate(&mut store, rows.into_iter())?,
+                LogicMode::RingBuffer | LogicMode::Table | LogicMode::StaticSlot => {
+                    ring::truncate(&mut store, rows.into_iter())?
+                }
+                LogicMode::Sorted => sorted::truncate(&mut store, rows.into_iter())?,
+                LogicMode::HashSlot => hash::truncate(&mut store, rows.into_iter())?,
+                LogicMode::TopK(_) => topk::truncate(&mut store, rows.into_iter())?,
             };
 
             (
@@ -390,4 +1047,15 @@ impl Orby {
 
         Ok(())
     }
+
+    /// 物理位置に依存する操作はシャード構成では使用できないため、`ConfigMismatch` を返します。
+    pub(crate) fn reject_sharded(&self, operation: &str) -> Result<(), OrbyError> {
+        if self.shards.is_some() {
+            return Err(OrbyError::ConfigMismatch {
+                name: self.name(),
+                reason: format!("{} is not available on a sharded pool", operation),
+            });
+        }
+        Ok(())
+    }
 }
@@ -20,6 +20,8 @@ pub struct OrbyIterator<'a, F> {
     pub(crate) cursor: usize,
     pub(crate) cap: usize,
     pub(crate) len: usize,
+    /// 保持期間に基づく期限の境界（ミリ秒）。イテレータ生成時点で固定されます。
+    pub(crate) cutoff: Option<u64>,
     pub(crate) file: Option<std::fs::File>,
 }
 
@@ -37,7 +39,12 @@ where
             self.current_idx += 1;
 
             let physical_idx = match self.logic_mode {
-                LogicMode::RingBuffer => {
+                LogicMode::RingBuffer
+                | LogicMode::Table
+                | LogicMode::StaticSlot
+                | LogicMode::Sorted
+                | LogicMode::HashSlot
+                | LogicMode::TopK(_) => {
                     if self.cursor > i {
                         self.cursor - 1 - i
                     } else {
@@ -46,6 +53,11 @@ where
                 }
             };
 
+            // 墓標（有効ビットなし）はスキップ
+            if !self.store.valid.get(physical_idx) {
+                continue;
+            }
+
             let row_data_vec: Vec<PulseCell> = if let Some(ref mut f) = self.file {
                 use std::io::{Read, Seek, SeekFrom};
                 // strideは廃止、ring_buffer_lane_countを使用
@@ -73,9 +85,8 @@ where
             };
 
             let row_data = &row_data_vec;
-
-            // 全てが 0 の行はスキップ
-            if row_data.iter().all(|v| v.as_u128() == 0) {
+            if crate::logic::ring::is_expired(row_data[self.store.key_lane].as_u128(), self.cutoff)
+            {
                 continue;
             }
 
@@ -1,6 +1,7 @@
 pub mod api;
 pub mod iter;
 pub mod persistence;
+pub(crate) mod shard;
 
 pub use iter::OrbyIterator;
 
@@ -9,16 +10,121 @@ mod tests;
 
 use crate::builder::OrbyBuilder;
 use crate::error::OrbyError;
+use crate::logic::unique::DuplicatePolicy;
 use crate::logic::OrbyRingBufferSilo;
 use crate::types::{LogicMode, SaveMode};
 use parking_lot::RwLock;
 use std::sync::Arc;
 use sysinfo::System;
 
+/// Per-lane constraints and lookup structures requested through the builder.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LaneOptions {
+    pub(crate) unique_lanes: Vec<(usize, DuplicatePolicy)>,
+    pub(crate) index_lanes: Vec<usize>,
+    pub(crate) bloom_lane: Option<usize>,
+    pub(crate) sketch_lanes: Vec<usize>,
+    pub(crate) bitmap_lanes: Vec<usize>,
+    pub(crate) composite_indexes: Vec<Vec<usize>>,
+    pub(crate) mask_indexes: Vec<(usize, u128)>,
+}
+
+impl LaneOptions {
+    /// Declares `lane` unique with `policy`, replacing the policy of an earlier declaration of the same lane.
+    pub(crate) fn set_unique(&mut self, lane: usize, policy: DuplicatePolicy) {
+        match self
+            .unique_lanes
+            .iter_mut()
+            .find(|(declared, _)| *declared == lane)
+        {
+            Some(entry) => entry.1 = policy,
+            None => self.unique_lanes.push((lane, policy)),
+        }
+    }
+
+    /// Lists every declared lane together with the builder option that declared it.
+    fn declared(&self) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+        let unique = self
+            .unique_lanes
+            .iter()
+            .map(|&(lane, policy)| match policy {
+                DuplicatePolicy::Skip => ("unique_lane", lane),
+                DuplicatePolicy::Reject => ("unique", lane),
+            });
+        let bloom = self.bloom_lane.map(|lane| ("bloom_lane", lane));
+        unique
+            .chain(self.index_lanes.iter().map(|&lane| ("index_lane", lane)))
+            .chain(bloom)
+            .chain(
+                self.sketch_lanes
+                    .iter()
+                    .map(|&lane| ("approx_distinct_lane", lane)),
+            )
+            .chain(self.bitmap_lanes.iter().map(|&lane| ("bitmap_lane", lane)))
+            .chain(
+                self.composite_indexes
+                    .iter()
+                    .flatten()
+                    .map(|&lane| ("composite_index", lane)),
+            )
+            .chain(
+                self.mask_indexes
+                    .iter()
+                    .map(|&(lane, _)| ("mask_index", lane)),
+            )
+    }
+
+    /// Checks that every declared lane exists and that the logic mode supports it.
+    /// All of these options are maintained by `logic::ring`, so only
+    /// `LogicMode::RingBuffer` and `LogicMode::Table` accept them.
+    fn validate(
+        &self,
+        name: &str,
+        lane_count: usize,
+        logic_mode: LogicMode,
+    ) -> Result<(), OrbyError> {
+        if self.composite_indexes.iter().any(|lanes| lanes.is_empty()) {
+            return Err(OrbyError::ConfigMismatch {
+                name: name.to_string(),
+                reason: "composite_index requires at least one lane".into(),
+            });
+        }
+        if self.mask_indexes.iter().any(|&(_, mask)| mask == 0) {
+            return Err(OrbyError::ConfigMismatch {
+                name: name.to_string(),
+                reason: "mask_index requires a non-zero mask".into(),
+            });
+        }
+        for (option, lane) in self.declared() {
+            let reason = if lane >= lane_count {
+                format!(
+                    "{} {} is out of range for {} lanes",
+                    option, lane, lane_count
+                )
+            } else if !matches!(logic_mode, LogicMode::RingBuffer | LogicMode::Table) {
+                format!(
+                    "{} requires LogicMode::RingBuffer or LogicMode::Table, but got {:?}",
+                    option, logic_mode
+                )
+            } else {
+                continue;
+            };
+            return Err(OrbyError::ConfigMismatch {
+                name: name.to_string(),
+                reason,
+            });
+        }
+        Ok(())
+    }
+}
+
 /// High-performance in-memory index engine specialized for 128-bit fixed-length data scanning.
 #[derive(Clone)]
 pub struct Orby {
     pub(crate) inner: Arc<RwLock<OrbyRingBufferSilo>>,
+    /// Independent shards behind this handle (`shards(n)` with `n > 1`).
+    /// When set, `inner` only carries the configuration and holds no rows.
+    pub(crate) shards: Option<Arc<shard::ShardSet>>,
 }
 
 impl Orby {
@@ -72,19 +178,47 @@ impl Orby {
     /// Returns: (current_length, capacity, lane_count)
     pub fn meta(&self) -> (usize, usize, usize) {
         let store = self.inner.read();
+        if let Some(shards) = &self.shards {
+            let (len, capacity) = shards.totals();
+            return (len, capacity, store.ring_buffer_lane_count);
+        }
         (store.len, store.capacity, store.ring_buffer_lane_count)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub(crate) async fn try_new(
         name: &str,
         capacity: usize,
         ring_buffer_lane_count: usize,
         storage_mode: SaveMode,
         logic_mode: LogicMode,
+        key_lane: usize,
+        retention: Option<std::time::Duration>,
+        lane_options: &LaneOptions,
         compaction: bool,
         aof_enabled: bool,
         capacity_usage_ratio: f64,
     ) -> Result<Self, OrbyError> {
+        if key_lane >= ring_buffer_lane_count {
+            return Err(OrbyError::ConfigMismatch {
+                name: name.to_string(),
+                reason: format!(
+                    "key_lane {} is out of range for {} lanes",
+                    key_lane, ring_buffer_lane_count
+                ),
+            });
+        }
+        lane_options.validate(name, ring_buffer_lane_count, logic_mode)?;
+        if retention.is_some() && !matches!(logic_mode, LogicMode::RingBuffer | LogicMode::Table) {
+            return Err(OrbyError::ConfigMismatch {
+                name: name.to_string(),
+                reason: format!(
+                    "retention requires LogicMode::RingBuffer or LogicMode::Table, but got {:?}",
+                    logic_mode
+                ),
+            });
+        }
+
         let row_bytes = ring_buffer_lane_count * 16;
         let required_bytes = capacity as u64 * row_bytes as u64;
 
@@ -143,60 +277,141 @@ impl Orby {
                 name: name.to_string(),
                 lanes,
                 cursor: 0,
+                tail: 0,
                 len: 0,
                 capacity,
                 ring_buffer_lane_count,
                 compaction,
                 logic_mode,
+                key_lane,
+                retention,
+                constraints: lane_options
+                    .unique_lanes
+                    .iter()
+                    .map(|&(lane, policy)| crate::logic::unique::UniqueKeys::new(lane, policy))
+                    .collect(),
+                indexes: lane_options
+                    .index_lanes
+                    .iter()
+                    .map(|&lane| crate::logic::index::LaneIndex::new(lane))
+                    .chain(
+                        lane_options.mask_indexes.iter().map(|&(lane, mask)| {
+                            crate::logic::index::LaneIndex::masked(lane, mask)
+                        }),
+                    )
+                    .collect(),
+                zones: crate::logic::zone::ZoneMap::new(ring_buffer_lane_count, capacity),
+                bloom: lane_options
+                    .bloom_lane
+                    .map(|lane| crate::logic::bloom::BlockBloom::new(lane, capacity)),
+                sketches: lane_options
+                    .sketch_lanes
+                    .iter()
+                    .map(|&lane| crate::logic::sketch::BlockSketch::new(lane, capacity))
+                    .collect(),
+                bitmaps: lane_options
+                    .bitmap_lanes
+                    .iter()
+                    .map(|&lane| crate::logic::bitmap_index::BitmapIndex::new(lane, capacity))
+                    .collect(),
+                composites: lane_options
+                    .composite_indexes
+                    .iter()
+                    .map(|lanes| crate::logic::composite::CompositeIndex::new(lanes.clone()))
+                    .collect(),
                 storage_mode,
+                max_groups: crate::types::DEFAULT_MAX_GROUPS,
+                free_slots: Default::default(),
+                valid: crate::logic::bitmap::SlotBitmap::new(capacity),
                 aof_sender,
                 mirror_sender,
                 mirror_path: mirror_path_buf,
                 vault_path: vault_path_buf,
+                index_files_current: std::sync::atomic::AtomicBool::new(false),
             })),
+            shards: None,
         })
     }
 
+    /// Returns the logic mode of the ring buffer.
+    pub fn logic_mode(&self) -> LogicMode {
+        self.inner.read().logic_mode
+    }
     /// Returns the name of the ring buffer.
     pub fn name(&self) -> String {
         self.inner.read().name.clone()
     }
     /// Returns the current number of elements in the ring buffer.
     pub fn len(&self) -> usize {
+        if let Some(shards) = &self.shards {
+            return shards.totals().0;
+        }
         self.inner.read().len
     }
-    /// Returns the count of active cells (non-zero entries) in the primary lane.
+    /// Returns the count of live rows tracked by the validity bitmap.
     pub fn count_active(&self) -> usize {
+        if let Some(shards) = &self.shards {
+            return shards.count_active();
+        }
         let store = self.inner.read();
         crate::logic::ring::count_active(&store)
     }
     /// Returns true if the ring buffer is empty.
     pub fn is_empty(&self) -> bool {
-        self.inner.read().len == 0
+        self.len() == 0
     }
     /// Deletes the data at the specified index.
     /// Returns true if the deletion was successful.
-    pub async fn delete(&self, index: usize) -> bool {
-        let (res, has_vault, compaction) = {
+    /// Physical indices are per shard, so a sharded handle fails with `OrbyError::ConfigMismatch`.
+    pub async fn delete(&self, index: usize) -> Result<bool, OrbyError> {
+        self.reject_sharded("delete")?;
+        let (res, has_vault, shifted, touched) = {
             let mut store = self.inner.write();
-            let (res, changes) = crate::logic::ring::delete(&mut *store, index);
+            let (res, changes) = match store.logic_mode {
+                LogicMode::RingBuffer | LogicMode::Table => {
+                    crate::logic::ring::delete(&mut store, index)
+                }
+                LogicMode::StaticSlot => crate::logic::slot::delete(&mut store, index),
+                LogicMode::Sorted | LogicMode::TopK(_) => {
+                    crate::logic::sorted::delete(&mut store, index)
+                }
+                LogicMode::HashSlot => crate::logic::hash::delete(&mut store, index),
+            };
             let has_vault = store.vault_path.is_some();
-            let compaction = store.compaction;
-            self.dispatch_persistence(&mut *store, changes);
-            (res, has_vault, compaction)
+            // 固定スロット・ハッシュ配置ではスロット番号を維持するため、コンパクションは行わない
+            let compaction = store.compaction
+                && !matches!(
+                    store.logic_mode,
+                    LogicMode::StaticSlot | LogicMode::HashSlot
+                );
+            let touched = if store.logic_mode == LogicMode::HashSlot
+                || (compaction
+                    && matches!(store.logic_mode, LogicMode::RingBuffer | LogicMode::Table))
+            {
+                changes.touched_slots()
+            } else {
+                Vec::new()
+            };
+            let shifted = changes.shifted_range();
+            self.dispatch_persistence(&mut store, changes);
+            (res, has_vault, shifted, touched)
         };
 
         if res && has_vault {
-            if compaction {
-                let _ = self.vault_delete_and_compact(index).await;
+            if !touched.is_empty() {
+                // 後方シフト削除やコンパクションで再配置されたスロットのみを書き戻す
+                self.commit_vault_slots(touched).await?;
+            } else if let Some(range) = shifted {
+                // キー順序を保つために再配置された範囲のみを書き戻す
+                self.commit_vault_range(range).await?;
             } else {
                 // Sparse delete: zero out entry in Vault
                 let dim = self.meta().2;
                 let zeros = vec![vec![0u128; dim]];
-                let _ = self.commit_vault_batch(index, zeros).await;
+                self.commit_vault_batch(index, zeros).await?;
             }
         }
-        res
+        Ok(res)
     }
 
     fn dispatch_persistence(
@@ -82,7 +82,7 @@ impl Orby {
                         new_data.push(val);
                         pos += 16;
                     }
-                    self.update_by_id(index, id, &new_data).await;
+                    self.update_by_id(index, id, &new_data).await?;
                 }
                 crate::logic::AOF_OP_TRUNCATE => {
                     self.purge_all_data(Vec::<[u128; 1]>::new()).await?;
@@ -102,6 +102,34 @@ impl Orby {
                     }
                     self.insert_lane_batch(lane_idx, &values).await?;
                 }
+                crate::logic::AOF_OP_INSERT_AT => {
+                    let slot =
+                        u64::from_le_bytes(buffer[pos..pos + 8].try_into().unwrap()) as usize;
+                    pos += 8;
+                    let mut row = Vec::with_capacity(dim);
+                    for _ in 0..dim {
+                        let val = u128::from_le_bytes(buffer[pos..pos + 16].try_into().unwrap());
+                        row.push(val);
+                        pos += 16;
+                    }
+                    self.insert_at(slot, &row).await?;
+                }
+                crate::logic::AOF_OP_DELETE => {
+                    let index =
+                        u64::from_le_bytes(buffer[pos..pos + 8].try_into().unwrap()) as usize;
+                    pos += 8;
+                    self.delete(index).await?;
+                }
+                crate::logic::AOF_OP_POP_OLDEST | crate::logic::AOF_OP_POP_NEWEST => {
+                    let count =
+                        u64::from_le_bytes(buffer[pos..pos + 8].try_into().unwrap()) as usize;
+                    pos += 8;
+                    if op == crate::logic::AOF_OP_POP_OLDEST {
+                        self.pop_oldest(count).await?;
+                    } else {
+                        self.pop_newest(count).await?;
+                    }
+                }
                 _ => break,
             }
         }
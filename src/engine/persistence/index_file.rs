@@ -0,0 +1,337 @@
+use crate::error::OrbyError;
+use crate::logic::bitmap::SlotBitmap;
+use crate::logic::bitmap_index::BitmapIndex;
+use crate::logic::composite::CompositeIndex;
+use crate::logic::index::LaneIndex;
+use crate::logic::{bitmap_index, composite, index, OrbyRingBufferSilo};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::Path;
+
+/// 索引ファイルの先頭に置くマジックナンバー。
+const INDEX_MAGIC: &[u8; 8] = b"ORBYIDX1";
+
+/// 索引ファイルのファイル名の接頭辞。`init_vault` で古い Vault の索引を取り除くために使用します。
+const INDEX_FILE_PREFIXES: [&str; 3] = ["index_", "composite_", "bitmap_"];
+
+/// `index_lane` の索引は `index_<lane>.bin`、`mask_index` の索引は `index_<lane>_<mask>.bin` に保存します。
+fn lane_index_file(index: &LaneIndex) -> String {
+    if index.is_full() {
+        format!("index_{}.bin", index.lane)
+    } else {
+        format!("index_{}_{:032x}.bin", index.lane, index.mask)
+    }
+}
+
+fn composite_file(index: &CompositeIndex) -> String {
+    let lanes: Vec<String> = index.lanes.iter().map(|lane| lane.to_string()).collect();
+    format!("composite_{}.bin", lanes.join("_"))
+}
+
+fn bitmap_file(index: &BitmapIndex) -> String {
+    format!("bitmap_{}.bin", index.lane)
+}
+
+/// 索引ファイルの共通の先頭部分を書き込みます。
+/// 手順: マジックナンバー -> 世代番号 -> 容量 -> エントリ数（書き込み後に埋める）
+fn preamble(generation: u64, capacity: usize) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(4096);
+    buf.extend_from_slice(INDEX_MAGIC);
+    buf.extend_from_slice(&generation.to_le_bytes());
+    buf.extend_from_slice(&(capacity as u64).to_le_bytes());
+    buf.extend_from_slice(&0u64.to_le_bytes());
+    buf
+}
+
+fn set_entry_count(buf: &mut [u8], count: usize) {
+    buf[24..32].copy_from_slice(&(count as u64).to_le_bytes());
+}
+
+fn push_slots(buf: &mut Vec<u8>, slots: &[usize]) {
+    buf.extend_from_slice(&(slots.len() as u64).to_le_bytes());
+    for &slot in slots {
+        buf.extend_from_slice(&(slot as u64).to_le_bytes());
+    }
+}
+
+/// 一時ファイルへ書き込んで fsync した後に置き換え、書き込み途中のファイルが残らないようにします。
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), OrbyError> {
+    let tmp = path.with_extension("tmp");
+    {
+        let mut f = std::fs::File::create(&tmp)?;
+        f.write_all(bytes)?;
+        f.sync_all()?;
+    }
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// 全ての検索用の索引を、Vault の世代番号とともに索引ファイルへ書き出します。
+pub(crate) fn save_indexes(
+    vault_path: &Path,
+    store: &OrbyRingBufferSilo,
+    generation: u64,
+) -> Result<(), OrbyError> {
+    let capacity = store.capacity;
+    for index in &store.indexes {
+        let mut buf = preamble(generation, capacity);
+        let mut count = 0;
+        for (key, slots) in index.entries() {
+            buf.extend_from_slice(&key.to_le_bytes());
+            push_slots(&mut buf, slots);
+            count += 1;
+        }
+        set_entry_count(&mut buf, count);
+        write_atomic(&vault_path.join(lane_index_file(index)), &buf)?;
+    }
+    for index in &store.composites {
+        let mut buf = preamble(generation, capacity);
+        let mut count = 0;
+        for (key, slots) in index.entries() {
+            for value in key {
+                buf.extend_from_slice(&value.to_le_bytes());
+            }
+            push_slots(&mut buf, slots);
+            count += 1;
+        }
+        set_entry_count(&mut buf, count);
+        write_atomic(&vault_path.join(composite_file(index)), &buf)?;
+    }
+    for index in &store.bitmaps {
+        if index.is_overflowed() {
+            // 上限を超えて無効になった索引は保存せず、ロード時の再構築に任せる
+            let _ = std::fs::remove_file(vault_path.join(bitmap_file(index)));
+            continue;
+        }
+        let mut buf = preamble(generation, capacity);
+        let mut count = 0;
+        for (key, bits) in index.entries() {
+            buf.extend_from_slice(&key.to_le_bytes());
+            buf.extend_from_slice(&bits.to_bytes());
+            count += 1;
+        }
+        set_entry_count(&mut buf, count);
+        write_atomic(&vault_path.join(bitmap_file(index)), &buf)?;
+    }
+    Ok(())
+}
+
+/// Vault ディレクトリ内の索引ファイルを全て削除します。
+pub(crate) fn remove_index_files(vault_path: &Path) -> Result<(), OrbyError> {
+    for entry in std::fs::read_dir(vault_path)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.ends_with(".bin")
+            && INDEX_FILE_PREFIXES
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+        {
+            std::fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// 索引ファイルを先頭から読み進めるためのカーソル。
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(len)?;
+        let chunk = self.bytes.get(self.pos..end)?;
+        self.pos = end;
+        Some(chunk)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn u128(&mut self) -> Option<u128> {
+        Some(u128::from_le_bytes(self.take(16)?.try_into().ok()?))
+    }
+
+    fn is_done(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+}
+
+/// 索引ファイルを読み込み、先頭部分（マジックナンバー・世代番号・容量）を検証します。
+/// 一致しない、またはファイルが読めない場合は `None` を返します。
+fn read_index(path: &Path, generation: u64, capacity: usize) -> Option<Vec<u8>> {
+    let bytes = std::fs::read(path).ok()?;
+    let mut reader = Reader {
+        bytes: &bytes,
+        pos: 0,
+    };
+    let current = reader.take(8)? == INDEX_MAGIC
+        && reader.u64()? == generation
+        && reader.u64()? == capacity as u64;
+    current.then_some(bytes)
+}
+
+/// 先頭部分の直後から本体を読み進めるカーソルと、エントリ数を返します。
+fn body(bytes: &[u8]) -> Option<(usize, Reader<'_>)> {
+    let mut reader = Reader { bytes, pos: 24 };
+    let count = reader.u64()? as usize;
+    Some((count, reader))
+}
+
+/// 物理スロットの一覧を読み込みます。生存行でないスロットを含む場合は `None` を返します。
+fn read_slots(reader: &mut Reader<'_>, store: &OrbyRingBufferSilo) -> Option<Vec<usize>> {
+    let len = reader.u64()? as usize;
+    let mut slots = Vec::with_capacity(len.min(store.capacity));
+    for _ in 0..len {
+        let slot = reader.u64()? as usize;
+        if !store.valid.get(slot) {
+            return None;
+        }
+        slots.push(slot);
+    }
+    Some(slots)
+}
+
+fn load_lane_index(
+    path: &Path,
+    store: &OrbyRingBufferSilo,
+    generation: u64,
+    live: usize,
+) -> Option<HashMap<u128, Vec<usize>>> {
+    let bytes = read_index(path, generation, store.capacity)?;
+    let (count, mut reader) = body(&bytes)?;
+    let mut slots = HashMap::with_capacity(count.min(store.capacity));
+    let mut total = 0;
+    for _ in 0..count {
+        let key = reader.u128()?;
+        let found = read_slots(&mut reader, store)?;
+        total += found.len();
+        slots.insert(key, found);
+    }
+    (reader.is_done() && total == live).then_some(slots)
+}
+
+fn load_composite(
+    path: &Path,
+    store: &OrbyRingBufferSilo,
+    generation: u64,
+    live: usize,
+    width: usize,
+) -> Option<BTreeMap<Vec<u128>, Vec<usize>>> {
+    let bytes = read_index(path, generation, store.capacity)?;
+    let (count, mut reader) = body(&bytes)?;
+    let mut slots = BTreeMap::new();
+    let mut total = 0;
+    for _ in 0..count {
+        let key = (0..width)
+            .map(|_| reader.u128())
+            .collect::<Option<Vec<u128>>>()?;
+        let found = read_slots(&mut reader, store)?;
+        total += found.len();
+        slots.insert(key, found);
+    }
+    (reader.is_done() && total == live).then_some(slots)
+}
+
+fn load_bitmap(
+    path: &Path,
+    store: &OrbyRingBufferSilo,
+    generation: u64,
+    live: usize,
+) -> Option<HashMap<u128, SlotBitmap>> {
+    let bytes = read_index(path, generation, store.capacity)?;
+    let (count, mut reader) = body(&bytes)?;
+    let bitmap_len = SlotBitmap::byte_len(store.capacity);
+    let mut values = HashMap::with_capacity(count.min(store.capacity));
+    let mut total = 0;
+    for _ in 0..count {
+        let key = reader.u128()?;
+        let bits = SlotBitmap::from_bytes(store.capacity, reader.take(bitmap_len)?)?;
+        total += bits.count_ones();
+        values.insert(key, bits);
+    }
+    (reader.is_done() && total == live).then_some(values)
+}
+
+/// 索引ファイルとして保存する索引が一つでも設定されているかを返します。
+pub(crate) fn has_persisted_indexes(store: &OrbyRingBufferSilo) -> bool {
+    !store.indexes.is_empty() || !store.composites.is_empty() || !store.bitmaps.is_empty()
+}
+
+/// Vault の索引ファイルから検索用の索引を復元します。
+/// 世代番号が Vault のヘッダと一致しない（索引の保存後にレーンが更新された）ファイルや、
+/// 内容が生存行と食い違うファイルは削除し、その種類の索引を生存行から作り直します。
+pub(crate) fn restore_indexes(vault_path: &Path, store: &mut OrbyRingBufferSilo, generation: u64) {
+    // 採用できるファイルがあったかに関わらず、最初のコミットでは世代番号を進めて残ったファイルを無効にする
+    *store.index_files_current.get_mut() = has_persisted_indexes(store);
+    if store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
+        index::rebuild(store);
+        composite::rebuild(store);
+        bitmap_index::rebuild(store);
+        return;
+    }
+    if !has_persisted_indexes(store) {
+        // 索引のないプールでは世代番号を進めないため、以前の構成で保存された索引ファイルを残さない
+        let _ = remove_index_files(vault_path);
+        return;
+    }
+    let live = store.valid.count_ones();
+    let discard = |path: &Path| {
+        // 残っていても世代番号で古いと判定されるため、削除の失敗は無視する
+        let _ = std::fs::remove_file(path);
+    };
+
+    let mut indexes = std::mem::take(&mut store.indexes);
+    let mut intact = true;
+    for idx in &mut indexes {
+        let path = vault_path.join(lane_index_file(idx));
+        match load_lane_index(&path, store, generation, live) {
+            Some(slots) => idx.restore(slots),
+            None => {
+                discard(&path);
+                intact = false;
+            }
+        }
+    }
+    store.indexes = indexes;
+    if !intact {
+        index::rebuild(store);
+    }
+
+    let mut composites = std::mem::take(&mut store.composites);
+    let mut intact = true;
+    for idx in &mut composites {
+        let path = vault_path.join(composite_file(idx));
+        match load_composite(&path, store, generation, live, idx.lanes.len()) {
+            Some(slots) => idx.restore(slots),
+            None => {
+                discard(&path);
+                intact = false;
+            }
+        }
+    }
+    store.composites = composites;
+    if !intact {
+        composite::rebuild(store);
+    }
+
+    let mut bitmaps = std::mem::take(&mut store.bitmaps);
+    let mut intact = true;
+    for idx in &mut bitmaps {
+        let path = vault_path.join(bitmap_file(idx));
+        match load_bitmap(&path, store, generation, live) {
+            Some(values) => idx.restore(values),
+            None => {
+                discard(&path);
+                intact = false;
+            }
+        }
+    }
+    store.bitmaps = bitmaps;
+    if !intact {
+        bitmap_index::rebuild(store);
+    }
+}
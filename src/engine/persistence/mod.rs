@@ -1,4 +1,5 @@
 pub mod aof;
+pub(crate) mod index_file;
 pub mod vault;
 
 #[cfg(test)]
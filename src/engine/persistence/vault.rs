@@ -1,8 +1,16 @@
+use crate::engine::persistence::index_file;
 use crate::engine::Orby;
 use crate::error::OrbyError;
-use crate::types::STORAGE_MAGIC_V1;
-use std::fs::{File, OpenOptions};
+use crate::logic::bitmap::SlotBitmap;
+use crate::logic::OrbyRingBufferSilo;
+use crate::types::{LogicMode, STORAGE_MAGIC_V1};
+use std::fs::File;
 use std::os::unix::fs::FileExt;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+
+/// 有効ビットマップを保存するファイル名。
+const VALIDITY_FILE: &str = "valid.bin";
 
 /// `Orby Vault System` は、多次元配列（Parallel Arrays）の各次元を
 /// 独立したバイナリファイルとして管理するストレージエンジンです。
@@ -11,13 +19,19 @@ pub struct OrbyVault;
 impl Orby {
     /// 指定されたパスを Vault ディレクトリとして初期化します。
     pub(crate) async fn init_vault(&self) -> Result<(), OrbyError> {
-        let (vault_path, capacity, ring_buffer_lane_count) = {
+        let (vault_path, capacity, ring_buffer_lane_count, logic_mode, key_lane) = {
             let store = self.inner.read();
             let p = store
                 .vault_path
                 .clone()
                 .ok_or_else(|| OrbyError::Custom("Vault path is not set".into()))?;
-            (p, store.capacity, store.ring_buffer_lane_count)
+            (
+                p,
+                store.capacity,
+                store.ring_buffer_lane_count,
+                store.logic_mode,
+                store.key_lane,
+            )
         };
 
         if !vault_path.exists() {
@@ -46,6 +60,7 @@ impl Orby {
                         ),
                         });
                     }
+                    verify_logic_header(&self.name(), logic_mode, key_lane, &header_data)?;
                 }
             }
         }
@@ -85,6 +100,20 @@ impl Orby {
             }
         }
 
+        // 有効ビットマップ（全スロット墓標）で初期化
+        {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(vault_path.join(VALIDITY_FILE))?;
+            file.write_all_at(&vec![0u8; SlotBitmap::byte_len(capacity)], 0)?;
+            file.sync_all()?;
+        }
+
+        // 以前の Vault の索引ファイルは新しいレーンと対応しないため取り除く
+        index_file::remove_index_files(&vault_path)?;
+
         // 2. Initialize Header File
         let header_path = vault_path.join("header.bin");
         let mut header_file = tokio::fs::OpenOptions::new()
@@ -100,6 +129,10 @@ impl Orby {
         header_data[24..32].copy_from_slice(&(0u64).to_le_bytes()); // len
         header_data[32..40].copy_from_slice(&(0u64).to_le_bytes()); // cursor
         header_data[40..44].copy_from_slice(&(ring_buffer_lane_count as u32).to_le_bytes()); // lane_count
+        header_data[44] = logic_mode.as_u8(); // logic_mode
+        header_data[48..52].copy_from_slice(&(key_lane as u32).to_le_bytes()); // key_lane
+        header_data[56..64].copy_from_slice(&(0u64).to_le_bytes()); // tail
+        header_data[64..72].copy_from_slice(&(0u64).to_le_bytes()); // generation
 
         use tokio::io::AsyncWriteExt;
         header_file.write_all(&header_data).await?;
@@ -134,7 +167,9 @@ impl Orby {
         let v_cap = u64::from_le_bytes(header_data[16..24].try_into().unwrap()) as usize;
         let v_len = u64::from_le_bytes(header_data[24..32].try_into().unwrap()) as usize;
         let v_cursor = u64::from_le_bytes(header_data[32..40].try_into().unwrap()) as usize;
+        let v_tail = u64::from_le_bytes(header_data[56..64].try_into().unwrap()) as usize;
         let v_dim = u32::from_le_bytes(header_data[40..44].try_into().unwrap()) as usize;
+        let v_generation = u64::from_le_bytes(header_data[64..72].try_into().unwrap());
 
         {
             let mut store = self.inner.write();
@@ -145,6 +180,7 @@ impl Orby {
                     found: v_dim,
                 });
             }
+            verify_logic_header(&store.name, store.logic_mode, store.key_lane, &header_data)?;
 
             // Size validation for lanes
             for i in 0..v_dim {
@@ -162,6 +198,12 @@ impl Orby {
 
             store.len = v_len;
             store.cursor = v_cursor;
+            // 満杯のリングでは最古の行は常にカーソル位置にある（tail 未記録の旧ヘッダにも対応）
+            store.tail = if store.logic_mode == LogicMode::RingBuffer && v_len == v_cap {
+                v_cursor
+            } else {
+                v_tail
+            };
 
             // Load to memory
             if !store.lanes.is_empty() && !store.lanes[0].buffer.is_empty() {
@@ -180,6 +222,14 @@ impl Orby {
                     }
                 }
             }
+
+            load_validity(&vault_path, &mut store, false)?;
+            crate::logic::rebuild_summaries(&mut store);
+            index_file::restore_indexes(&vault_path, &mut store, v_generation);
+
+            if store.logic_mode == LogicMode::StaticSlot {
+                crate::logic::slot::rebuild_free_slots(&mut store);
+            }
         }
 
         Ok(())
@@ -188,8 +238,12 @@ impl Orby {
     /// Writes all in-memory data to disk and synchronizes completely.
     /// This is a maintenance command to prevent data volatility.
     pub async fn sleep(&self) -> Result<(), OrbyError> {
+        if let Some(shards) = &self.shards {
+            return shards.sleep().await;
+        }
         let inner = self.inner.clone();
 
+        self.invalidate_vault_indexes().await?;
         tokio::task::spawn_blocking(move || {
             let store = inner.read();
             let vault_path = store
@@ -230,14 +284,45 @@ impl Orby {
         .await
         .map_err(|e| OrbyError::Custom(format!("Blocking task join error: {}", e)))??;
 
+        self.commit_vault_validity(None).await?;
         // Header Update
         self.commit_vault_header().await?;
+        self.commit_vault_indexes().await?;
+
+        Ok(())
+    }
+
+    /// 検索用の索引を、現在のヘッダの世代番号とともに索引ファイルへ書き出します。
+    /// 以降のコミットで世代番号が進むと、書き出した索引ファイルはロード時に古いものとして破棄されます。
+    async fn commit_vault_indexes(&self) -> Result<(), OrbyError> {
+        let inner = self.inner.clone();
+
+        tokio::task::spawn_blocking(move || {
+            // 世代番号の読み取りから書き出しまで読み取りロックを保持し、その間のメモリ更新を防ぐ
+            let store = inner.read();
+            if !index_file::has_persisted_indexes(&store) {
+                return Ok(());
+            }
+            let vault_path = store
+                .vault_path
+                .as_ref()
+                .ok_or_else(|| OrbyError::Custom("Vault path is not set".into()))?;
+
+            let mut generation = [0u8; 8];
+            File::open(vault_path.join("header.bin"))?.read_exact_at(&mut generation, 64)?;
+            index_file::save_indexes(vault_path, &store, u64::from_le_bytes(generation))?;
+            store.index_files_current.store(true, Ordering::Release);
+            Ok::<(), OrbyError>(())
+        })
+        .await
+        .map_err(|e| OrbyError::Custom(format!("Blocking task join error: {}", e)))??;
 
         Ok(())
     }
 
     /// 改ざん防止・アトミック書き込みを伴うコミット処理。
-    /// 手順: 1. 全レーン書き込み -> 2. fsync(Lanes) -> 3. ヘッダ更新 -> 4. fsync(Header)
+    /// 手順: 0. 世代番号の更新（索引ファイルの無効化） -> 1. 全レーン書き込み -> 2. fsync(Lanes)
+    /// -> 3. ヘッダ更新 -> 4. fsync(Header)
     pub(crate) async fn commit_vault_batch(
         &self,
         index: usize,
@@ -252,9 +337,11 @@ impl Orby {
             )
         };
 
+        let touched: Vec<usize> = (0..rows.len()).map(|i| (index + i) % capacity).collect();
         let rows = std::sync::Arc::new(rows);
         let this = self.clone();
 
+        self.invalidate_vault_indexes().await?;
         tokio::task::spawn_blocking(move || {
             use rayon::prelude::*;
 
@@ -278,6 +365,7 @@ impl Orby {
         .await
         .map_err(|e| OrbyError::Custom(format!("Blocking task join error: {}", e)))??;
 
+        self.commit_vault_validity(Some(touched)).await?;
         // 3. Update Header
         self.commit_vault_header().await?;
 
@@ -300,7 +388,12 @@ impl Orby {
             )
         };
 
+        let touched: Vec<usize> = (0..values.len())
+            .map(|i| (start_index + i) % capacity)
+            .collect();
         let this = self.clone();
+
+        self.invalidate_vault_indexes().await?;
         tokio::task::spawn_blocking(move || {
             // 1. ターゲットレーンへのバルク I/O
             {
@@ -328,12 +421,187 @@ impl Orby {
         .await
         .map_err(|e| OrbyError::Custom(format!("Blocking task join error: {}", e)))??;
 
+        self.commit_vault_validity(Some(touched)).await?;
         // 3. ヘッダー更新
         self.commit_vault_header().await?;
 
         Ok(())
     }
 
+    /// 任意の物理スロットへ行単位で書き込みます（固定スロット等、非連続な書き込み用）。
+    /// 手順は `commit_vault_batch` と同様に、全レーン書き込み -> fsync -> ヘッダ更新 の順です。
+    pub(crate) async fn commit_vault_rows(
+        &self,
+        rows: Vec<(usize, Vec<u128>)>,
+    ) -> Result<(), OrbyError> {
+        let (vault_path, ring_buffer_lane_count) = {
+            let store = self.inner.read();
+            (
+                store.vault_path.clone().unwrap(),
+                store.ring_buffer_lane_count,
+            )
+        };
+
+        let touched: Vec<usize> = rows.iter().map(|(slot, _)| *slot).collect();
+        let rows = std::sync::Arc::new(rows);
+
+        self.invalidate_vault_indexes().await?;
+        tokio::task::spawn_blocking(move || {
+            use rayon::prelude::*;
+
+            (0..ring_buffer_lane_count)
+                .into_par_iter()
+                .try_for_each(|col| {
+                    let p = vault_path.join(format!("lane_{}.bin", col));
+                    let f = std::fs::OpenOptions::new().write(true).open(p)?;
+
+                    for (slot, row) in rows.iter() {
+                        let val = row.get(col).copied().unwrap_or(0);
+                        f.write_at(&val.to_le_bytes(), (slot * crate::types::PULSE_SIZE) as u64)?;
+                    }
+                    f.sync_all()?;
+
+                    Ok::<(), OrbyError>(())
+                })
+        })
+        .await
+        .map_err(|e| OrbyError::Custom(format!("Blocking task join error: {}", e)))??;
+
+        self.commit_vault_validity(Some(touched)).await?;
+        self.commit_vault_header().await?;
+
+        Ok(())
+    }
+
+    /// メモリ上の物理範囲 `[start, end)` を、全レーン分そのまま Vault へ書き戻します。
+    /// ソート済みモード等で行が再配置（シフト）された場合に使用します。
+    pub(crate) async fn commit_vault_range(
+        &self,
+        range: std::ops::Range<usize>,
+    ) -> Result<(), OrbyError> {
+        let inner = self.inner.clone();
+        let touched: Vec<usize> = range.clone().collect();
+
+        self.invalidate_vault_indexes().await?;
+        tokio::task::spawn_blocking(move || {
+            let store = inner.read();
+            let vault_path = store
+                .vault_path
+                .as_ref()
+                .ok_or_else(|| OrbyError::Custom("Vault path is not set".into()))?;
+            let end = range.end.min(store.capacity);
+            if range.start >= end {
+                return Ok(());
+            }
+
+            use rayon::prelude::*;
+            store
+                .lanes
+                .par_iter()
+                .enumerate()
+                .try_for_each(|(col, lane)| {
+                    let p = vault_path.join(format!("lane_{}.bin", col));
+                    let f = std::fs::OpenOptions::new().write(true).open(p)?;
+
+                    let bytes: &[u8] = bytemuck::cast_slice(&lane.buffer[range.start..end]);
+                    f.write_at(bytes, (range.start * crate::types::PULSE_SIZE) as u64)?;
+                    f.sync_all()?;
+
+                    Ok::<(), OrbyError>(())
+                })
+        })
+        .await
+        .map_err(|e| OrbyError::Custom(format!("Blocking task join error: {}", e)))??;
+
+        self.commit_vault_validity(Some(touched)).await?;
+        self.commit_vault_header().await?;
+
+        Ok(())
+    }
+
+    /// 指定した物理スロット群について、メモリ上の現在の行を Vault へ書き戻します。
+    pub(crate) async fn commit_vault_slots(&self, slots: Vec<usize>) -> Result<(), OrbyError> {
+        if slots.is_empty() {
+            return Ok(());
+        }
+        let inner = self.inner.clone();
+        let touched = slots.clone();
+
+        self.invalidate_vault_indexes().await?;
+        tokio::task::spawn_blocking(move || {
+            let store = inner.read();
+            let vault_path = store
+                .vault_path
+                .as_ref()
+                .ok_or_else(|| OrbyError::Custom("Vault path is not set".into()))?;
+
+            use rayon::prelude::*;
+            store
+                .lanes
+                .par_iter()
+                .enumerate()
+                .try_for_each(|(col, lane)| {
+                    let p = vault_path.join(format!("lane_{}.bin", col));
+                    let f = std::fs::OpenOptions::new().write(true).open(p)?;
+
+                    for &slot in slots.iter().filter(|&&slot| slot < store.capacity) {
+                        let val = lane.buffer[slot].as_u128();
+                        f.write_at(&val.to_le_bytes(), (slot * crate::types::PULSE_SIZE) as u64)?;
+                    }
+                    f.sync_all()?;
+
+                    Ok::<(), OrbyError>(())
+                })
+        })
+        .await
+        .map_err(|e| OrbyError::Custom(format!("Blocking task join error: {}", e)))??;
+
+        self.commit_vault_validity(Some(touched)).await?;
+        self.commit_vault_header().await?;
+
+        Ok(())
+    }
+
+    /// 有効ビットマップ（`valid.bin`）を書き戻します。
+    /// `slots` を指定した場合はそれらを含むワードのみ、`None` の場合はファイル全体を書き直します。
+    async fn commit_vault_validity(&self, slots: Option<Vec<usize>>) -> Result<(), OrbyError> {
+        let inner = self.inner.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let store = inner.read();
+            let vault_path = store
+                .vault_path
+                .as_ref()
+                .ok_or_else(|| OrbyError::Custom("Vault path is not set".into()))?;
+            let f = std::fs::OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(vault_path.join(VALIDITY_FILE))?;
+
+            match slots {
+                Some(slots) => {
+                    let words: std::collections::BTreeMap<usize, u64> = slots
+                        .into_iter()
+                        .filter(|&slot| slot < store.capacity)
+                        .map(|slot| store.valid.word_at(slot))
+                        .collect();
+                    for (offset, word) in words {
+                        f.write_at(&word.to_le_bytes(), offset as u64)?;
+                    }
+                }
+                None => f.write_all_at(&store.valid.to_bytes(), 0)?,
+            }
+            f.sync_all()?;
+
+            Ok::<(), OrbyError>(())
+        })
+        .await
+        .map_err(|e| OrbyError::Custom(format!("Blocking task join error: {}", e)))??;
+
+        Ok(())
+    }
+
     /// リングバッファのラップアラウンドを考慮したバルク書き込みヘルパー
     /// RingBufferのラップアラウンドを考慮したバルク書き込みヘルパー
     /// bytemuckを使用してゼロコピーで書き込みます
@@ -368,12 +636,18 @@ impl Orby {
     }
 
     async fn commit_vault_header(&self) -> Result<(), OrbyError> {
-        let (vault_path, len, cursor) = {
+        let (vault_path, len, cursor, tail) = {
             let store = self.inner.read();
-            (store.vault_path.clone().unwrap(), store.len, store.cursor)
+            (
+                store.vault_path.clone().unwrap(),
+                store.len,
+                store.cursor,
+                store.tail,
+            )
         };
         let header_path = vault_path.join("header.bin");
         let mut header_file = tokio::fs::OpenOptions::new()
+            .read(true)
             .write(true)
             .open(&header_path)
             .await?;
@@ -387,81 +661,56 @@ impl Orby {
             .write_all(&(cursor as u64).to_le_bytes())
             .await?;
 
+        header_file.seek(std::io::SeekFrom::Start(56)).await?;
+        header_file.write_all(&(tail as u64).to_le_bytes()).await?;
+
         header_file.sync_all().await?;
         Ok(())
     }
 
-    /// ストレージ上の Parallel Arrays を前方シフト（Compaction）します。
-    pub(crate) async fn vault_delete_and_compact(&self, index: usize) -> Result<(), OrbyError> {
-        let (vault_path, ring_buffer_lane_count, capacity) = {
+    /// ヘッダの世代番号を進めて fsync し、それ以前に保存された索引ファイルを無効にします。
+    /// レーンや `valid.bin` を書き換える前に呼び出すことで、書き込みの途中で停止しても、
+    /// 書き換え前の内容に基づく索引ファイルがロード時に採用されないようにします。
+    /// 索引ファイルは `sleep()` でのみ書き出されるため、世代番号を進めるのは書き出し後の最初のコミットだけです。
+    /// 現在の世代番号の索引ファイルがない間（索引を保存しないプールを含む）は、ヘッダの fsync を避けるため何もしません。
+    async fn invalidate_vault_indexes(&self) -> Result<(), OrbyError> {
+        let vault_path = {
             let store = self.inner.read();
-            (
-                store.vault_path.clone().unwrap(),
-                store.ring_buffer_lane_count,
-                store.capacity,
-            )
+            let Some(vault_path) = store.vault_path.clone() else {
+                return Ok(());
+            };
+            // 世代番号を進める前に下ろすことで、並行する `sleep()` が立て直した印を消さない
+            if !store.index_files_current.swap(false, Ordering::AcqRel) {
+                return Ok(());
+            }
+            vault_path
         };
-
-        if index >= capacity {
-            return Ok(());
+        if let Err(e) = bump_vault_generation(&vault_path).await {
+            // 索引ファイルはまだ有効なため、次のコミットで改めて無効化する
+            self.inner
+                .read()
+                .index_files_current
+                .store(true, Ordering::Release);
+            return Err(e);
         }
-
-        tokio::task::spawn_blocking(move || {
-            let lane_files: Vec<std::fs::File> = (0..ring_buffer_lane_count)
-                .map(|i| {
-                    let p = vault_path.join(format!("lane_{}.bin", i));
-                    OpenOptions::new().read(true).write(true).open(p)
-                })
-                .collect::<Result<Vec<_>, _>>()?;
-
-            // Fast Move: index+1..len -> index
-            if index < capacity - 1 {
-                let buf_size = 64 * 1024; // 64KB block
-                let mut buffer = vec![0u8; buf_size];
-
-                for f in &lane_files {
-                    let mut current_pos = index;
-                    while current_pos < capacity - 1 {
-                        let to_read = (buf_size / 16).min(capacity - 1 - current_pos);
-                        if to_read == 0 {
-                            break;
-                        }
-
-                        let read_offset = ((current_pos + 1) * crate::types::PULSE_SIZE) as u64;
-                        let write_offset = (current_pos * crate::types::PULSE_SIZE) as u64;
-                        let byte_count = to_read * crate::types::PULSE_SIZE;
-
-                        f.read_at(&mut buffer[..byte_count], read_offset)?;
-                        f.write_at(&buffer[..byte_count], write_offset)?;
-
-                        current_pos += to_read;
-                    }
-                    // Zero out the last slot
-                    let zeros = [0u8; crate::types::PULSE_SIZE];
-                    f.write_at(&zeros, ((capacity - 1) * crate::types::PULSE_SIZE) as u64)?;
-                    f.sync_all()?;
-                }
-            }
-            Ok::<(), OrbyError>(())
-        })
-        .await
-        .map_err(|e| OrbyError::Custom(format!("Blocking task join error: {}", e)))??;
-
-        // Update Header
-        self.commit_vault_header().await?;
-
         Ok(())
     }
 
     /// 既存の Vault データを検証し、メモリへ並列ロードします。
     pub(crate) async fn validate_and_load_vault(&self, strict: bool) -> Result<(), OrbyError> {
-        let (vault_path, expected_cap, expected_dim) = {
+        let (vault_path, expected_cap, expected_dim, expected_mode, expected_key_lane) = {
             let store = self.inner.read();
             let p = store
                 .vault_path
                 .clone()
                 .ok_or_else(|| OrbyError::Custom("Vault path is not set".into()))?;
-            (p, store.capacity, store.ring_buffer_lane_count)
+            (
+                p,
+                store.capacity,
+                store.ring_buffer_lane_count,
+                store.logic_mode,
+                store.key_lane,
+            )
         };
 
         // 1. Metadata Validation (header.bin)
@@ -489,7 +738,9 @@ impl Orby {
         let v_cap = u64::from_le_bytes(header_data[16..24].try_into().unwrap()) as usize;
         let v_len = u64::from_le_bytes(header_data[24..32].try_into().unwrap()) as usize;
         let v_cursor = u64::from_le_bytes(header_data[32..40].try_into().unwrap()) as usize;
+        let v_tail = u64::from_le_bytes(header_data[56..64].try_into().unwrap()) as usize;
         let v_dim = u32::from_le_bytes(header_data[40..44].try_into().unwrap()) as usize;
+        let v_generation = u64::from_le_bytes(header_data[64..72].try_into().unwrap());
 
         if v_cap != expected_cap || v_dim != expected_dim {
             return Err(OrbyError::ConfigMismatch {
@@ -500,8 +751,9 @@ impl Orby {
                 ),
             });
         }
+        verify_logic_header(&self.name(), expected_mode, expected_key_lane, &header_data)?;
 
-        // 2. Physical Integrity Check & Consistency Check (CommitCycle)
+        // 2. Physical Integrity Check
         if strict {
             for i in 0..v_dim {
                 let lane_path = vault_path.join(format!("lane_{}.bin", i));
@@ -555,61 +807,174 @@ impl Orby {
             let mut store = self.inner.write();
             store.len = v_len;
             store.cursor = v_cursor;
-
-            // CommitCycle Validation Vector
-            let mut ref_cycle_map: Option<Vec<u8>> = None;
+            // 満杯のリングでは最古の行は常にカーソル位置にある（tail 未記録の旧ヘッダにも対応）
+            store.tail = if store.logic_mode == LogicMode::RingBuffer && v_len == v_cap {
+                v_cursor
+            } else {
+                v_tail
+            };
 
             for (lane_idx, buf) in loaded_results {
-                // Zero-copy cast from bytes to PulseCell is unsafe without alignment guarantee,
-                // but PulseCell is u128 and buf is read from file.
-                // We will stick to manual conversion loop for safety or use bytemuck if we can guarantee alignment.
-                // Since buf is Vec<u8> allocated by us, it might not be 16-byte aligned.
-                // However, let's keep the existing loop but add cycle check logic.
-
                 if !store.lanes.is_empty() && !store.lanes[lane_idx].buffer.is_empty() {
-                    // Pre-allocate cycle map for the first lane
-                    if lane_idx == 0 {
-                        ref_cycle_map = Some(vec![0u8; v_cap]);
-                    }
-
                     for (row, chunk) in buf.chunks_exact(crate::types::PULSE_SIZE).enumerate() {
                         let val = u128::from_le_bytes(chunk.try_into().unwrap());
-                        let cell = crate::types::PulseCell::new(val);
-                        store.lanes[lane_idx].buffer[row] = cell;
-
-                        // Strict Consistency Check
-                        if strict {
-                            let cycle = cell.commit_cycle();
-                            match lane_idx {
-                                0 => {
-                                    if let Some(map) = &mut ref_cycle_map {
-                                        map[row] = cycle;
-                                    }
-                                }
-                                _ => {
-                                    // Compare with lane 0
-                                    if let Some(map) = &ref_cycle_map {
-                                        if map[row] != cycle {
-                                            // 0 cycle implies empty/deleted, sometimes OK but in strict mode we might want to ensure sync.
-                                            // If both are 0, it matches. If one is not 0, it's a mismatch if strict Sync.
-                                            // However, for performance benchmarks we often use raw logic.
-                                            // Let's enforce strict equality only for active rows?
-                                            // The requirement says "不一致がある場合は...検知し、OrbyError".
-                                            if cell.as_u128() != 0 && map[row] != cycle {
-                                                return Err(OrbyError::InconsistentWrite {
-                                                    pool_name: self.name(),
-                                                });
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
+                        store.lanes[lane_idx].buffer[row] = crate::types::PulseCell::new(val);
                     }
                 }
             }
+
+            load_validity(&vault_path, &mut store, strict)?;
+            if strict {
+                verify_invalid_slots_cleared(&store)?;
+            }
+
+            // 空きリストやゾーンマップはメモリ上のみで管理されるため、ロードしたデータから再構築する。
+            // 検索用の索引は、ヘッダと世代番号が一致する索引ファイルがあればそこから復元する
+            crate::logic::rebuild_summaries(&mut store);
+            index_file::restore_indexes(&vault_path, &mut store, v_generation);
+            if store.logic_mode == LogicMode::StaticSlot {
+                crate::logic::slot::rebuild_free_slots(&mut store);
+            }
+
+            // 二分探索の前提となるキー順序が Vault 上で崩れていないことを確認
+            if strict
+                && matches!(store.logic_mode, LogicMode::Sorted | LogicMode::TopK(_))
+                && !crate::logic::sorted::is_ordered(&store)
+            {
+                return Err(OrbyError::InconsistentState {
+                    name: store.name.clone(),
+                    message: "Rows in vault are not ordered by the key lane".into(),
+                });
+            }
+
+            // ハッシュ配置はそのまま再利用するため、各行が探索で到達可能であることを確認
+            if strict
+                && store.logic_mode == LogicMode::HashSlot
+                && !crate::logic::hash::is_consistent(&store)
+            {
+                return Err(OrbyError::InconsistentState {
+                    name: store.name.clone(),
+                    message: "Rows in vault are not reachable from their hash slots".into(),
+                });
+            }
         }
 
         Ok(())
     }
 }
+
+/// Vault の有効ビットマップをロードします。
+/// ビットマップを持たない旧形式の Vault では、非ゼロの行を生存行とみなして再構築します。
+fn load_validity(
+    vault_path: &Path,
+    store: &mut OrbyRingBufferSilo,
+    strict: bool,
+) -> Result<(), OrbyError> {
+    let path = vault_path.join(VALIDITY_FILE);
+    if path.exists() {
+        let bytes = std::fs::read(&path)?;
+        match SlotBitmap::from_bytes(store.capacity, &bytes) {
+            Some(bitmap) => {
+                store.valid = bitmap;
+                return Ok(());
+            }
+            None if strict => {
+                return Err(OrbyError::ConfigMismatch {
+                    name: store.name.clone(),
+                    reason: format!(
+                        "{} size mismatch. Expected {}, found {}",
+                        VALIDITY_FILE,
+                        SlotBitmap::byte_len(store.capacity),
+                        bytes.len()
+                    ),
+                });
+            }
+            None => {}
+        }
+    }
+
+    let mut bitmap = SlotBitmap::new(store.capacity);
+    for slot in 0..store.capacity {
+        if store
+            .lanes
+            .iter()
+            .any(|lane| lane.buffer[slot].as_u128() != 0)
+        {
+            bitmap.set(slot);
+        }
+    }
+    store.valid = bitmap;
+    Ok(())
+}
+
+/// 無効なスロットのレーンがすべて 0 であることを検証します。
+/// 削除や空きスロットでは全レーンを 0 にした上で有効ビットを落とし、コミットはレーン -> `valid.bin` ->
+/// ヘッダの順に書き込みます。そのため、無効なスロットにいずれかのレーンの値が残っている場合は、
+/// レーンの書き込みに対応する有効ビットがコミットされていない（書き込みが途中で中断された）ことを意味します。
+fn verify_invalid_slots_cleared(store: &OrbyRingBufferSilo) -> Result<(), OrbyError> {
+    if store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
+        return Ok(());
+    }
+    let torn = (0..store.capacity).any(|slot| {
+        !store.valid.get(slot)
+            && store
+                .lanes
+                .iter()
+                .any(|lane| lane.buffer[slot].as_u128() != 0)
+    });
+    if torn {
+        return Err(OrbyError::InconsistentWrite {
+            pool_name: store.name.clone(),
+        });
+    }
+    Ok(())
+}
+
+/// ヘッダに記録された論理モードとキーレーンが、現在の設定と一致するかを検証します。
+fn verify_logic_header(
+    name: &str,
+    logic_mode: LogicMode,
+    key_lane: usize,
+    header_data: &[u8],
+) -> Result<(), OrbyError> {
+    let v_mode = header_data[44];
+    let v_key_lane = u32::from_le_bytes(header_data[48..52].try_into().unwrap()) as usize;
+    if v_mode != logic_mode.as_u8() || v_key_lane != key_lane {
+        return Err(OrbyError::ConfigMismatch {
+            name: name.to_string(),
+            reason: format!(
+                "Vault logic configuration mismatch. Expected mode={:?}, key_lane={}, but found mode={:?}, key_lane={}",
+                logic_mode,
+                key_lane,
+                LogicMode::from_u8(v_mode),
+                v_key_lane
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// ヘッダの世代番号を一つ進めて fsync します。
+async fn bump_vault_generation(vault_path: &Path) -> Result<(), OrbyError> {
+    let header_path = vault_path.join("header.bin");
+    if !header_path.exists() {
+        // ヘッダのない Vault には、世代番号の一致する索引ファイルも存在しない
+        return Ok(());
+    }
+    let mut header_file = tokio::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&header_path)
+        .await?;
+
+    use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+    let mut generation = [0u8; 8];
+    header_file.seek(std::io::SeekFrom::Start(64)).await?;
+    header_file.read_exact(&mut generation).await?;
+    let generation = u64::from_le_bytes(generation).wrapping_add(1);
+    header_file.seek(std::io::SeekFrom::Start(64)).await?;
+    header_file.write_all(&generation.to_le_bytes()).await?;
+
+    header_file.sync_all().await?;
+    Ok(())
+}
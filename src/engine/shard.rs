@@ -0,0 +1,625 @@
+use crate::engine::Orby;
+use crate::error::OrbyError;
+use crate::filter::Filter;
+use crate::logic::{aggregate, hash, ring, sketch, unique};
+use crate::types::{
+    AccessPath, Agg, AggregateResult, InsertOutcome, LaneZone, LogicMode, PulseCell, QueryPlan,
+};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// `shards(n)` で構成された独立したシャード群。
+///
+/// 各シャードは独自のロック・カーソル・Vault サブディレクトリを持つ `Orby` です。
+/// シャードの行には、ユーザーのレーンの後ろにグローバルな挿入順序番号（sequence）を
+/// 格納する隠しレーンが一本追加されます。シャードをまたぐ検索結果は、この番号で最新順に統合されます。
+pub(crate) struct ShardSet {
+    pub(crate) shards: Vec<Orby>,
+    /// 振り分けに使用するキーレーン。
+    pub(crate) key_lane: usize,
+    /// ユーザーから見たレーン数（sequence レーンを含まない）。
+    pub(crate) lane_count: usize,
+    /// 次に割り当てる sequence 番号。
+    next_seq: AtomicU64,
+    /// シャードごとの書き込みロック。
+    /// 事前検証から書き込みまでの間、対象のシャードに他の書き込みが割り込まないようにします。
+    writes: Vec<tokio::sync::Mutex<()>>,
+}
+
+impl ShardSet {
+    /// シャード群を構成し、既存データの最大 sequence 番号から採番を再開します。
+    pub(crate) fn new(shards: Vec<Orby>, key_lane: usize, lane_count: usize) -> Self {
+        let next_seq = shards
+            .iter()
+            .filter_map(|shard| {
+                let store = shard.inner.read();
+                let seqs = store.lanes.get(lane_count)?;
+                seqs.buffer
+                    .iter()
+                    .enumerate()
+                    .filter(|(slot, _)| store.valid.get(*slot))
+                    .map(|(_, cell)| cell.as_u128() as u64)
+                    .max()
+            })
+            .max()
+            .map_or(0, |max| max + 1);
+        let writes = shards.iter().map(|_| tokio::sync::Mutex::new(())).collect();
+        Self {
+            shards,
+            key_lane,
+            lane_count,
+            next_seq: AtomicU64::new(next_seq),
+            writes,
+        }
+    }
+
+    /// 指定したシャードの書き込みロックを取得します。
+    /// 常にシャード番号の昇順で取得するため、複数のシャードを対象とする書き込みどうしが互いを待ち続けることはありません。
+    async fn lock_shards(
+        &self,
+        targets: impl IntoIterator<Item = usize>,
+    ) -> Vec<tokio::sync::MutexGuard<'_, ()>> {
+        let targets: std::collections::BTreeSet<usize> = targets.into_iter().collect();
+        let mut guards = Vec::with_capacity(targets.len());
+        for shard in targets {
+            guards.push(self.writes[shard].lock().await);
+        }
+        guards
+    }
+
+    /// `lane` の値が `id` に一致する行を持ち得るシャードの一覧を返します。キーレーンであれば振り分け先の一つのみです。
+    fn shards_holding(&self, lane: usize, id: u128) -> Vec<usize> {
+        if lane == self.key_lane {
+            vec![self.route(id)]
+        } else {
+            (0..self.shards.len()).collect()
+        }
+    }
+
+    /// キーの値から振り分け先のシャードを決定します。
+    #[inline]
+    fn route(&self, key: u128) -> usize {
+        hash::home_slot(key, self.shards.len())
+    }
+
+    /// `count` 件分の sequence 番号を予約し、先頭の番号を返します。
+    #[inline]
+    fn reserve_seq(&self, count: usize) -> u64 {
+        self.next_seq.fetch_add(count as u64, Ordering::Relaxed)
+    }
+
+    /// 行の末尾に sequence 番号を付加します。
+    fn with_seq(&self, row: &[u128], seq: u64) -> Vec<u128> {
+        let mut tagged = Vec::with_capacity(row.len() + 1);
+        tagged.extend_from_slice(row);
+        tagged.push(seq as u128);
+        tagged
+    }
+
+    fn lane_mismatch(&self, found: usize) -> OrbyError {
+        OrbyError::LaneCountMismatch {
+            pool_name: self.shards[0].name(),
+            expected: self.lane_count,
+            found,
+        }
+    }
+
+    /// キーレーンの値 `value` の重複を、シャード自身の書き込みと同じく振り分け先のシャードの名前で報告します。
+    pub(crate) fn duplicate_key(&self, value: u128) -> OrbyError {
+        OrbyError::DuplicateKey {
+            pool_name: self.shards[self.route(value)].name(),
+            lane: self.key_lane,
+            value,
+        }
+    }
+
+    /// 行をキーのハッシュで各シャードへ振り分けて挿入し、結果を元の順序で返します。
+    /// スロット番号は各シャード内の物理位置です。
+    /// 容量不足（`LogicMode::Table`）や一意性制約の違反は、どのシャードにも書き込む前に全シャードで確認します。
+    /// 確認から書き込みまでは振り分け先のシャードの書き込みロックを保持するため、
+    /// 並行する書き込みが同じ空きや値を取り合うことはありません。
+    pub(crate) async fn insert_rows(
+        &self,
+        rows: Vec<Vec<u128>>,
+    ) -> Result<Vec<InsertOutcome>, OrbyError> {
+        if let Some(row) = rows.iter().find(|row| row.len() != self.lane_count) {
+            return Err(self.lane_mismatch(row.len()));
+        }
+        let _writes = self
+            .lock_shards(rows.iter().map(|row| self.route(row[self.key_lane])))
+            .await;
+        self.insert_locked(rows).await
+    }
+
+    /// `insert_rows` の本体です。呼び出し側が振り分け先のシャードの書き込みロックを保持している必要があります。
+    async fn insert_locked(&self, rows: Vec<Vec<u128>>) -> Result<Vec<InsertOutcome>, OrbyError> {
+        let first_seq = self.reserve_seq(rows.len());
+        let mut routed: Vec<(Vec<usize>, Vec<Vec<u128>>)> =
+            vec![(Vec::new(), Vec::new()); self.shards.len()];
+        for (i, row) in rows.iter().enumerate() {
+            let (positions, tagged) = &mut routed[self.route(row[self.key_lane])];
+            positions.push(i);
+            tagged.push(self.with_seq(row, first_seq + i as u64));
+        }
+
+        for (shard, (_, tagged)) in self.shards.iter().zip(&routed) {
+            if !tagged.is_empty() {
+                ring::check_insert_batch(&shard.inner.read(), tagged)?;
+            }
+        }
+
+        let mut outcomes = vec![InsertOutcome::Duplicate; rows.len()];
+        for (shard, (positions, tagged)) in self.shards.iter().zip(routed) {
+            if tagged.is_empty() {
+                continue;
+            }
+            let results = Box::pin(shard.insert_rows(tagged)).await?;
+            for (pos, outcome) in positions.into_iter().zip(results) {
+                outcomes[pos] = outcome;
+            }
+        }
+        Ok(outcomes)
+    }
+
+    /// シャードから集めた行を sequence 番号の降順に並べ、隠しレーンを除いて最大 `limit` 件を返します。
+    fn merge_newest(&self, mut rows: Vec<Arc<[u128]>>, limit: usize) -> Vec<Arc<[u128]>> {
+        let dim = self.lane_count;
+        rows.sort_unstable_by(|a, b| b[dim].cmp(&a[dim]));
+        rows.into_iter()
+            .take(limit)
+            .map(|row| Arc::from(&row[..dim]))
+            .collect()
+    }
+
+    /// 全シャードを検索し、sequence 番号の降順（最新順）に統合して最大 `limit` 件を返します。
+    pub(crate) fn query_raw<F>(&self, filter: F, limit: usize) -> Vec<Arc<[u128]>>
+    where
+        F: Fn(&[PulseCell]) -> bool + Sync + Send,
+    {
+        let dim = self.lane_count;
+        let merged = self
+            .shards
+            .iter()
+            .flat_map(|shard| {
+                // シャードは常にリング系の論理モードのため、ストアを直接走査する
+                let store = shard.inner.read();
+                ring::query_raw(&store, |row| filter(&row[..dim]), limit)
+            })
+            .collect();
+        self.merge_newest(merged, limit)
+    }
+
+    /// 全シャードをレーンに束縛した述語で検索し、sequence 番号の降順に統合して最大 `limit` 件を返します。
+    pub(crate) fn query_lanes<P>(
+        &self,
+        lanes: &[usize],
+        predicate: P,
+        limit: usize,
+    ) -> Vec<Arc<[u128]>>
+    where
+        P: Fn(&[u128]) -> bool + Sync + Send,
+    {
+        if lanes.iter().any(|&lane| lane >= self.lane_count) {
+            return Vec::new();
+        }
+        let merged = self
+            .shards
+            .iter()
+            .flat_map(|shard| {
+                let store = shard.inner.read();
+                ring::query_lanes(&store, lanes, &predicate, limit)
+            })
+            .collect();
+        self.merge_newest(merged, limit)
+    }
+
+    /// 各シャードの `query`（索引があれば索引を使用）の結果を、sequence 番号の降順に統合します。
+    /// sequence 番号の隠しレーン以降を参照するフィルタは、範囲外のレーンとして空の結果を返します。
+    pub(crate) fn query(&self, filter: &Filter, limit: usize) -> Vec<Arc<[u128]>> {
+        if filter.lanes().iter().any(|&lane| lane >= self.lane_count) {
+            return Vec::new();
+        }
+        let merged = self
+            .shards
+            .iter()
+            .flat_map(|shard| shard.query(filter, limit))
+            .collect();
+        self.merge_newest(merged, limit)
+    }
+
+    /// 各シャードの集計結果を統合します。
+    pub(crate) fn aggregate(
+        &self,
+        filter: &Filter,
+        aggs: &[Agg],
+    ) -> Result<AggregateResult, OrbyError> {
+        aggregate::validate(&self.shards[0].name(), self.lane_count, aggs)?;
+        // sequence 番号の隠しレーンを参照するフィルタは、範囲外のレーンとして一致しない
+        if filter.lanes().iter().any(|&lane| lane >= self.lane_count) {
+            return Ok(aggregate::identity(aggs));
+        }
+        let mut merged = aggregate::identity(aggs);
+        for shard in &self.shards {
+            merged = aggregate::merge(aggs, merged, shard.aggregate(filter, aggs)?);
+        }
+        Ok(merged)
+    }
+
+    /// 各シャードのグループごとの集計結果を統合します。統合後のグループ数も `max_groups` で制限します。
+    pub(crate) fn group_by(
+        &self,
+        key_lane: usize,
+        filter: &Filter,
+        aggs: &[Agg],
+        max_groups: usize,
+    ) -> Result<HashMap<u128, AggregateResult>, OrbyError> {
+        let name = self.shards[0].name();
+        aggregate::check_lane(&name, self.lane_count, key_lane)?;
+        aggregate::validate(&name, self.lane_count, aggs)?;
+        if filter.lanes().iter().any(|&lane| lane >= self.lane_count) {
+            return Ok(HashMap::new());
+        }
+        let mut merged = HashMap::new();
+        for shard in &self.shards {
+            let groups = shard.group_by(key_lane, filter, aggs)?;
+            merged =
+                aggregate::merge_groups(aggs, merged, groups, max_groups).ok_or_else(|| {
+                    OrbyError::TooManyGroups {
+                        pool_name: name.clone(),
+                        max_groups,
+                    }
+                })?;
+        }
+        Ok(merged)
+    }
+
+    /// 各シャードの異なる値（小さい順に最大 `limit` 件）を統合し、全体で小さい順に `limit` 件を返します。
+    pub(crate) fn distinct(
+        &self,
+        lane: usize,
+        filter: &Filter,
+        limit: usize,
+    ) -> Result<Vec<u128>, OrbyError> {
+        aggregate::check_lane(&self.shards[0].name(), self.lane_count, lane)?;
+        if filter.lanes().iter().any(|&lane| lane >= self.lane_count) {
+            return Ok(Vec::new());
+        }
+        let mut merged = Vec::new();
+        for shard in &self.shards {
+            merged.extend(shard.distinct(lane, filter, limit)?);
+        }
+        merged.sort_unstable();
+        merged.dedup();
+        merged.truncate(limit);
+        Ok(merged)
+    }
+
+    /// 各シャードのスケッチのレジスタを統合して、全体の異なる値の数を推定します。
+    /// 同じ値が複数のシャードにあっても、レジスタの最大値を取るため二重に数えません。
+    pub(crate) fn approx_distinct(&self, lane: usize) -> Result<u64, OrbyError> {
+        aggregate::check_lane(&self.shards[0].name(), self.lane_count, lane)?;
+        let mut merged = vec![0u8; sketch::REGISTERS];
+        for shard in &self.shards {
+            sketch::merge_into(&mut merged, &shard.sketch_registers(lane)?);
+        }
+        Ok(sketch::estimate(&merged))
+    }
+
+    /// 各シャードの `explain` を合計します。経路は最もコストの高いシャードのものとします。
+    pub(crate) fn explain(&self, filter: &Filter) -> QueryPlan {
+        // 経路は最も安いものから始め、シャードごとに高い方へ置き換える
+        let empty = QueryPlan {
+            path: AccessPath::LaneIndex,
+            estimated_rows: 0,
+            blocks_total: 0,
+            blocks_skipped: 0,
+            recheck: false,
+        };
+        self.shards
+            .iter()
+            .map(|shard| shard.explain(filter))
+            .fold(empty, |acc, plan| QueryPlan {
+                path: acc.path.max(plan.path),
+                estimated_rows: acc.estimated_rows + plan.estimated_rows,
+                blocks_total: acc.blocks_total + plan.blocks_total,
+                blocks_skipped: acc.blocks_skipped + plan.blocks_skipped,
+                recheck: acc.recheck || plan.recheck,
+            })
+    }
+
+    /// 各シャードで単調レーンの二分探索を行い、結果を sequence 番号の降順に統合します。
+    /// シャード内の行は sequence 番号の順に並ぶため、各シャードでも単調性が保たれます。
+    pub(crate) fn find_range_monotonic(
+        &self,
+        lane: usize,
+        min: u128,
+        max: u128,
+    ) -> Result<Vec<Arc<[u128]>>, OrbyError> {
+        if lane >= self.lane_count {
+            return Err(self.lane_mismatch(lane + 1));
+        }
+        let mut merged = Vec::new();
+        for shard in &self.shards {
+            merged.extend(shard.find_range_monotonic(lane, min, max)?);
+        }
+        Ok(self.merge_newest(merged, usize::MAX))
+    }
+
+    /// 全シャードの生存行を sequence 番号の降順に統合した順序で、条件に一致する行の論理インデックスを返します。
+    /// `get_at` と同じ順序のため、返したインデックスはそのまま `get_at` に渡せます。
+    pub(crate) fn find_indices<F>(&self, filter: F, limit: usize) -> Vec<usize>
+    where
+        F: Fn(&[PulseCell]) -> bool + Sync + Send,
+    {
+        let mut cells = Vec::with_capacity(self.lane_count);
+        self.query_raw(|_| true, usize::MAX)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, row)| {
+                cells.clear();
+                cells.extend(row.iter().map(|&value| PulseCell::from(value)));
+                filter(&cells)
+            })
+            .map(|(index, _)| index)
+            .take(limit)
+            .collect()
+    }
+
+    /// 各シャードのゾーンマップを、シャードの番号順に物理位置を連結して返します。
+    /// シャード `i` のブロックの位置は、それより前のシャードの容量の合計だけずらされます。
+    pub(crate) fn zone_map(&self, lane: usize) -> Vec<LaneZone> {
+        if lane >= self.lane_count {
+            return Vec::new();
+        }
+        let mut offset = 0;
+        let mut zones = Vec::new();
+        for shard in &self.shards {
+            let store = shard.inner.read();
+            zones.extend(
+                store
+                    .zones
+                    .lane_zones(lane, store.capacity)
+                    .into_iter()
+                    .map(|zone| LaneZone {
+                        start: zone.start + offset,
+                        end: zone.end + offset,
+                        ..zone
+                    }),
+            );
+            offset += store.capacity;
+        }
+        zones
+    }
+
+    /// 全シャードのゾーンマップを合わせたレーンの値の範囲を返します。
+    pub(crate) fn lane_range(&self, lane: usize) -> Option<(u128, u128)> {
+        if lane >= self.lane_count {
+            return None;
+        }
+        self.shards
+            .iter()
+            .filter_map(|shard| shard.lane_range(lane))
+            .reduce(|(lo, hi), (min, max)| (lo.min(min), hi.max(max)))
+    }
+
+    /// 全シャードから一致する行を削除します。
+    pub(crate) async fn purge_by_id(&self, index: usize, id: u128) {
+        let targets = self.shards_holding(index, id);
+        let _writes = self.lock_shards(targets.iter().copied()).await;
+        for shard in targets {
+            Box::pin(self.shards[shard].purge_by_id(index, id)).await;
+        }
+    }
+
+    /// キーレーンに一意性のレーン（`unique_lane` / `unique`）が設定されているかを返します。
+    fn unique_key(&self) -> bool {
+        self.shards[0]
+            .inner
+            .read()
+            .constraints
+            .iter()
+            .any(|keys| keys.lane == self.key_lane)
+    }
+
+    /// 全シャードから `lane` の値が `value` に一致する行の sequence 番号を集めます。
+    fn matching_seqs(&self, lane: usize, value: u128) -> Vec<Vec<u128>> {
+        let dim = self.lane_count;
+        self.shards
+            .iter()
+            .map(|shard| {
+                let store = shard.inner.read();
+                ring::query_lanes(&store, &[lane], |v| v[0] == value, store.capacity)
+                    .iter()
+                    .map(|row| row[dim])
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// 全シャードの一致する行を更新します。
+    /// 各行の sequence 番号は維持されるため、単一プールと同様に更新で並び順は変わりません。
+    /// キーレーンの変更で振り分け先が変わる行は、新しいシャードへ挿入してから元のシャードから取り除きます。
+    ///
+    /// 一意性制約と、移動する行を振り分け先のシャードが受け入れられること（容量・重複）は、
+    /// どのシャードにも書き込む前に確認し、違反する場合はどのシャードも更新しません。
+    /// 確認を通過した後に失敗し得るのは Vault への書き込みエラーのみで、その場合は先に処理したシャードの更新が残ります。
+    /// 行の移動は二つのシャードへの別々の書き込みのため、並行する読み取りからは一時的に両方のシャードに見えることがあります。
+    pub(crate) async fn update_by_id(
+        &self,
+        index: usize,
+        id: u128,
+        new_data: &[u128],
+    ) -> Result<bool, OrbyError> {
+        if index >= self.lane_count || new_data.len() != self.lane_count {
+            return Ok(false);
+        }
+        let _writes = self.lock_update(index, id, new_data).await;
+        self.update_locked(index, id, new_data).await
+    }
+
+    /// `index` の値が `id` の行を `row` で書き換える、または `row` を挿入する書き込みが対象とする
+    /// シャード（一致する行を持ち得るシャードと、`row` の振り分け先）の書き込みロックを取得します。
+    async fn lock_update(
+        &self,
+        index: usize,
+        id: u128,
+        row: &[u128],
+    ) -> Vec<tokio::sync::MutexGuard<'_, ()>> {
+        let mut locked = self.shards_holding(index, id);
+        locked.push(self.route(row[self.key_lane]));
+        self.lock_shards(locked).await
+    }
+
+    /// `update_by_id` の本体です。呼び出し側が `lock_update` で書き込みロックを保持している必要があります。
+    async fn update_locked(
+        &self,
+        index: usize,
+        id: u128,
+        new_data: &[u128],
+    ) -> Result<bool, OrbyError> {
+        let dim = self.lane_count;
+        let value = new_data[self.key_lane];
+        let home = self.route(value);
+        let targets = self.matching_seqs(index, id);
+        let total: usize = targets.iter().map(Vec::len).sum();
+        if total > 0 && self.unique_key() {
+            // 更新後のキーを持つ行が、更新対象の一行だけであることを確認する
+            let holders = self.matching_seqs(self.key_lane, value);
+            let foreign = holders
+                .iter()
+                .flatten()
+                .any(|seq| !targets.iter().flatten().any(|target| target == seq));
+            if total > 1 || foreign {
+                return Err(self.duplicate_key(value));
+            }
+        }
+
+        // 振り分け先が変わる行は、振り分け先のシャードがすべて受け入れられることを先に確認する
+        let moved: Vec<Vec<u128>> = targets
+            .iter()
+            .enumerate()
+            .filter(|&(current, _)| current != home)
+            .flat_map(|(_, seqs)| seqs.iter().map(|&seq| self.with_seq(new_data, seq as u64)))
+            .collect();
+        if !moved.is_empty() {
+            let store = self.shards[home].inner.read();
+            ring::check_insert_batch(&store, &moved)?;
+            if unique::admit(&store, &moved).contains(&false) {
+                return Err(self.duplicate_key(value));
+            }
+        }
+
+        let mut found = false;
+        for (current, (shard, seqs)) in self.shards.iter().zip(targets).enumerate() {
+            // sequence 番号は行ごとに一意なため、隠しレーンを指定して一行ずつ更新する
+            for seq in seqs {
+                let tagged = self.with_seq(new_data, seq as u64);
+                if current == home {
+                    found |= Box::pin(shard.update_by_id(dim, seq, &tagged)).await?;
+                    continue;
+                }
+                // 以降の挿入・一意性の確認は振り分け先のシャードのみで行われるため、行を移す
+                Box::pin(self.shards[home].insert_rows(vec![tagged])).await?;
+                Box::pin(shard.purge_by_id(dim, seq)).await;
+                found = true;
+            }
+        }
+        Ok(found)
+    }
+
+    /// 一致する行があれば更新し、なければ振り分け先のシャードへ挿入します。
+    /// 更新と挿入の間も対象のシャードの書き込みロックを保持するため、並行する書き込みが割り込むことはありません。
+    /// 挿入する行が一意性のレーンで除外される場合は、単一のプールと同様にエラーを返します。
+    pub(crate) async fn upsert(
+        &self,
+        index: usize,
+        id: u128,
+        data: &[u128],
+    ) -> Result<(), OrbyError> {
+        if data.len() != self.lane_count {
+            return Err(self.lane_mismatch(data.len()));
+        }
+        let _writes = self.lock_update(index, id, data).await;
+        if index < self.lane_count && self.update_locked(index, id, data).await? {
+            return Ok(());
+        }
+        match self.insert_locked(vec![data.to_vec()]).await?[0] {
+            InsertOutcome::Inserted(_) => Ok(()),
+            // シャードは常にリング系の論理モードのため、除外されるのは重複のみ
+            _ => Err(self.duplicate_key(data[self.key_lane])),
+        }
+    }
+
+    /// 全シャードの内容を破棄し、指定された行を振り分けて再投入します。
+    /// 行をシャードごとに振り分けた上で、`LogicMode::Table` のシャードが自身の容量を超えないことと、
+    /// キーレーンの一意性制約に違反しないことを、どのシャードも破棄する前に確認します。
+    /// `LogicMode::RingBuffer` のシャードでは、容量を超えた分は古い行から切り捨てられます。
+    pub(crate) async fn purge_all_data(&self, rows: Vec<Vec<u128>>) -> Result<(), OrbyError> {
+        let _writes = self.lock_shards(0..self.shards.len()).await;
+        if let Some(row) = rows.iter().find(|row| row.len() != self.lane_count) {
+            return Err(self.lane_mismatch(row.len()));
+        }
+        if self.unique_key() {
+            let mut seen = HashSet::with_capacity(rows.len());
+            if let Some(row) = rows.iter().find(|row| !seen.insert(row[self.key_lane])) {
+                return Err(self.duplicate_key(row[self.key_lane]));
+            }
+        }
+
+        let first_seq = self.reserve_seq(rows.len());
+        let mut buckets = vec![Vec::new(); self.shards.len()];
+        for (i, row) in rows.iter().enumerate() {
+            buckets[self.route(row[self.key_lane])].push(self.with_seq(row, first_seq + i as u64));
+        }
+        for (shard, bucket) in self.shards.iter().zip(&mut buckets) {
+            let store = shard.inner.read();
+            if bucket.len() <= store.capacity {
+                continue;
+            }
+            if store.logic_mode == LogicMode::Table {
+                return Err(OrbyError::StorageFull {
+                    pool_name: store.name.clone(),
+                    capacity: store.capacity,
+                });
+            }
+            bucket.drain(..bucket.len() - store.capacity);
+        }
+
+        for (shard, bucket) in self.shards.iter().zip(buckets) {
+            Box::pin(shard.purge_all_data(bucket)).await?;
+        }
+        Ok(())
+    }
+
+    /// 全シャードの期限切れの行を取り除き、その合計件数を返します。
+    pub(crate) async fn purge_expired(&self) -> Result<usize, OrbyError> {
+        let mut total = 0;
+        for (shard, writes) in self.shards.iter().zip(&self.writes) {
+            let _writes = writes.lock().await;
+            total += Box::pin(shard.purge_expired()).await?;
+        }
+        Ok(total)
+    }
+
+    /// 全シャードのメモリ内容を Vault へ退避します。
+    pub(crate) async fn sleep(&self) -> Result<(), OrbyError> {
+        for shard in &self.shards {
+            Box::pin(shard.sleep()).await?;
+        }
+        Ok(())
+    }
+
+    /// 全シャードの (行数, 容量) の合計を返します。
+    pub(crate) fn totals(&self) -> (usize, usize) {
+        self.shards.iter().fold((0, 0), |(len, cap), shard| {
+            let (l, c, _) = shard.meta();
+            (len + l, cap + c)
+        })
+    }
+
+    /// 全シャードの生存行数の合計を返します。
+    pub(crate) fn count_active(&self) -> usize {
+        self.shards.iter().map(|shard| shard.count_active()).sum()
+    }
+}
@@ -1,5 +1,8 @@
 use super::*;
+use crate::filter::Filter;
 use crate::row::PulseCellPack;
+use crate::types::{AccessPath, Agg, InsertOutcome, PulseCell, RankOrder};
+use std::collections::HashSet;
 
 #[tokio::test]
 async fn test_insert() {
@@ -75,7 +78,7 @@ async fn test_update_and_upsert() {
         .await
         .unwrap();
 
-    let found = engine.update_by_id(0, 1, &[1, 11]).await;
+    let found = engine.update_by_id(0, 1, &[1, 11]).await.unwrap();
     assert!(found);
 
     let results = engine.query_raw(|row| row[0].as_u128() == 1, 1);
@@ -265,6 +268,139 @@ async fn test_vault_autoload() {
     let _ = std::fs::remove_dir_all(&db_path);
 }
 
+#[tokio::test]
+async fn test_vault_persists_indexes() {
+    let label = "test_vault_persists_indexes";
+    let db_path = std::env::temp_dir().join(label);
+    if db_path.exists() {
+        let _ = std::fs::remove_dir_all(&db_path);
+    }
+    let open = || {
+        Orby::builder(label)
+            .ring_buffer_lane_item_count(10)
+            .ring_buffer_lane_count(3)
+            .index_lane(0)
+            .composite_index(&[1, 2])
+            .bitmap_lane(1)
+            .with_storage(crate::types::SaveMode::Vault(Some(
+                db_path.parent().unwrap().to_path_buf(),
+            )))
+            .autoload(true)
+            .build()
+    };
+    let index_files = ["index_0.bin", "composite_1_2.bin", "bitmap_1.bin"];
+    let tenant_user = Filter::And(vec![
+        Filter::Eq { lane: 1, value: 10 },
+        Filter::Eq {
+            lane: 2,
+            value: 100,
+        },
+    ]);
+
+    // 1. sleep で索引ファイルが書き出される
+    {
+        let engine = open().await.unwrap();
+        engine
+            .insert_batch(&[[1u128, 10, 100], [2, 10, 200], [3, 20, 100]])
+            .await
+            .unwrap();
+        engine.sleep().await.unwrap();
+    }
+    for file in index_files {
+        assert!(db_path.join(file).exists(), "{} was not written", file);
+    }
+
+    // 2. 世代番号が一致するため、索引ファイルから復元される
+    {
+        let engine = open().await.unwrap();
+        for file in index_files {
+            assert!(db_path.join(file).exists(), "{} was discarded", file);
+        }
+        assert_eq!(engine.find_by(0, &HashSet::from([3]), 1)[0][1], 20);
+        assert_eq!(engine.query(&tenant_user, 10)[0][0], 1);
+        assert_eq!(
+            engine.query(&Filter::Eq { lane: 1, value: 10 }, 10).len(),
+            2
+        );
+
+        // sleep せずに更新すると、索引ファイルは古くなる
+        engine.insert(&[4, 10, 100]).await.unwrap();
+    }
+
+    // 3. 古い索引ファイルは破棄され、ロードしたレーンから作り直される
+    {
+        let engine = open().await.unwrap();
+        for file in index_files {
+            assert!(!db_path.join(file).exists(), "stale {} was kept", file);
+        }
+        let ids: Vec<u128> = engine
+            .query(&tenant_user, 10)
+            .iter()
+            .map(|row| row[0])
+            .collect();
+        assert_eq!(ids, vec![4, 1]);
+        assert_eq!(engine.find_by(0, &HashSet::from([4]), 1).len(), 1);
+        engine.sleep().await.unwrap();
+    }
+
+    // 4. 索引のない構成では書き込みで世代番号を進めず、残っている索引ファイルはロード時に削除される
+    let generation = || {
+        let header = std::fs::read(db_path.join("header.bin")).unwrap();
+        u64::from_le_bytes(header[64..72].try_into().unwrap())
+    };
+    {
+        let engine = Orby::builder(label)
+            .ring_buffer_lane_item_count(10)
+            .ring_buffer_lane_count(3)
+            .with_storage(crate::types::SaveMode::Vault(Some(
+                db_path.parent().unwrap().to_path_buf(),
+            )))
+            .autoload(true)
+            .build()
+            .await
+            .unwrap();
+        for file in index_files {
+            assert!(!db_path.join(file).exists(), "unused {} was kept", file);
+        }
+        let before = generation();
+        engine.insert(&[5, 10, 100]).await.unwrap();
+        assert_eq!(generation(), before);
+    }
+
+    // 5. 索引を再び設定すると、レーンから作り直される
+    {
+        let engine = open().await.unwrap();
+        let ids: Vec<u128> = engine
+            .query(&tenant_user, 10)
+            .iter()
+            .map(|row| row[0])
+            .collect();
+        assert_eq!(ids, vec![5, 4, 1]);
+        assert_eq!(engine.find_by(0, &HashSet::from([5]), 1).len(), 1);
+
+        // 世代番号は索引ファイルを書き出した後の最初のコミットでのみ進む
+        engine.sleep().await.unwrap();
+        let saved = generation();
+        engine.insert(&[6, 10, 100]).await.unwrap();
+        assert_eq!(generation(), saved + 1);
+        engine.insert(&[7, 10, 100]).await.unwrap();
+        engine.update_by_id(0, 6, &[6, 10, 200]).await.unwrap();
+        assert_eq!(generation(), saved + 1);
+        engine.sleep().await.unwrap();
+        engine.purge_by_id(0, 7).await;
+        assert_eq!(generation(), saved + 2);
+    }
+
+    // 6. 書き出し後に更新された索引ファイルは採用されず、最新のレーンから作り直される
+    {
+        let engine = open().await.unwrap();
+        assert!(engine.find_by(0, &HashSet::from([7]), 1).is_empty());
+        assert_eq!(engine.find_by(0, &HashSet::from([6]), 1)[0][2], 200);
+    }
+
+    let _ = std::fs::remove_dir_all(&db_path);
+}
+
 #[tokio::test]
 async fn test_vault_config_mismatch() {
     let label = "test_vault_mismatch";
@@ -307,3 +443,2299 @@ async fn test_vault_config_mismatch() {
 
     let _ = std::fs::remove_dir_all(&db_path);
 }
+
+#[tokio::test]
+async fn test_table_mode_storage_full() {
+    let label = "test_table_mode";
+    let engine = Orby::builder(label)
+        .ring_buffer_lane_item_count(3)
+        .ring_buffer_lane_count(2)
+        .with_storage(SaveMode::MemoryOnly)
+        .logic_mode(LogicMode::Table)
+        .build()
+        .await
+        .unwrap();
+
+    engine
+        .insert_batch(&[[1u128, 10u128], [2u128, 20u128]])
+        .await
+        .unwrap();
+
+    // 容量を超えるバッチは一件も書き込まれずに拒否される
+    let result = engine
+        .insert_batch(&[[3u128, 30u128], [4u128, 40u128]])
+        .await;
+    assert!(matches!(result, Err(OrbyError::StorageFull { .. })));
+    assert_eq!(engine.len(), 2);
+
+    engine
+        .insert_fixed(vec![PulseCellPack::new([3u128, 30u128])])
+        .await
+        .unwrap();
+    assert_eq!(engine.len(), 3);
+
+    let result = engine
+        .insert_fixed(vec![PulseCellPack::new([4u128, 40u128])])
+        .await;
+    assert!(matches!(result, Err(OrbyError::StorageFull { .. })));
+    let result = engine.insert_lane_batch(0, &[4u128]).await;
+    assert!(matches!(result, Err(OrbyError::StorageFull { .. })));
+    let result = engine.upsert(0, 4, &[4, 40]).await;
+    assert!(matches!(result, Err(OrbyError::StorageFull { .. })));
+
+    // 既存 ID の upsert は更新なので成功する
+    engine.upsert(0, 1, &[1, 11]).await.unwrap();
+
+    // 最古のデータが上書きされていないこと
+    assert_eq!(engine.len(), 3);
+    assert_eq!(engine.get_at(2).unwrap()[0], 1);
+    assert_eq!(engine.get_at(2).unwrap()[1], 11);
+    assert_eq!(engine.get_at(0).unwrap()[0], 3);
+}
+
+#[tokio::test]
+async fn test_vault_logic_mode_mismatch() {
+    let label = "test_vault_logic_mode_mismatch";
+    let db_path = std::env::temp_dir().join(label);
+    if db_path.exists() {
+        let _ = std::fs::remove_dir_all(&db_path);
+    }
+
+    // 1. Table モードで作成
+    {
+        let engine = Orby::builder(label)
+            .ring_buffer_lane_item_count(10)
+            .ring_buffer_lane_count(2)
+            .with_storage(SaveMode::Vault(Some(
+                db_path.parent().unwrap().to_path_buf(),
+            )))
+            .logic_mode(LogicMode::Table)
+            .build()
+            .await
+            .unwrap();
+
+        engine.insert_batch(&[[1u128, 10u128]]).await.unwrap();
+    }
+
+    // 2. RingBuffer モードで開こうとするとエラー
+    {
+        let result = Orby::builder(label)
+            .ring_buffer_lane_item_count(10)
+            .ring_buffer_lane_count(2)
+            .with_storage(SaveMode::Vault(Some(
+                db_path.parent().unwrap().to_path_buf(),
+            )))
+            .logic_mode(LogicMode::RingBuffer)
+            .build()
+            .await;
+
+        match result.err().unwrap() {
+            OrbyError::ConfigMismatch { .. } => {}
+            e => panic!("Expected ConfigMismatch, got {:?}", e),
+        }
+    }
+
+    // 3. 同じモードであればロードできる
+    {
+        let engine = Orby::builder(label)
+            .ring_buffer_lane_item_count(10)
+            .ring_buffer_lane_count(2)
+            .with_storage(SaveMode::Vault(Some(
+                db_path.parent().unwrap().to_path_buf(),
+            )))
+            .logic_mode(LogicMode::Table)
+            .build()
+            .await
+            .unwrap();
+
+        assert_eq!(engine.len(), 1);
+        assert_eq!(engine.get_at(0).unwrap()[1], 10);
+    }
+
+    let _ = std::fs::remove_dir_all(&db_path);
+}
+
+#[tokio::test]
+async fn test_static_slot_reuse() {
+    let label = "test_static_slot";
+    let engine = Orby::builder(label)
+        .ring_buffer_lane_item_count(4)
+        .ring_buffer_lane_count(2)
+        .with_storage(SaveMode::MemoryOnly)
+        .logic_mode(LogicMode::StaticSlot)
+        .build()
+        .await
+        .unwrap();
+
+    assert_eq!(engine.insert(&[1, 10]).await.unwrap(), 0);
+    assert_eq!(engine.insert(&[2, 20]).await.unwrap(), 1);
+    assert_eq!(engine.insert(&[3, 30]).await.unwrap(), 2);
+
+    // 削除してもスロット番号は詰められない
+    assert!(engine.delete(1).await.unwrap());
+    assert!(engine.get_slot(1).is_none());
+    assert_eq!(engine.get_slot(2).unwrap()[0], 3);
+    assert_eq!(engine.len(), 2);
+
+    // 解放されたスロットが再利用される
+    assert_eq!(engine.insert(&[4, 40]).await.unwrap(), 1);
+    assert_eq!(engine.insert(&[5, 50]).await.unwrap(), 3);
+    let result = engine.insert(&[6, 60]).await;
+    assert!(matches!(result, Err(OrbyError::StorageFull { .. })));
+
+    // スロットを直接指定して上書き
+    engine.insert_at(2, &[7, 70]).await.unwrap();
+    assert_eq!(engine.get_slot(2).unwrap()[1], 70);
+    assert_eq!(engine.len(), 4);
+
+    let result = engine.insert_at(4, &[8, 80]).await;
+    assert!(matches!(result, Err(OrbyError::SlotOutOfRange { .. })));
+}
+
+#[tokio::test]
+async fn test_static_slot_vault_reload() {
+    let label = "test_static_slot_vault_reload";
+    let db_path = std::env::temp_dir().join(label);
+    if db_path.exists() {
+        let _ = std::fs::remove_dir_all(&db_path);
+    }
+
+    let build = || {
+        Orby::builder(label)
+            .ring_buffer_lane_item_count(10)
+            .ring_buffer_lane_count(2)
+            .with_storage(SaveMode::Vault(Some(
+                db_path.parent().unwrap().to_path_buf(),
+            )))
+            .logic_mode(LogicMode::StaticSlot)
+            .build()
+    };
+
+    // 1. スロット 0..3 を使い、1 を解放。5 を直接指定して 3, 4 を飛び越える
+    {
+        let engine = build().await.unwrap();
+        engine
+            .insert_batch(&[[1u128, 10u128], [2, 20], [3, 30]])
+            .await
+            .unwrap();
+        assert!(engine.delete(1).await.unwrap());
+        engine.insert_at(5, &[6, 60]).await.unwrap();
+    }
+
+    // 2. 再起動後、空きリストが復元され小さい番号から再利用される
+    {
+        let engine = build().await.unwrap();
+        assert_eq!(engine.len(), 3);
+        assert_eq!(engine.get_slot(5).unwrap()[0], 6);
+        assert_eq!(engine.insert(&[7, 70]).await.unwrap(), 1);
+        assert_eq!(engine.insert(&[8, 80]).await.unwrap(), 3);
+        assert_eq!(engine.insert(&[9, 90]).await.unwrap(), 4);
+        assert_eq!(engine.insert(&[10, 100]).await.unwrap(), 6);
+    }
+
+    let _ = std::fs::remove_dir_all(&db_path);
+}
+
+#[tokio::test]
+async fn test_sorted_mode_range_lookup() {
+    let label = "test_sorted_mode";
+    let engine = Orby::builder(label)
+        .ring_buffer_lane_item_count(10)
+        .ring_buffer_lane_count(2)
+        .with_storage(SaveMode::MemoryOnly)
+        .logic_mode(LogicMode::Sorted)
+        .key_lane(1)
+        .build()
+        .await
+        .unwrap();
+
+    engine
+        .insert_batch(&[[1u128, 50u128], [2, 10], [3, 30], [4, 20], [5, 40]])
+        .await
+        .unwrap();
+
+    // 物理的にはキーレーン（lane 1）の昇順に並ぶ。get_at(0) は最大キー
+    assert_eq!(engine.get_at(0).unwrap()[1], 50);
+    assert_eq!(engine.get_at(4).unwrap()[1], 10);
+
+    let results = engine.find_custom(1, 20, 40, 10);
+    let keys: Vec<u128> = results.iter().map(|r| r[1]).collect();
+    assert_eq!(keys, vec![40, 30, 20]);
+
+    let targets: HashSet<u128> = [10u128, 50, 99].into_iter().collect();
+    let results = engine.find_by(1, &targets, 10);
+    let keys: Vec<u128> = results.iter().map(|r| r[1]).collect();
+    assert_eq!(keys, vec![50, 10]);
+
+    // キーが変わる更新は順序を保つ位置へ移動する
+    assert!(engine.update_by_id(0, 2, &[2, 60]).await.unwrap());
+    assert_eq!(engine.get_at(0).unwrap()[0], 2);
+
+    // 削除後も詰められて順序が維持される
+    engine.purge_by_id(1, 30).await;
+    assert_eq!(engine.len(), 4);
+    let keys: Vec<u128> = engine.take(10).iter().map(|r| r[1]).collect();
+    assert_eq!(keys, vec![60, 50, 40, 20]);
+}
+
+#[tokio::test]
+async fn test_sorted_mode_vault_reload() {
+    let label = "test_sorted_mode_vault_reload";
+    let db_path = std::env::temp_dir().join(label);
+    if db_path.exists() {
+        let _ = std::fs::remove_dir_all(&db_path);
+    }
+
+    let build = || {
+        Orby::builder(label)
+            .ring_buffer_lane_item_count(10)
+            .ring_buffer_lane_count(2)
+            .with_storage(SaveMode::Vault(Some(
+                db_path.parent().unwrap().to_path_buf(),
+            )))
+            .logic_mode(LogicMode::Sorted)
+            .build()
+    };
+
+    {
+        let engine = build().await.unwrap();
+        engine
+            .insert_batch(&[[30u128, 3u128], [10, 1], [20, 2]])
+            .await
+            .unwrap();
+        engine.insert(&[15, 4]).await.unwrap();
+        assert!(engine.delete(2).await.unwrap()); // key 20
+    }
+
+    {
+        let engine = build().await.unwrap();
+        assert_eq!(engine.len(), 3);
+        let keys: Vec<u128> = engine.take(10).iter().map(|r| r[0]).collect();
+        assert_eq!(keys, vec![30, 15, 10]);
+        assert_eq!(engine.find_custom(0, 11, 30, 10).len(), 2);
+
+        // キーレーン以外の更新はその場で、キーが変わる更新は順序を保つ位置へ移動する
+        assert!(engine.update_by_id(1, 3, &[30, 5]).await.unwrap());
+        assert_eq!(engine.get_at(0).unwrap().to_vec(), vec![30, 5]);
+        assert!(engine.update_by_id(0, 10, &[40, 1]).await.unwrap());
+    }
+
+    {
+        let engine = build().await.unwrap();
+        assert_eq!(engine.len(), 3);
+        let rows: Vec<Vec<u128>> = engine.take(10).iter().map(|r| r.to_vec()).collect();
+        assert_eq!(rows, vec![vec![40, 1], vec![30, 5], vec![15, 4]]);
+    }
+
+    let _ = std::fs::remove_dir_all(&db_path);
+}
+
+#[tokio::test]
+async fn test_hash_slot_point_ops() {
+    let engine = Orby::builder("test_hash_slot_point_ops")
+        .with_storage(SaveMode::MemoryOnly)
+        .ring_buffer_lane_item_count(8)
+        .ring_buffer_lane_count(2)
+        .logic_mode(LogicMode::HashSlot)
+        .build()
+        .await
+        .unwrap();
+
+    for key in 1..=8u128 {
+        engine.insert(&[key, key * 10]).await.unwrap();
+    }
+    assert_eq!(engine.len(), 8);
+    for key in 1..=8u128 {
+        assert_eq!(engine.get_by_key(key).unwrap()[1], key * 10);
+    }
+
+    // 既存キーの挿入は上書きになり、満杯でも受け付ける
+    engine.insert(&[3, 333]).await.unwrap();
+    assert_eq!(engine.get_by_key(3).unwrap()[1], 333);
+    assert_eq!(engine.len(), 8);
+
+    // 新規キーは満杯のため拒否される
+    let res = engine.insert(&[9, 90]).await;
+    assert!(matches!(res, Err(OrbyError::StorageFull { .. })));
+    let res = engine.get_by_key(0);
+    assert!(res.is_none());
+
+    // 削除後もクラスタ内の全キーへ到達できる
+    engine.purge_by_id(0, 5).await;
+    assert!(engine.get_by_key(5).is_none());
+    assert_eq!(engine.len(), 7);
+    for key in (1..=8u128).filter(|&k| k != 5) {
+        assert!(engine.get_by_key(key).is_some(), "key {} lost", key);
+    }
+
+    engine.upsert(0, 9, &[9, 90]).await.unwrap();
+    assert_eq!(engine.get_by_key(9).unwrap()[1], 90);
+    assert!(engine.update_by_id(0, 9, &[10, 100]).await.unwrap());
+    assert!(engine.get_by_key(9).is_none());
+    assert_eq!(engine.get_by_key(10).unwrap()[1], 100);
+
+    // 別の行が持つキーへの変更は、どちらの行も書き換えずに拒否される
+    assert!(!engine.update_by_id(0, 10, &[1, 111]).await.unwrap());
+    assert_eq!(engine.len(), 8);
+    assert_eq!(engine.get_by_key(1).unwrap()[1], 10);
+    assert_eq!(engine.get_by_key(10).unwrap()[1], 100);
+    let res = engine.upsert(0, 10, &[1, 111]).await;
+    assert!(matches!(res, Err(OrbyError::DuplicateKey { value: 1, .. })));
+    assert_eq!(engine.get_by_key(10).unwrap()[1], 100);
+
+    // 複数の行を一つのキーへ集約する更新も拒否される
+    assert!(engine.update_by_id(0, 2, &[2, 7]).await.unwrap());
+    assert!(engine.update_by_id(0, 4, &[4, 7]).await.unwrap());
+    assert!(!engine.update_by_id(1, 7, &[42, 7]).await.unwrap());
+    assert_eq!(engine.len(), 8);
+    assert!(engine.get_by_key(42).is_none());
+
+    let targets: HashSet<u128> = [1, 2, 42].into_iter().collect();
+    assert_eq!(engine.find_by(0, &targets, 10).len(), 2);
+}
+
+#[tokio::test]
+async fn test_hash_slot_vault_reload() {
+    let label = "test_hash_slot_vault_reload";
+    let db_path = std::env::temp_dir().join(label);
+    if db_path.exists() {
+        let _ = std::fs::remove_dir_all(&db_path);
+    }
+
+    let build = || {
+        Orby::builder(label)
+            .ring_buffer_lane_item_count(16)
+            .ring_buffer_lane_count(2)
+            .with_storage(SaveMode::Vault(Some(
+                db_path.parent().unwrap().to_path_buf(),
+            )))
+            .logic_mode(LogicMode::HashSlot)
+            .build()
+    };
+
+    {
+        let engine = build().await.unwrap();
+        let rows: Vec<[u128; 2]> = (1..=12u128).map(|k| [k, k + 100]).collect();
+        engine.insert_batch(&rows).await.unwrap();
+        engine.purge_by_id(0, 4).await;
+        engine.upsert(0, 7, &[7, 777]).await.unwrap();
+    }
+
+    {
+        let engine = build().await.unwrap();
+        assert_eq!(engine.len(), 11);
+        assert!(engine.get_by_key(4).is_none());
+        assert_eq!(engine.get_by_key(7).unwrap()[1], 777);
+        assert_eq!(engine.get_by_key(12).unwrap()[1], 112);
+    }
+
+    let _ = std::fs::remove_dir_all(&db_path);
+}
+
+#[tokio::test]
+async fn test_pop_oldest_and_newest() {
+    let engine = Orby::builder("test_pop_oldest_and_newest")
+        .with_storage(SaveMode::MemoryOnly)
+        .ring_buffer_lane_item_count(4)
+        .ring_buffer_lane_count(2)
+        .build()
+        .await
+        .unwrap();
+
+    // 周回させて、最古の行が物理末尾側に来る状態を作る
+    for i in 1..=6u128 {
+        engine.insert_batch(vec![vec![i, i * 10]]).await.unwrap();
+    }
+    assert_eq!(engine.len(), 4);
+
+    let oldest = engine.pop_oldest(1).await.unwrap();
+    assert_eq!(oldest[0][0], 3);
+    let newest = engine.pop_newest(2).await.unwrap();
+    let keys: Vec<u128> = newest.iter().map(|r| r[0]).collect();
+    assert_eq!(keys, vec![6, 5]);
+    assert_eq!(engine.len(), 1);
+    assert_eq!(engine.take(10)[0][0], 4);
+
+    // 追加後も FIFO 順序が維持される
+    engine
+        .insert_batch(vec![vec![7, 70], vec![8, 80], vec![9, 90]])
+        .await
+        .unwrap();
+    let keys: Vec<u128> = engine.take(10).iter().map(|r| r[0]).collect();
+    assert_eq!(keys, vec![9, 8, 7, 4]);
+    let keys: Vec<u128> = engine
+        .pop_oldest(10)
+        .await
+        .unwrap()
+        .iter()
+        .map(|r| r[0])
+        .collect();
+    assert_eq!(keys, vec![4, 7, 8, 9]);
+    assert!(engine.is_empty());
+    assert!(engine.pop_newest(1).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_pop_vault_reload() {
+    let label = "test_pop_vault_reload";
+    let db_path = std::env::temp_dir().join(label);
+    if db_path.exists() {
+        let _ = std::fs::remove_dir_all(&db_path);
+    }
+
+    let build = || {
+        Orby::builder(label)
+            .ring_buffer_lane_item_count(4)
+            .ring_buffer_lane_count(2)
+            .with_storage(SaveMode::Vault(Some(
+                db_path.parent().unwrap().to_path_buf(),
+            )))
+            .build()
+    };
+
+    {
+        let engine = build().await.unwrap();
+        let rows: Vec<[u128; 2]> = (1..=5u128).map(|i| [i, i]).collect();
+        engine.insert_batch(&rows).await.unwrap();
+        engine.pop_oldest(2).await.unwrap();
+    }
+
+    {
+        let engine = build().await.unwrap();
+        assert_eq!(engine.len(), 2);
+        let keys: Vec<u128> = engine
+            .pop_oldest(2)
+            .await
+            .unwrap()
+            .iter()
+            .map(|r| r[0])
+            .collect();
+        assert_eq!(keys, vec![4, 5]);
+    }
+
+    let _ = std::fs::remove_dir_all(&db_path);
+}
+
+#[tokio::test]
+async fn test_compaction_after_pop_and_wraparound() {
+    let label = "test_compaction_after_pop_and_wraparound";
+    let db_path = std::env::temp_dir().join(label);
+    if db_path.exists() {
+        let _ = std::fs::remove_dir_all(&db_path);
+    }
+
+    let build = || {
+        Orby::builder(label)
+            .ring_buffer_lane_item_count(4)
+            .ring_buffer_lane_count(2)
+            .compaction(true)
+            .with_storage(SaveMode::Vault(Some(
+                db_path.parent().unwrap().to_path_buf(),
+            )))
+            .build()
+    };
+
+    {
+        let engine = build().await.unwrap();
+        // 物理配置: [5, 6, 3, 4]、最古の行（3）はスロット 2
+        for i in 1..=6u128 {
+            engine.insert(&[i, i * 10]).await.unwrap();
+        }
+        assert_eq!(engine.pop_oldest(1).await.unwrap()[0][0], 3);
+
+        // スロット 3 の行（4）を削除すると、折り返した 5, 6 が前へ詰められる
+        assert!(engine.delete(3).await.unwrap());
+        assert_eq!(engine.len(), 2);
+        let keys: Vec<u128> = engine.take(10).iter().map(|r| r[0]).collect();
+        assert_eq!(keys, vec![6, 5]);
+
+        // 詰めた後のカーソル位置から追記される
+        engine.insert(&[7, 70]).await.unwrap();
+        let keys: Vec<u128> = engine.take(10).iter().map(|r| r[0]).collect();
+        assert_eq!(keys, vec![7, 6, 5]);
+    }
+
+    {
+        let engine = build().await.unwrap();
+        let keys: Vec<u128> = engine
+            .pop_oldest(10)
+            .await
+            .unwrap()
+            .iter()
+            .map(|r| r[0])
+            .collect();
+        assert_eq!(keys, vec![5, 6, 7]);
+    }
+
+    let _ = std::fs::remove_dir_all(&db_path);
+}
+
+#[tokio::test]
+async fn test_zero_rows_are_live_data() {
+    let engine = Orby::builder("test_zero_rows_are_live_data")
+        .ring_buffer_lane_item_count(4)
+        .ring_buffer_lane_count(2)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+
+    // 全次元ゼロの行も墓標ではなく生存行として扱われる
+    engine.insert_batch(&[[0u128, 0], [0, 7]]).await.unwrap();
+    assert_eq!(engine.count_active(), 2);
+    assert_eq!(engine.query_raw(|row| row[0].as_u128() == 0, 10).len(), 2);
+    assert_eq!(engine.query_iter(|row| row[1].as_u128() == 0).count(), 1);
+
+    // ゼロ値を指定した削除も機能する
+    engine.purge_by_id(1, 0).await;
+    assert_eq!(engine.count_active(), 1);
+    let rows = engine.query_raw(|_| true, 10);
+    assert_eq!(rows.len(), 1);
+    assert_eq!(&rows[0][..], &[0, 7]);
+
+    // get_at は墓標を数えずに生存行を最新順に辿る
+    engine.insert_batch(&[[1u128, 1], [2, 2]]).await.unwrap();
+    engine.purge_by_id(0, 2).await;
+    assert_eq!(engine.get_at(0).as_deref(), Some(&[1u128, 1][..]));
+    assert_eq!(engine.get_at(1).as_deref(), Some(&[0u128, 7][..]));
+    assert!(engine.get_at(2).is_none());
+
+    // ゼロキーはハッシュ配置でも使用できる
+    let dict = Orby::builder("test_zero_rows_are_live_data_hash")
+        .ring_buffer_lane_item_count(4)
+        .ring_buffer_lane_count(2)
+        .logic_mode(LogicMode::HashSlot)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    dict.insert(&[0, 0]).await.unwrap();
+    assert_eq!(dict.get_by_key(0).as_deref(), Some(&[0u128, 0][..]));
+
+    // ハッシュ配置でも get_at は全生存行を一度ずつ返す
+    dict.insert_batch(&[[1u128, 1], [2, 2]]).await.unwrap();
+    dict.purge_by_id(0, 1).await;
+    let mut keys: Vec<u128> = (0..dict.len())
+        .map(|i| dict.get_at(i).unwrap()[0])
+        .collect();
+    keys.sort_unstable();
+    assert_eq!(keys, vec![0, 2]);
+}
+
+#[tokio::test]
+async fn test_validity_bitmap_vault_reload() {
+    let label = "test_validity_bitmap_vault_reload";
+    let db_path = std::env::temp_dir().join(label);
+    if db_path.exists() {
+        let _ = std::fs::remove_dir_all(&db_path);
+    }
+
+    let build = || {
+        Orby::builder(label)
+            .ring_buffer_lane_item_count(4)
+            .ring_buffer_lane_count(2)
+            .with_storage(SaveMode::Vault(Some(
+                db_path.parent().unwrap().to_path_buf(),
+            )))
+            .build()
+    };
+
+    {
+        let engine = build().await.unwrap();
+        engine
+            .insert_batch(&[[0u128, 0], [1, 1], [2, 2]])
+            .await
+            .unwrap();
+        engine.purge_by_id(0, 1).await;
+    }
+
+    {
+        let engine = build().await.unwrap();
+        assert_eq!(engine.count_active(), 2);
+        let mut keys: Vec<u128> = engine
+            .query_raw(|_| true, 10)
+            .iter()
+            .map(|r| r[0])
+            .collect();
+        keys.sort_unstable();
+        assert_eq!(keys, vec![0, 2]);
+    }
+
+    let _ = std::fs::remove_dir_all(&db_path);
+}
+
+#[tokio::test]
+async fn test_top_k_keeps_best_rows() {
+    let engine = Orby::builder("test_top_k_keeps_best_rows")
+        .ring_buffer_lane_item_count(3)
+        .ring_buffer_lane_count(2)
+        .logic_mode(LogicMode::TopK(RankOrder::Largest))
+        .key_lane(1)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+
+    engine
+        .insert_batch(&[[1u128, 30], [2, 10], [3, 20]])
+        .await
+        .unwrap();
+
+    // 順位に入らない行のみが行ごとに報告され、残りの行は最下位の行を追い出して書き込まれる
+    let outcomes = engine.insert_batch(&[[4u128, 40], [5, 10]]).await.unwrap();
+    assert!(matches!(outcomes[0], InsertOutcome::Inserted(_)));
+    assert_eq!(outcomes[1], InsertOutcome::RankTooLow);
+    assert_eq!(engine.len(), 3);
+    assert!(engine.get_by_key(10).is_none());
+
+    // 1 行の挿入ではエラーとして返される
+    assert!(matches!(
+        engine.insert(&[7, 20]).await,
+        Err(OrbyError::RankTooLow {
+            value: 20,
+            threshold: 20,
+            ..
+        })
+    ));
+
+    // 上位の行は最下位の行を追い出す
+    engine.insert(&[6, 25]).await.unwrap();
+    let scores = |engine: &Orby| -> Vec<u128> {
+        engine
+            .query_raw(|_| true, 10)
+            .iter()
+            .map(|r| r[1])
+            .collect()
+    };
+    assert_eq!(scores(&engine), vec![40, 30, 25]);
+    assert!(engine.get_by_key(20).is_none());
+
+    // キーを最下位より下位へ変更する更新は、追い出した行より下位になり得るため拒否される
+    for (id, key) in [(1u128, 5u128), (6, 24)] {
+        assert!(matches!(
+            engine.update_by_id(0, id, &[id, key]).await,
+            Err(OrbyError::RankTooLow { value, threshold: 25, .. }) if value == key
+        ));
+        assert!(matches!(
+            engine.upsert(0, id, &[id, key]).await,
+            Err(OrbyError::RankTooLow { .. })
+        ));
+    }
+    assert_eq!(scores(&engine), vec![40, 30, 25]);
+
+    // 最下位以上への変更は受け入れられ、キー順に並べ直される
+    assert!(engine.update_by_id(0, 1, &[1, 27]).await.unwrap());
+    assert!(engine.update_by_id(0, 6, &[6, 50]).await.unwrap());
+    assert_eq!(scores(&engine), vec![50, 40, 27]);
+
+    let fastest = Orby::builder("test_top_k_keeps_best_rows_smallest")
+        .ring_buffer_lane_item_count(2)
+        .ring_buffer_lane_count(1)
+        .logic_mode(LogicMode::TopK(RankOrder::Smallest))
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    fastest
+        .insert_batch(&[[50u128], [70], [60], [40]])
+        .await
+        .unwrap();
+    let times: Vec<u128> = fastest
+        .query_raw(|_| true, 10)
+        .iter()
+        .map(|r| r[0])
+        .collect();
+    assert_eq!(times, vec![50, 40]);
+    assert!(fastest.insert(&[55]).await.is_err());
+}
+
+#[tokio::test]
+async fn test_top_k_aof_replay() {
+    let label = "test_top_k_aof_replay";
+    let aof_path = std::path::PathBuf::from(format!("{}.aof", label));
+    let _ = std::fs::remove_file(&aof_path);
+
+    let build = || {
+        Orby::builder(label)
+            .ring_buffer_lane_item_count(3)
+            .ring_buffer_lane_count(2)
+            .logic_mode(LogicMode::TopK(RankOrder::Largest))
+            .with_storage(SaveMode::MemoryOnly)
+    };
+
+    let expected: Vec<Vec<u128>> = {
+        let engine = build().enable_aof(true).build().await.unwrap();
+        for score in [5u128, 1, 9, 3, 7, 2, 8] {
+            let _ = engine.insert(&[score, score * 10]).await;
+        }
+        engine.purge_by_id(0, 8).await;
+        engine.insert(&[6, 60]).await.unwrap();
+        // AOF ワーカーの書き込み完了を待つ
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        engine
+            .query_raw(|_| true, 10)
+            .iter()
+            .map(|r| r.to_vec())
+            .collect()
+    };
+    assert_eq!(expected, vec![vec![9, 90], vec![7, 70], vec![6, 60]]);
+
+    let restored = build().from_file(&aof_path).build().await.unwrap();
+    let rows: Vec<Vec<u128>> = restored
+        .query_raw(|_| true, 10)
+        .iter()
+        .map(|r| r.to_vec())
+        .collect();
+    assert_eq!(rows, expected);
+
+    let _ = std::fs::remove_file(&aof_path);
+}
+
+/// 指定したミリ秒タイムスタンプを先頭 48bit に持つ UUIDv7 風の値を生成します。
+fn uuid_v7_at(millis: u64, seq: u128) -> u128 {
+    ((millis as u128) << 80) | (0x7 << 76) | seq
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[tokio::test]
+async fn test_retention_hides_and_purges_expired_rows() {
+    let engine = Orby::builder("test_retention_hides_and_purges_expired_rows")
+        .ring_buffer_lane_item_count(8)
+        .ring_buffer_lane_count(2)
+        .retention(std::time::Duration::from_secs(60))
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+
+    let now = now_millis();
+    let old = now - 120_000;
+    engine
+        .insert_batch(&[
+            [uuid_v7_at(old, 1), 1],
+            [uuid_v7_at(old, 2), 2],
+            [uuid_v7_at(now, 3), 3],
+        ])
+        .await
+        .unwrap();
+
+    // 期限切れの行はパージ前でもクエリから見えない
+    let rows = engine.query_raw(|_| true, 10);
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0][1], 3);
+    assert_eq!(engine.query_iter(|_| true).count(), 1);
+    assert_eq!(engine.find_indices(|_| true, 10).len(), 1);
+
+    assert_eq!(engine.purge_expired().await.unwrap(), 2);
+    assert_eq!(engine.len(), 1);
+    assert_eq!(engine.purge_expired().await.unwrap(), 0);
+    let popped = engine.pop_oldest(1).await.unwrap();
+    assert_eq!(popped[0][1], 3);
+
+    // 保持期間は RingBuffer / Table 専用
+    let res = Orby::builder("test_retention_hides_and_purges_expired_rows_sorted")
+        .ring_buffer_lane_item_count(8)
+        .ring_buffer_lane_count(2)
+        .logic_mode(LogicMode::Sorted)
+        .retention(std::time::Duration::from_secs(60))
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await;
+    assert!(matches!(res, Err(OrbyError::ConfigMismatch { .. })));
+}
+
+#[tokio::test]
+async fn test_retention_vault_reload() {
+    let label = "test_retention_vault_reload";
+    let db_path = std::env::temp_dir().join(label);
+    if db_path.exists() {
+        let _ = std::fs::remove_dir_all(&db_path);
+    }
+
+    let build = || {
+        Orby::builder(label)
+            .ring_buffer_lane_item_count(4)
+            .ring_buffer_lane_count(2)
+            .retention(std::time::Duration::from_secs(60))
+            .with_storage(SaveMode::Vault(Some(
+                db_path.parent().unwrap().to_path_buf(),
+            )))
+            .build()
+    };
+
+    let now = now_millis();
+    {
+        let engine = build().await.unwrap();
+        engine
+            .insert_batch(&[[uuid_v7_at(now - 120_000, 1), 1], [uuid_v7_at(now, 2), 2]])
+            .await
+            .unwrap();
+        assert_eq!(engine.purge_expired().await.unwrap(), 1);
+    }
+
+    {
+        // 前進した tail が Vault のヘッダから復元される
+        let engine = build().await.unwrap();
+        assert_eq!(engine.len(), 1);
+        engine.insert(&[uuid_v7_at(now, 3), 3]).await.unwrap();
+        let values: Vec<u128> = engine
+            .pop_oldest(2)
+            .await
+            .unwrap()
+            .iter()
+            .map(|r| r[1])
+            .collect();
+        assert_eq!(values, vec![2, 3]);
+    }
+
+    let _ = std::fs::remove_dir_all(&db_path);
+}
+
+#[tokio::test]
+async fn test_unique_lane_rejects_duplicates() {
+    let engine = Orby::builder("test_unique_lane_rejects_duplicates")
+        .ring_buffer_lane_item_count(3)
+        .ring_buffer_lane_count(2)
+        .unique_lane(0)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+
+    engine.insert_batch(&[[1u128, 10], [2, 20]]).await.unwrap();
+
+    // 既存の値とバッチ内の重複は書き込まれず、行ごとに報告される
+    let outcomes = engine
+        .insert_batch(&[[2u128, 21], [3, 30], [3, 31]])
+        .await
+        .unwrap();
+    assert_eq!(
+        outcomes,
+        vec![
+            InsertOutcome::Duplicate,
+            InsertOutcome::Inserted(2),
+            InsertOutcome::Duplicate
+        ]
+    );
+    assert_eq!(engine.len(), 3);
+    let res = engine.insert(&[1, 11]).await;
+    assert!(matches!(
+        res,
+        Err(OrbyError::DuplicateKey {
+            lane: 0,
+            value: 1,
+            ..
+        })
+    ));
+
+    // 周回で上書きされた値は再び挿入できる
+    let outcomes = engine
+        .insert_fixed(vec![PulseCellPack::new([4u128, 40])])
+        .await
+        .unwrap();
+    assert_eq!(outcomes, vec![InsertOutcome::Inserted(0)]);
+    assert_eq!(engine.insert(&[1, 12]).await.unwrap(), 1);
+
+    // 削除された値も同様
+    engine.purge_by_id(0, 3).await;
+    assert!(engine.insert(&[3, 32]).await.is_ok());
+
+    // 行を読み飛ばせない書き込みでも、重複は同じ DuplicateKey として拒否される
+    assert!(matches!(
+        engine.update_by_id(0, 3, &[1, 33]).await,
+        Err(OrbyError::DuplicateKey { pool_name, lane: 0, value: 1 })
+            if pool_name == "test_unique_lane_rejects_duplicates"
+    ));
+    assert!(matches!(
+        engine.upsert(0, 9, &[1, 90]).await,
+        Err(OrbyError::DuplicateKey {
+            lane: 0,
+            value: 1,
+            ..
+        })
+    ));
+    assert_eq!(engine.query(&Filter::Eq { lane: 0, value: 3 }, 1)[0][1], 32);
+    assert_eq!(engine.len(), 3);
+}
+
+#[tokio::test]
+async fn test_unique_policies_per_lane() {
+    let engine = Orby::builder("test_unique_policies_per_lane")
+        .ring_buffer_lane_item_count(8)
+        .ring_buffer_lane_count(3)
+        .unique_lane(0)
+        .unique(1)
+        .unique(2)
+        .unique_lane(2)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    engine.insert(&[1, 10, 100]).await.unwrap();
+
+    // 読み飛ばすレーン（0 と、後から unique_lane で指定し直した 2）の重複は行ごとに除外される
+    let outcomes = engine
+        .insert_batch(&[[1u128, 11, 101], [2, 12, 100], [3, 13, 103]])
+        .await
+        .unwrap();
+    assert_eq!(
+        outcomes,
+        vec![
+            InsertOutcome::Duplicate,
+            InsertOutcome::Duplicate,
+            InsertOutcome::Inserted(1)
+        ]
+    );
+    assert!(matches!(
+        engine.insert(&[4, 14, 103]).await,
+        Err(OrbyError::DuplicateKey {
+            lane: 2,
+            value: 103,
+            ..
+        })
+    ));
+
+    // 拒否するレーン 1 の重複は、書き込み全体をエラーにする
+    assert!(matches!(
+        engine.insert_batch(&[[5u128, 15, 105], [6, 10, 106]]).await,
+        Err(OrbyError::DuplicateKey {
+            lane: 1,
+            value: 10,
+            ..
+        })
+    ));
+    assert_eq!(engine.len(), 2);
+}
+
+#[tokio::test]
+async fn test_unique_requires_ring_modes() {
+    for mode in [
+        LogicMode::StaticSlot,
+        LogicMode::Sorted,
+        LogicMode::HashSlot,
+        LogicMode::TopK(RankOrder::Largest),
+    ] {
+        for skip in [true, false] {
+            let builder = Orby::builder("test_unique_requires_ring_modes")
+                .ring_buffer_lane_item_count(8)
+                .ring_buffer_lane_count(2)
+                .logic_mode(mode)
+                .with_storage(SaveMode::MemoryOnly);
+            let builder = if skip {
+                builder.unique_lane(1)
+            } else {
+                builder.unique(1)
+            };
+            assert!(
+                matches!(builder.build().await, Err(OrbyError::ConfigMismatch { .. })),
+                "{:?} accepted a unique lane",
+                mode
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_lane_options_require_ring_modes() {
+    type LaneOption = fn(OrbyBuilder) -> OrbyBuilder;
+    let options: [(&str, LaneOption); 7] = [
+        ("index_lane", |b| b.index_lane(1)),
+        ("mask_index", |b| b.mask_index(1, 0xF0)),
+        ("prefix_index", |b| b.prefix_index(1, 4)),
+        ("bloom_lane", |b| b.bloom_lane(1)),
+        ("approx_distinct_lane", |b| b.approx_distinct_lane(1)),
+        ("bitmap_lane", |b| b.bitmap_lane(1)),
+        ("composite_index", |b| b.composite_index(&[0, 1])),
+    ];
+    for mode in [
+        LogicMode::StaticSlot,
+        LogicMode::Sorted,
+        LogicMode::HashSlot,
+        LogicMode::TopK(RankOrder::Largest),
+    ] {
+        for (name, option) in options {
+            let builder = Orby::builder("test_lane_options_require_ring_modes")
+                .ring_buffer_lane_item_count(8)
+                .ring_buffer_lane_count(2)
+                .logic_mode(mode)
+                .with_storage(SaveMode::MemoryOnly);
+            assert!(
+                matches!(
+                    option(builder).build().await,
+                    Err(OrbyError::ConfigMismatch { .. })
+                ),
+                "{:?} accepted {}",
+                mode,
+                name
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_unique_lane_vault_reload() {
+    let label = "test_unique_lane_vault_reload";
+    let db_path = std::env::temp_dir().join(label);
+    if db_path.exists() {
+        let _ = std::fs::remove_dir_all(&db_path);
+    }
+
+    let build = || {
+        Orby::builder(label)
+            .ring_buffer_lane_item_count(4)
+            .ring_buffer_lane_count(2)
+            .unique_lane(1)
+            .with_storage(SaveMode::Vault(Some(
+                db_path.parent().unwrap().to_path_buf(),
+            )))
+            .build()
+    };
+
+    {
+        let engine = build().await.unwrap();
+        engine.insert_batch(&[[1u128, 10], [2, 20]]).await.unwrap();
+    }
+
+    {
+        // 索引はロードしたデータから再構築される
+        let engine = build().await.unwrap();
+        let outcomes = engine.insert_batch(&[[3u128, 20], [4, 40]]).await.unwrap();
+        assert_eq!(
+            outcomes,
+            vec![InsertOutcome::Duplicate, InsertOutcome::Inserted(2)]
+        );
+    }
+
+    let _ = std::fs::remove_dir_all(&db_path);
+}
+
+#[tokio::test]
+async fn test_shards_merge_newest_first() {
+    let engine = Orby::builder("test_shards_merge_newest_first")
+        .ring_buffer_lane_item_count(64)
+        .ring_buffer_lane_count(2)
+        .shards(4)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+
+    let rows: Vec<[u128; 2]> = (1..=20u128).map(|i| [i, i * 10]).collect();
+    engine.insert_batch(&rows).await.unwrap();
+    engine.insert(&[21, 210]).await.unwrap();
+    assert_eq!(engine.len(), 21);
+    assert_eq!(engine.meta(), (21, 64, 2));
+
+    // シャードをまたいでも挿入順の最新から返される
+    let keys: Vec<u128> = engine.take(5).iter().map(|r| r[0]).collect();
+    assert_eq!(keys, vec![21, 20, 19, 18, 17]);
+    assert!(engine.take(1)[0].len() == 2);
+
+    let found = engine.find_by(1, &HashSet::from([30, 150]), 10);
+    let keys: Vec<u128> = found.iter().map(|r| r[0]).collect();
+    assert_eq!(keys, vec![15, 3]);
+
+    // 更新しても並び順は変わらない
+    assert!(engine.update_by_id(0, 5, &[5, 55]).await.unwrap());
+    assert_eq!(&engine.get_by_key(5).unwrap()[..], &[5, 55]);
+    assert_eq!(engine.take(1)[0][0], 21);
+
+    engine.purge_by_id(0, 5).await;
+    assert_eq!(engine.count_active(), 20);
+    assert!(engine.get_by_key(5).is_none());
+
+    // get_at / find_indices は統合した挿入順で数え、物理位置に依存する操作は使用できない
+    assert_eq!(engine.get_at(0).unwrap()[0], 21);
+    assert_eq!(engine.get_at(16).unwrap()[0], 4);
+    assert!(engine.get_at(20).is_none());
+    assert_eq!(engine.find_indices(|_| true, 3), vec![0, 1, 2]);
+    assert_eq!(
+        engine.find_indices(|row| row[0].as_u128() == 4, 10),
+        vec![16]
+    );
+    assert_eq!(
+        engine.find_indices_where(&Filter::Eq { lane: 1, value: 40 }, 10),
+        vec![16]
+    );
+    assert!(engine
+        .find_indices_where(&Filter::Eq { lane: 2, value: 0 }, 10)
+        .is_empty());
+
+    // ゾーンマップは各シャードのブロックを連結した位置で返される
+    let zones = engine.zone_map(1);
+    assert!(!zones.is_empty());
+    assert!(zones
+        .iter()
+        .all(|zone| zone.start < zone.end && zone.end <= 64));
+    let merged = zones
+        .iter()
+        .map(|zone| (zone.min, zone.max))
+        .reduce(|(lo, hi), (min, max)| (lo.min(min), hi.max(max)));
+    assert_eq!(merged, engine.lane_range(1));
+    assert_eq!(merged, Some((10, 210)));
+    let iterate = std::panic::AssertUnwindSafe(|| engine.query_iter(|_| true).count());
+    assert!(std::panic::catch_unwind(iterate).is_err());
+    assert!(matches!(
+        engine.delete(0).await,
+        Err(OrbyError::ConfigMismatch { .. })
+    ));
+    assert_eq!(engine.count_active(), 20);
+    assert!(matches!(
+        engine.pop_oldest(1).await,
+        Err(OrbyError::ConfigMismatch { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_shards_table_batch_is_atomic() {
+    let engine = Orby::builder("test_shards_table_batch_is_atomic")
+        .ring_buffer_lane_item_count(8)
+        .ring_buffer_lane_count(2)
+        .shards(2)
+        .logic_mode(LogicMode::Table)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    let shard_of = |key: u128| crate::logic::hash::home_slot(key, 2);
+    // 後ろのシャード（1）だけを満杯にする
+    for key in (0..).filter(|&k| shard_of(k) == 1).take(4) {
+        engine.insert(&[key, 0]).await.unwrap();
+    }
+    let stored = engine.count_active();
+    assert_eq!(stored, 4);
+
+    // 先に書き込まれるシャード（0）への行を含むバッチも、一行も書き込まれずに拒否される
+    let batch: Vec<[u128; 2]> = [0, 1]
+        .map(|shard| (100..).find(|&k| shard_of(k) == shard).unwrap())
+        .map(|key| [key, 1])
+        .to_vec();
+    assert!(matches!(
+        engine.insert_batch(&batch).await,
+        Err(OrbyError::StorageFull { .. })
+    ));
+    assert_eq!(engine.count_active(), stored);
+    assert!(engine
+        .query(&Filter::Eq { lane: 1, value: 1 }, 10)
+        .is_empty());
+
+    // 一つのシャードの容量を超える再投入は、既存の行を破棄する前に拒否される
+    let skewed: Vec<[u128; 2]> = (200..)
+        .filter(|&k| shard_of(k) == 1)
+        .take(5)
+        .map(|key| [key, 2])
+        .collect();
+    assert!(matches!(
+        engine.purge_all_data(skewed.clone()).await,
+        Err(OrbyError::StorageFull { .. })
+    ));
+    assert_eq!(engine.count_active(), stored);
+    engine.purge_all_data(skewed[..4].to_vec()).await.unwrap();
+    assert_eq!(engine.count_active(), 4);
+
+    // シャード 1 の 4 行をシャード 0 へ移す更新は容量を超えるため、
+    // 先に処理されるシャード 0 の一致行も含めて何も書き換えずに拒否される
+    let moving = (300..).find(|&k| shard_of(k) == 0).unwrap();
+    engine.insert(&[moving, 2]).await.unwrap();
+    let target = (400..).find(|&k| shard_of(k) == 0).unwrap();
+    assert!(matches!(
+        engine.update_by_id(1, 2, &[target, 3]).await,
+        Err(OrbyError::StorageFull { .. })
+    ));
+    assert_eq!(engine.count_active(), 5);
+    assert!(engine
+        .query(&Filter::Eq { lane: 1, value: 3 }, 10)
+        .is_empty());
+    assert_eq!(engine.get_by_key(moving).unwrap()[1], 2);
+}
+
+#[tokio::test]
+async fn test_shards_vault_reload() {
+    let label = "test_shards_vault_reload";
+    let db_path = std::env::temp_dir().join(label);
+    if db_path.exists() {
+        let _ = std::fs::remove_dir_all(&db_path);
+    }
+
+    let build = || {
+        Orby::builder(label)
+            .ring_buffer_lane_item_count(16)
+            .ring_buffer_lane_count(2)
+            .shards(2)
+            .with_storage(SaveMode::Vault(Some(db_path.clone())))
+            .build()
+    };
+
+    {
+        let engine = build().await.unwrap();
+        let rows: Vec<[u128; 2]> = (1..=6u128).map(|i| [i, i]).collect();
+        engine.insert_batch(&rows).await.unwrap();
+    }
+    assert!(db_path
+        .join(label)
+        .join(format!("{}_shard_0", label))
+        .exists());
+    assert!(db_path
+        .join(label)
+        .join(format!("{}_shard_1", label))
+        .exists());
+
+    {
+        // 採番は既存データの続きから再開される
+        let engine = build().await.unwrap();
+        assert_eq!(engine.len(), 6);
+        engine.insert(&[7, 7]).await.unwrap();
+        let keys: Vec<u128> = engine.take(3).iter().map(|r| r[0]).collect();
+        assert_eq!(keys, vec![7, 6, 5]);
+    }
+
+    let _ = std::fs::remove_dir_all(&db_path);
+}
+
+#[tokio::test]
+async fn test_index_lane_tracks_mutations() {
+    let engine = Orby::builder("test_index_lane_tracks_mutations")
+        .ring_buffer_lane_item_count(4)
+        .ring_buffer_lane_count(2)
+        .index_lane(1)
+        .compaction(true)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    let find = |id: u128| engine.find_by(1, &HashSet::from([id]), 10);
+
+    engine
+        .insert_batch(&[[1u128, 7], [2, 8], [3, 7], [4, 9]])
+        .await
+        .unwrap();
+    // 索引経由でも最新順に返る
+    let rows = find(7);
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0][0], 3);
+    assert_eq!(rows[1][0], 1);
+
+    // 周回で上書きされた行は索引から外れる
+    engine.insert(&[5, 10]).await.unwrap();
+    assert_eq!(find(7).len(), 1);
+    assert_eq!(find(10)[0][0], 5);
+
+    // 更新・削除も索引に反映される
+    assert!(engine.update_by_id(1, 8, &[2, 11]).await.unwrap());
+    assert!(find(8).is_empty());
+    assert_eq!(find(11)[0][0], 2);
+    engine.purge_by_id(1, 9).await;
+    assert!(find(9).is_empty());
+    assert_eq!(engine.len(), 3);
+
+    // コンパクションで行が移動しても索引は正しい位置を指す
+    assert!(engine.delete(1).await.unwrap());
+    assert_eq!(find(7)[0][0], 3);
+    assert!(engine.update_by_id(1, 7, &[3, 12]).await.unwrap());
+    assert_eq!(find(12)[0][0], 3);
+
+    // 再初期化後は新しいデータのみが索引に残る
+    engine.purge_all_data(vec![vec![6u128, 12]]).await.unwrap();
+    let rows = find(12);
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0][0], 6);
+}
+
+#[tokio::test]
+async fn test_zone_map_skips_blocks() {
+    let engine = Orby::builder("test_zone_map_skips_blocks")
+        .ring_buffer_lane_item_count(10_000)
+        .ring_buffer_lane_count(2)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    let rows: Vec<Vec<u128>> = (0..10_000u128).map(|i| vec![i, i * 2]).collect();
+    engine.insert_batch(rows).await.unwrap();
+
+    let zones = engine.zone_map(1);
+    assert_eq!(zones.len(), 3);
+    assert_eq!((zones[1].start, zones[1].end), (4096, 8192));
+    assert_eq!((zones[1].min, zones[1].max), (8192, 16382));
+    assert_eq!(zones[2].end, 10_000);
+    assert_eq!(engine.lane_range(1), Some((0, 19_998)));
+
+    // 範囲検索は重なるブロックのみを走査し、最新順に返す
+    let res = engine.find_custom(1, 10_000, 10_004, 10);
+    assert_eq!(res.len(), 3);
+    assert_eq!(res[0][0], 5002);
+    assert_eq!(res[2][0], 5000);
+    assert!(engine.find_custom(1, 30_000, 40_000, 10).is_empty());
+
+    // 書き込みで範囲が広がり、再初期化で作り直される
+    assert!(engine.update_by_id(0, 1, &[1, 50_000]).await.unwrap());
+    assert_eq!(engine.find_custom(1, 50_000, 50_000, 10)[0][0], 1);
+    assert_eq!(engine.lane_range(1), Some((0, 50_000)));
+    engine.purge_all_data(vec![vec![7u128, 70]]).await.unwrap();
+    assert_eq!(engine.lane_range(1), Some((70, 70)));
+    assert_eq!(engine.zone_map(1).len(), 1);
+}
+
+#[tokio::test]
+async fn test_zone_map_recovers_after_wraparound() {
+    let engine = Orby::builder("test_zone_map_recovers_after_wraparound")
+        .ring_buffer_lane_item_count(3 * 4096)
+        .ring_buffer_lane_count(2)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    let lap = 3 * 4096u128;
+    let narrow = |base: u128| Filter::Range {
+        lane: 1,
+        min: base + 100,
+        max: base + 200,
+    };
+    let rows: Vec<Vec<u128>> = (0..lap).map(|i| vec![i, i]).collect();
+    engine.insert_batch(rows).await.unwrap();
+    assert_eq!(engine.explain(&narrow(0)).blocks_skipped, 2);
+
+    // 一周分上書きされると、各ブロックの要約は書き終えた時点で新しい行から作り直される
+    let rows: Vec<Vec<u128>> = (lap..2 * lap).map(|i| vec![i, i]).collect();
+    engine.insert_batch(rows).await.unwrap();
+    let plan = engine.explain(&narrow(lap));
+    assert_eq!(plan.path, AccessPath::BlockScan);
+    assert_eq!(plan.blocks_skipped, 2);
+    assert_eq!(engine.lane_range(1), Some((lap, 2 * lap - 1)));
+    assert_eq!(engine.find_custom(1, lap + 100, lap + 200, 1000).len(), 101);
+}
+
+#[tokio::test]
+async fn test_bloom_lane_skips_absent_keys() {
+    let engine = Orby::builder("test_bloom_lane_skips_absent_keys")
+        .ring_buffer_lane_item_count(8192)
+        .ring_buffer_lane_count(2)
+        .bloom_lane(0)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    let rows: Vec<Vec<u128>> = (0..8192u128).map(|i| vec![i, i + 1]).collect();
+    engine.insert_batch(rows).await.unwrap();
+
+    assert_eq!(engine.find_by(0, &HashSet::from([100]), 1)[0][1], 101);
+    assert!(engine.find_by(0, &HashSet::from([1_000_000]), 1).is_empty());
+    {
+        let store = engine.inner.read();
+        let bloom = store.bloom.as_ref().unwrap();
+        assert!(bloom.may_contain(0, 100));
+        assert!(!bloom.may_contain(1, 100));
+    }
+
+    // 周回でブロックを書き終えると、上書きされた値はフィルタから消える
+    let rows: Vec<Vec<u128>> = (10_000..14_096u128).map(|i| vec![i, i + 1]).collect();
+    engine.insert_batch(rows).await.unwrap();
+    {
+        let store = engine.inner.read();
+        let bloom = store.bloom.as_ref().unwrap();
+        assert!(!bloom.may_contain(0, 100));
+        assert!(bloom.may_contain(0, 10_100));
+    }
+    assert!(engine.find_by(0, &HashSet::from([100]), 1).is_empty());
+
+    // ブルームフィルタを経由した更新・削除
+    assert!(engine.update_by_id(0, 5000, &[5000, 7]).await.unwrap());
+    assert_eq!(engine.find_by(0, &HashSet::from([5000]), 1)[0][1], 7);
+    engine.purge_by_id(0, 5000).await;
+    assert!(engine.find_by(0, &HashSet::from([5000]), 1).is_empty());
+    assert!(!engine.update_by_id(0, 100, &[100, 0]).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_bitmap_lane_answers_filters() {
+    let engine = Orby::builder("test_bitmap_lane_answers_filters")
+        .ring_buffer_lane_item_count(5)
+        .ring_buffer_lane_count(3)
+        .bitmap_lane(1)
+        .bitmap_lane(2)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    // [id, status, kind]
+    engine
+        .insert_batch(&[[1u128, 0, 7], [2, 1, 7], [3, 1, 8], [4, 2, 8], [5, 1, 7]])
+        .await
+        .unwrap();
+    let ids = |filter: Filter| -> Vec<u128> {
+        engine.query(&filter, 10).iter().map(|row| row[0]).collect()
+    };
+
+    assert_eq!(ids(Filter::Eq { lane: 1, value: 1 }), vec![5, 3, 2]);
+    assert_eq!(
+        ids(Filter::In {
+            lane: 1,
+            values: vec![0, 2]
+        }),
+        vec![4, 1]
+    );
+    let active_sevens = Filter::And(vec![
+        Filter::Eq { lane: 1, value: 1 },
+        Filter::Eq { lane: 2, value: 7 },
+    ]);
+    assert_eq!(ids(active_sevens.clone()), vec![5, 2]);
+    assert_eq!(
+        ids(Filter::Or(vec![
+            Filter::Eq { lane: 1, value: 2 },
+            Filter::Eq { lane: 2, value: 8 },
+        ])),
+        vec![4, 3]
+    );
+    // 索引のないレーンの条件は候補に対してのみ確認される
+    assert_eq!(
+        ids(Filter::And(vec![
+            Filter::Eq { lane: 1, value: 1 },
+            Filter::Eq { lane: 0, value: 3 },
+        ])),
+        vec![3]
+    );
+    assert_eq!(ids(Filter::Eq { lane: 0, value: 4 }), vec![4]);
+
+    // 上書き・更新・削除がビットマップに反映される
+    engine.insert(&[6, 1, 7]).await.unwrap();
+    assert_eq!(ids(active_sevens.clone()), vec![6, 5, 2]);
+    assert!(engine.update_by_id(0, 2, &[2, 0, 7]).await.unwrap());
+    engine.purge_by_id(0, 5).await;
+    assert_eq!(ids(active_sevens), vec![6]);
+    assert_eq!(ids(Filter::Eq { lane: 1, value: 0 }), vec![2]);
+}
+
+#[tokio::test]
+async fn test_bitmap_lane_falls_back_past_value_limit() {
+    use crate::logic::bitmap_index::MAX_BITMAP_VALUES;
+    let engine = Orby::builder("test_bitmap_lane_falls_back_past_value_limit")
+        .ring_buffer_lane_item_count(1000)
+        .ring_buffer_lane_count(2)
+        .bitmap_lane(1)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    let limit = MAX_BITMAP_VALUES as u128;
+    let rows: Vec<Vec<u128>> = (0..limit).map(|i| vec![i, i]).collect();
+    engine.insert_batch(rows).await.unwrap();
+    let status = |value: u128| Filter::Eq { lane: 1, value };
+    assert_eq!(engine.explain(&status(7)).path, AccessPath::BitmapIndex);
+
+    // 上限を超える値が現れると索引を破棄し、走査で正しい結果を返す
+    engine.insert(&[limit, limit]).await.unwrap();
+    engine.insert(&[limit + 1, 7]).await.unwrap();
+    assert_ne!(engine.explain(&status(7)).path, AccessPath::BitmapIndex);
+    let ids: Vec<u128> = engine
+        .query(&status(7), 10)
+        .iter()
+        .map(|row| row[0])
+        .collect();
+    assert_eq!(ids, vec![limit + 1, 7]);
+    assert_eq!(engine.query(&status(limit), 10).len(), 1);
+
+    // 再初期化で上限内に収まれば索引は再び使われる
+    engine
+        .purge_all_data(vec![vec![1u128, 0], vec![2, 1]])
+        .await
+        .unwrap();
+    assert_eq!(engine.explain(&status(1)).path, AccessPath::BitmapIndex);
+    assert_eq!(engine.query(&status(1), 10)[0][0], 2);
+}
+
+#[tokio::test]
+async fn test_composite_index_prefix_lookup() {
+    let engine = Orby::builder("test_composite_index_prefix_lookup")
+        .ring_buffer_lane_item_count(6)
+        .ring_buffer_lane_count(3)
+        .composite_index(&[1, 2])
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    // [id, tenant, user]
+    engine
+        .insert_batch(&[[1u128, 10, 100], [2, 10, 200], [3, 20, 100], [4, 10, 100]])
+        .await
+        .unwrap();
+    let ids = |filter: Filter| -> Vec<u128> {
+        engine.query(&filter, 10).iter().map(|row| row[0]).collect()
+    };
+    let tenant_user = Filter::And(vec![
+        Filter::Eq {
+            lane: 2,
+            value: 100,
+        },
+        Filter::Eq { lane: 1, value: 10 },
+    ]);
+
+    // 全レーンの完全一致と、先頭レーンのみの前方一致
+    assert_eq!(ids(tenant_user.clone()), vec![4, 1]);
+    assert_eq!(ids(Filter::Eq { lane: 1, value: 10 }), vec![4, 2, 1]);
+    assert_eq!(engine.find_indices_where(&tenant_user, 10), vec![0, 3]);
+    // 先頭レーンを含まない条件は索引を使わずに評価される
+    assert_eq!(
+        ids(Filter::Eq {
+            lane: 2,
+            value: 100
+        }),
+        vec![4, 3, 1]
+    );
+    // 索引外のレーンの条件は候補に対して確認される
+    assert_eq!(
+        ids(Filter::And(vec![
+            Filter::Eq { lane: 1, value: 10 },
+            Filter::Eq { lane: 0, value: 2 },
+        ])),
+        vec![2]
+    );
+
+    // 上書き・更新・削除が複合索引に反映される
+    engine
+        .insert_batch(&[[5u128, 10, 100], [6, 20, 100], [7, 10, 100]])
+        .await
+        .unwrap();
+    assert_eq!(ids(tenant_user.clone()), vec![7, 5, 4]);
+    assert!(engine.update_by_id(0, 4, &[4, 20, 100]).await.unwrap());
+    engine.purge_by_id(0, 5).await;
+    assert_eq!(ids(tenant_user), vec![7]);
+    assert_eq!(ids(Filter::Eq { lane: 1, value: 20 }), vec![6, 4, 3]);
+}
+
+#[tokio::test]
+async fn test_prefix_index_on_upper_bits() {
+    let engine = Orby::builder("test_prefix_index_on_upper_bits")
+        .ring_buffer_lane_item_count(6)
+        .ring_buffer_lane_count(2)
+        .prefix_index(1, 8)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    // レーン 1 の上位 8 ビットにエンティティ種別を格納する
+    let entity = |kind: u128, id: u128| (kind << 120) | id;
+    engine
+        .insert_batch(&[
+            [1u128, entity(1, 10)],
+            [2, entity(2, 20)],
+            [3, entity(1, 30)],
+            [4, entity(3, 10)],
+        ])
+        .await
+        .unwrap();
+    let ids = |filter: Filter| -> Vec<u128> {
+        engine.query(&filter, 10).iter().map(|row| row[0]).collect()
+    };
+
+    assert_eq!(ids(Filter::prefix(1, 8, 1)), vec![3, 1]);
+    assert_eq!(
+        engine.find_indices_where(&Filter::prefix(1, 8, 2), 10),
+        vec![2]
+    );
+    // 索引より細かいマスクは候補に対して確認される
+    let exact = Filter::Mask {
+        lane: 1,
+        mask: Filter::prefix_mask(8) | 0xFF,
+        value: entity(1, 30),
+    };
+    assert_eq!(ids(exact), vec![3]);
+    // マスク外のビットを持つ値はどの行とも一致しない
+    let outside = Filter::Mask {
+        lane: 1,
+        mask: Filter::prefix_mask(8),
+        value: entity(1, 5),
+    };
+    assert!(!outside.matches(&[PulseCell::new(1), PulseCell::new(entity(1, 5))]));
+    assert!(ids(outside.clone()).is_empty());
+    assert_eq!(engine.explain(&outside).estimated_rows, 0);
+
+    // 周回上書き・更新・削除が索引に反映される
+    engine
+        .insert_batch(&[
+            [5u128, entity(1, 50)],
+            [6, entity(2, 60)],
+            [7, entity(1, 70)],
+        ])
+        .await
+        .unwrap();
+    assert_eq!(ids(Filter::prefix(1, 8, 1)), vec![7, 5, 3]);
+    assert!(engine
+        .update_by_id(0, 3, &[3, entity(2, 30)])
+        .await
+        .unwrap());
+    engine.purge_by_id(0, 5).await;
+    assert_eq!(ids(Filter::prefix(1, 8, 1)), vec![7]);
+    assert_eq!(ids(Filter::prefix(1, 8, 2)), vec![6, 3, 2]);
+}
+
+#[tokio::test]
+async fn test_unique_constraint_rejects_every_write_path() {
+    let engine = Orby::builder("test_unique_constraint_rejects_every_write_path")
+        .ring_buffer_lane_item_count(4)
+        .ring_buffer_lane_count(2)
+        .unique(0)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    fn violation<T>(result: Result<T, OrbyError>, expected: u128) {
+        match result {
+            Err(OrbyError::DuplicateKey { lane: 0, value, .. }) => assert_eq!(value, expected),
+            other => panic!("Expected DuplicateKey, got {:?}", other.map(|_| ())),
+        }
+    }
+    engine.insert_batch(&[[1u128, 10], [2, 20]]).await.unwrap();
+
+    // 挿入: 既存の値、およびバッチ内の重複はどちらも書き込まれない
+    violation(engine.insert(&[1, 30]).await, 1);
+    violation(engine.insert_batch(&[[3u128, 30], [3, 31]]).await, 3);
+    assert_eq!(engine.len(), 2);
+
+    // 更新: 別の行の値へ書き換えることはできないが、同じ値のままの更新はできる
+    violation(engine.update_by_id(0, 2, &[1, 21]).await, 1);
+    assert!(!engine.update_by_id(0, 9, &[9, 90]).await.unwrap());
+    assert_eq!(engine.get_by_key(2).unwrap()[1], 20);
+    assert!(engine.update_by_id(0, 2, &[2, 22]).await.unwrap());
+
+    // upsert: 更新・挿入のどちらの経路でも確認される
+    violation(engine.upsert(0, 2, &[1, 0]).await, 1);
+    violation(engine.upsert(0, 5, &[1, 0]).await, 1);
+    engine.upsert(0, 5, &[5, 50]).await.unwrap();
+    violation(engine.purge_all_data(vec![[7u128, 0], [7, 1]]).await, 7);
+    assert_eq!(engine.len(), 3);
+
+    // 周回で上書きされる最古の行の値は、同じ書き込みで再び使用できる
+    engine.insert(&[6, 60]).await.unwrap();
+    engine.insert(&[1, 11]).await.unwrap();
+    let ids: Vec<u128> = engine.take(4).iter().map(|row| row[0]).collect();
+    assert_eq!(ids, vec![1, 6, 5, 2]);
+
+    // シャード構成ではシャードをまたいで確認される
+    let sharded = Orby::builder("test_unique_constraint_rejects_every_write_path_sharded")
+        .ring_buffer_lane_item_count(8)
+        .ring_buffer_lane_count(2)
+        .shards(2)
+        .unique(0)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    sharded.insert_batch(&[[1u128, 10], [2, 20]]).await.unwrap();
+    violation(sharded.update_by_id(0, 2, &[1, 21]).await, 1);
+    violation(sharded.insert(&[2, 30]).await, 2);
+    assert_eq!(sharded.get_by_key(2).unwrap()[1], 20);
+}
+
+#[tokio::test]
+async fn test_unique_key_update_moves_sharded_row() {
+    let sharded = Orby::builder("test_unique_key_update_moves_sharded_row")
+        .ring_buffer_lane_item_count(16)
+        .ring_buffer_lane_count(2)
+        .shards(4)
+        .unique(0)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    sharded.insert(&[1, 10]).await.unwrap();
+    // キーを変えるたびに行は新しいキーのシャードへ移り、その後の挿入は重複として拒否される
+    for key in 2..=8u128 {
+        assert!(sharded
+            .update_by_id(0, key - 1, &[key, key * 10])
+            .await
+            .unwrap());
+        assert!(matches!(
+            sharded.insert(&[key, 0]).await,
+            Err(OrbyError::DuplicateKey { pool_name, lane: 0, value })
+                if value == key && pool_name.starts_with("test_unique_key_update_moves_sharded_row_shard_")
+        ));
+        let found = sharded.find_by(0, &HashSet::from([key]), 10);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0][1], key * 10);
+        assert!(sharded.get_by_key(key - 1).is_none());
+        sharded.insert(&[key - 1, 0]).await.unwrap();
+    }
+    assert_eq!(sharded.count_active(), 8);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_sharded_upsert_is_atomic() {
+    let sharded = Orby::builder("test_sharded_upsert_is_atomic")
+        .ring_buffer_lane_item_count(64)
+        .ring_buffer_lane_count(2)
+        .shards(4)
+        .unique_lane(0)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    // 同じキーへの upsert が並行しても、更新と挿入の間に割り込まれて挿入が除外されることはない
+    let tasks: Vec<_> = (0..32u128)
+        .map(|i| {
+            let sharded = sharded.clone();
+            tokio::spawn(async move { sharded.upsert(0, i % 4, &[i % 4, i]).await })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap().unwrap();
+    }
+    assert_eq!(sharded.count_active(), 4);
+    for key in 0..4u128 {
+        assert_eq!(sharded.find_by(0, &HashSet::from([key]), 10).len(), 1);
+    }
+    assert!(matches!(
+        sharded.insert(&[2, 0]).await,
+        Err(OrbyError::DuplicateKey { pool_name, lane: 0, value: 2 })
+            if pool_name.starts_with("test_sharded_upsert_is_atomic_shard_")
+    ));
+}
+
+#[tokio::test]
+async fn test_find_range_monotonic_across_wrap() {
+    let engine = Orby::builder("test_find_range_monotonic_across_wrap")
+        .ring_buffer_lane_item_count(5)
+        .ring_buffer_lane_count(2)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    for key in (1..=7u128).map(|i| i * 10) {
+        engine.insert(&[key, key + 1]).await.unwrap();
+    }
+    let keys = |min: u128, max: u128| -> Vec<u128> {
+        engine
+            .find_range_monotonic(0, min, max)
+            .unwrap()
+            .iter()
+            .map(|row| row[0])
+            .collect()
+    };
+
+    // 生存ウィンドウは 30..=70 で、折り返し点をまたいで探索される
+    assert_eq!(keys(35, 60), vec![60, 50, 40]);
+    assert_eq!(keys(0, 1000), vec![70, 60, 50, 40, 30]);
+    assert_eq!(keys(70, 70), vec![70]);
+    assert!(keys(0, 25).is_empty());
+
+    // 墓標は読み飛ばされる
+    engine.purge_by_id(0, 50).await;
+    assert_eq!(keys(40, 60), vec![60, 40]);
+
+    // 単調でない値が混ざると報告される
+    engine.insert(&[5, 6]).await.unwrap();
+    assert!(matches!(
+        engine.find_range_monotonic(0, 0, 1000),
+        Err(OrbyError::ConfigMismatch { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_find_range_monotonic_over_long_tombstone_runs() {
+    let engine = Orby::builder("test_find_range_monotonic_over_long_tombstone_runs")
+        .ring_buffer_lane_item_count(1000)
+        .ring_buffer_lane_count(2)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    let rows: Vec<Vec<u128>> = (0..1000u128).map(|i| vec![i, i]).collect();
+    engine.insert_batch(rows).await.unwrap();
+    for key in 10..990u128 {
+        engine.purge_by_id(0, key).await;
+    }
+    let keys = |min: u128, max: u128| -> Vec<u128> {
+        engine
+            .find_range_monotonic(0, min, max)
+            .unwrap()
+            .iter()
+            .map(|row| row[0])
+            .collect()
+    };
+
+    // 探索点の先に長い墓標の列があっても、全体の走査に切り替えて正しく返す
+    assert_eq!(keys(5, 12), vec![9, 8, 7, 6, 5]);
+    assert_eq!(keys(985, 992), vec![992, 991, 990]);
+    assert!(keys(100, 900).is_empty());
+    assert_eq!(keys(0, u128::MAX).len(), 20);
+
+    // 走査に切り替えた場合も単調でない値は報告される
+    engine.insert(&[1, 1]).await.unwrap();
+    assert!(matches!(
+        engine.find_range_monotonic(0, 0, 5),
+        Err(OrbyError::ConfigMismatch { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_explain_reports_plan() {
+    let engine = Orby::builder("test_explain_reports_plan")
+        .ring_buffer_lane_item_count(10_000)
+        .ring_buffer_lane_count(3)
+        .bloom_lane(0)
+        .index_lane(2)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    let rows: Vec<Vec<u128>> = (0..10_000u128).map(|i| vec![i, i * 2, i / 100]).collect();
+    engine.insert_batch(rows).await.unwrap();
+    let ids = |filter: &Filter| -> Vec<u128> {
+        engine.query(filter, 100).iter().map(|row| row[0]).collect()
+    };
+
+    // 要約で判定できない条件は全件走査になる
+    let plan = engine.explain(&Filter::prefix(1, 8, 0));
+    assert_eq!(plan.path, AccessPath::FullScan);
+    assert_eq!(
+        (plan.estimated_rows, plan.blocks_total, plan.blocks_skipped),
+        (10_000, 3, 0)
+    );
+
+    // ゾーンマップで範囲と重ならないブロックを読み飛ばす
+    let range = Filter::Range {
+        lane: 1,
+        min: 10_000,
+        max: 10_004,
+    };
+    let plan = engine.explain(&range);
+    assert_eq!(plan.path, AccessPath::BlockScan);
+    assert_eq!((plan.estimated_rows, plan.blocks_skipped), (4096, 2));
+    assert_eq!(ids(&range), vec![5002, 5001, 5000]);
+    assert_eq!(
+        engine.find_indices_where(&range, 10),
+        vec![4997, 4998, 4999]
+    );
+
+    // ブルームフィルタと組み合わせ、いずれかの値を含み得るブロックのみを走査する
+    let keys = Filter::Or(vec![
+        Filter::Eq {
+            lane: 0,
+            value: 100,
+        },
+        Filter::Eq {
+            lane: 0,
+            value: 9000,
+        },
+    ]);
+    let plan = engine.explain(&keys);
+    assert_eq!((plan.path, plan.blocks_skipped), (AccessPath::BlockScan, 1));
+    assert_eq!(plan.estimated_rows, 4096 + 1808);
+    assert_eq!(ids(&keys), vec![9000, 100]);
+
+    // 候補の少ない索引が選ばれ、索引で評価できない条件は候補の行で確認する
+    let plan = engine.explain(&Filter::Eq { lane: 2, value: 7 });
+    assert_eq!(plan.path, AccessPath::LaneIndex);
+    assert_eq!((plan.estimated_rows, plan.blocks_skipped), (100, 2));
+    assert!(!plan.recheck);
+    let narrowed = Filter::And(vec![
+        Filter::Eq { lane: 2, value: 7 },
+        Filter::Range {
+            lane: 1,
+            min: 0,
+            max: 1500,
+        },
+    ]);
+    let plan = engine.explain(&narrowed);
+    assert_eq!((plan.path, plan.recheck), (AccessPath::LaneIndex, true));
+    let found = ids(&narrowed);
+    assert_eq!((found.len(), found[0], found[50]), (51, 750, 700));
+}
+
+#[tokio::test]
+async fn test_query_lanes_binds_predicate() {
+    let engine = Orby::builder("test_query_lanes_binds_predicate")
+        .ring_buffer_lane_item_count(100)
+        .ring_buffer_lane_count(64)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    let rows: Vec<Vec<u128>> = (0..150u128)
+        .map(|i| (0..64u128).map(|lane| i * 1000 + lane).collect())
+        .collect();
+    engine.insert_batch(rows).await.unwrap();
+
+    // 述語には指定したレーンの値のみが指定した順に渡され、結果は行全体を最新順に返す
+    let rows = engine.query_lanes(&[40, 3], |v| v[0] % 2_000 == 40 && v[1] >= 120_003, 10);
+    let ids: Vec<u128> = rows.iter().map(|row| row[0] / 1000).collect();
+    assert_eq!(ids, vec![148, 146, 144, 142, 140, 138, 136, 134, 132, 130]);
+    assert_eq!(rows[0].len(), 64);
+    assert_eq!(rows[0][63], 148_063);
+
+    // 周回で上書きされた行と墓標は対象外で、Filter の検索とも一致する
+    engine.purge_by_id(0, 148_000).await;
+    let rows = engine.query_lanes(&[1], |v| v[0] % 2_000 == 1, 100);
+    assert_eq!(rows.len(), 49);
+    assert_eq!(rows[0][0], 146_000);
+    assert_eq!(
+        engine
+            .query(
+                &Filter::Range {
+                    lane: 63,
+                    min: 0,
+                    max: 120_000
+                },
+                100
+            )
+            .len(),
+        70
+    );
+
+    // 範囲外のレーンを指定した場合は空の結果を返す
+    assert!(engine.query_lanes(&[64], |_| true, 10).is_empty());
+}
+
+#[tokio::test]
+async fn test_filter_serializes_and_drives_lookups() {
+    // フィルタは serde で往復でき、ログや通信にそのまま使える
+    let filter = Filter::And(vec![
+        Filter::Range {
+            lane: 1,
+            min: 20,
+            max: 40,
+        },
+        Filter::Not(Box::new(Filter::In {
+            lane: 0,
+            values: vec![3],
+        })),
+    ]);
+    let json = serde_json::to_string(&filter).unwrap();
+    assert_eq!(serde_json::from_str::<Filter>(&json).unwrap(), filter);
+    assert_eq!(filter.lanes(), vec![1, 0]);
+
+    let engine = Orby::builder("test_filter_serializes_and_drives_lookups")
+        .ring_buffer_lane_item_count(10)
+        .ring_buffer_lane_count(2)
+        .with_storage(SaveMode::MemoryOnly)
+        .logic_mode(LogicMode::Sorted)
+        .key_lane(1)
+        .build()
+        .await
+        .unwrap();
+    engine
+        .insert_batch(&[[1u128, 50u128], [2, 10], [3, 30], [4, 20], [5, 40]])
+        .await
+        .unwrap();
+    let ids = |rows: Vec<Arc<[u128]>>| -> Vec<u128> { rows.iter().map(|row| row[0]).collect() };
+
+    // キーレーンの条件は二分探索で評価され、それ以外の条件は候補の行で確認される
+    let plan = engine.explain(&filter);
+    assert_eq!(
+        (plan.path, plan.estimated_rows, plan.recheck),
+        (AccessPath::KeyLookup, 3, true)
+    );
+    assert_eq!(ids(engine.query(&filter, 10)), vec![5, 4]);
+    assert_eq!(
+        engine.find_indices_where(&Filter::Not(Box::new(filter.clone())), 10),
+        vec![0, 2, 4]
+    );
+
+    // find_by / find_custom は Filter による query と同じ結果を返す
+    let targets = HashSet::from([10u128, 50, 99]);
+    assert_eq!(ids(engine.find_by(1, &targets, 10)), vec![1, 2]);
+    assert_eq!(
+        engine
+            .explain(&Filter::In {
+                lane: 1,
+                values: vec![10, 50, 99]
+            })
+            .path,
+        AccessPath::KeyLookup
+    );
+    assert_eq!(
+        ids(engine.find_custom(1, 20, 40, 10)),
+        ids(engine.query(
+            &Filter::Range {
+                lane: 1,
+                min: 20,
+                max: 40
+            },
+            10
+        ))
+    );
+    assert!(engine.find_custom(1, 40, 20, 10).is_empty());
+    assert!(engine.find_by(2, &targets, 10).is_empty());
+}
+
+#[tokio::test]
+async fn test_aggregate_matching_rows() {
+    let engine = Orby::builder("test_aggregate_matching_rows")
+        .ring_buffer_lane_item_count(10_000)
+        .ring_buffer_lane_count(3)
+        .index_lane(2)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    let rows: Vec<Vec<u128>> = (0..10_000u128).map(|i| vec![i, i % 7, i / 1000]).collect();
+    engine.insert_batch(rows).await.unwrap();
+
+    let aggs = [
+        Agg::Count,
+        Agg::Min(0),
+        Agg::Max(0),
+        Agg::WrappingSum(0),
+        Agg::Histogram {
+            lane: 1,
+            min: 1,
+            width: 2,
+            buckets: 2,
+        },
+    ];
+    // 走査（ゾーンマップで読み飛ばし）でも索引の候補でも、同じ集計結果になる
+    let scanned = engine
+        .aggregate(
+            &Filter::Range {
+                lane: 0,
+                min: 2000,
+                max: 2999,
+            },
+            &aggs,
+        )
+        .unwrap();
+    let indexed = engine
+        .aggregate(&Filter::Eq { lane: 2, value: 2 }, &aggs)
+        .unwrap();
+    assert_eq!(scanned, indexed);
+    assert_eq!(scanned.count(0), Some(1000));
+    assert_eq!((scanned.min(1), scanned.max(2)), (Some(2000), Some(2999)));
+    assert_eq!(scanned.sum(3), Some((2000..3000).sum()));
+    let histogram = scanned.histogram(4).unwrap();
+    assert_eq!(histogram.counts, vec![286, 285]);
+    assert_eq!((histogram.below, histogram.above), (143, 286));
+
+    // 一致する行がなければ件数 0 で最小・最大はなく、墓標は数えない
+    let none = engine
+        .aggregate(&Filter::Eq { lane: 2, value: 99 }, &aggs)
+        .unwrap();
+    assert_eq!(
+        (none.count(0), none.min(1), none.sum(3)),
+        (Some(0), None, Some(0))
+    );
+    engine.purge_by_id(0, 2999).await;
+    let after = engine
+        .aggregate(&Filter::Eq { lane: 2, value: 2 }, &aggs)
+        .unwrap();
+    assert_eq!((after.count(0), after.max(2)), (Some(999), Some(2998)));
+
+    // 合計はラップアラウンドまたは飽和を選べる
+    engine.insert(&[u128::MAX, 0, 50]).await.unwrap();
+    engine.insert(&[2, 0, 50]).await.unwrap();
+    let sums = engine
+        .aggregate(
+            &Filter::Eq { lane: 2, value: 50 },
+            &[Agg::WrappingSum(0), Agg::SaturatingSum(0)],
+        )
+        .unwrap();
+    assert_eq!((sums.sum(0), sums.sum(1)), (Some(1), Some(u128::MAX)));
+
+    assert!(matches!(
+        engine.aggregate(&Filter::And(vec![]), &[Agg::Max(3)]),
+        Err(OrbyError::LaneCountMismatch { .. })
+    ));
+    assert!(matches!(
+        engine.aggregate(
+            &Filter::And(vec![]),
+            &[Agg::Histogram {
+                lane: 0,
+                min: 0,
+                width: 0,
+                buckets: 4
+            }]
+        ),
+        Err(OrbyError::ConfigMismatch { .. })
+    ));
+    let histogram = |buckets: usize| Agg::Histogram {
+        lane: 0,
+        min: 0,
+        width: 1,
+        buckets,
+    };
+    let limit = crate::logic::aggregate::MAX_HISTOGRAM_BUCKETS;
+    assert!(engine
+        .aggregate(&Filter::And(vec![]), &[histogram(limit)])
+        .is_ok());
+    assert!(matches!(
+        engine.aggregate(&Filter::And(vec![]), &[histogram(limit + 1)]),
+        Err(OrbyError::ConfigMismatch { .. })
+    ));
+    assert!(matches!(
+        engine.group_by(1, &Filter::And(vec![]), &[histogram(usize::MAX)]),
+        Err(OrbyError::ConfigMismatch { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_group_by_tenant() {
+    let engine = Orby::builder("test_group_by_tenant")
+        .ring_buffer_lane_item_count(1000)
+        .ring_buffer_lane_count(3)
+        .max_groups(8)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    // レーン 1 にテナント、レーン 2 に応答時間を格納する。周回で最古の 200 行は上書きされる
+    let rows: Vec<Vec<u128>> = (0..1200u128).map(|i| vec![i, i % 4, i % 100]).collect();
+    engine.insert_batch(rows).await.unwrap();
+
+    let groups = engine
+        .group_by(1, &Filter::And(vec![]), &[Agg::Count, Agg::Max(2)])
+        .unwrap();
+    assert_eq!(groups.len(), 4);
+    for tenant in 0..4u128 {
+        assert_eq!(groups[&tenant].count(0), Some(250));
+        assert_eq!(groups[&tenant].max(1), Some(96 + tenant));
+    }
+
+    // フィルタに一致する行だけがまとめられ、墓標は数えない
+    engine.purge_by_id(0, 1199).await;
+    let slow = engine
+        .group_by(
+            1,
+            &Filter::Range {
+                lane: 2,
+                min: 90,
+                max: 99,
+            },
+            &[Agg::Count],
+        )
+        .unwrap();
+    assert_eq!(slow[&3].count(0), Some(29));
+    assert_eq!(slow[&0].count(0), Some(20));
+
+    // グループ数が上限を超えると打ち切られる
+    assert!(matches!(
+        engine.group_by(0, &Filter::And(vec![]), &[Agg::Count]),
+        Err(OrbyError::TooManyGroups { max_groups: 8, .. })
+    ));
+    assert_eq!(
+        engine
+            .group_by(
+                0,
+                &Filter::Range {
+                    lane: 0,
+                    min: 0,
+                    max: 207
+                },
+                &[Agg::Count]
+            )
+            .unwrap()
+            .len(),
+        8
+    );
+    assert!(matches!(
+        engine.group_by(3, &Filter::And(vec![]), &[Agg::Count]),
+        Err(OrbyError::LaneCountMismatch { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_distinct_and_approx_distinct() {
+    let engine = Orby::builder("test_distinct_and_approx_distinct")
+        .ring_buffer_lane_item_count(10_000)
+        .ring_buffer_lane_count(3)
+        .approx_distinct_lane(1)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    // レーン 1 にユーザー ID を格納する。周回で最古の 2000 行は上書きされる
+    let rows: Vec<Vec<u128>> = (0..12_000u128).map(|i| vec![i, i % 3000, i % 7]).collect();
+    engine.insert_batch(rows).await.unwrap();
+
+    // 正確な異なる値は小さい順に `limit` 件まで
+    let all = Filter::And(vec![]);
+    assert_eq!(engine.distinct(1, &all, 5).unwrap(), vec![0, 1, 2, 3, 4]);
+    assert_eq!(engine.distinct(2, &all, 100).unwrap().len(), 7);
+    let recent = Filter::Range {
+        lane: 0,
+        min: 11_000,
+        max: 11_009,
+    };
+    assert_eq!(
+        engine.distinct(1, &recent, 100).unwrap(),
+        (2000..2010).collect::<Vec<u128>>()
+    );
+
+    // 推定値は実際の異なる値の数（3000）から標準誤差の数倍以内に収まる
+    let estimate = engine.approx_distinct(1).unwrap();
+    assert!((2700..=3300).contains(&estimate), "estimate {estimate}");
+    assert!(matches!(
+        engine.approx_distinct(2),
+        Err(OrbyError::ConfigMismatch { .. })
+    ));
+    assert!(matches!(
+        engine.distinct(3, &all, 1),
+        Err(OrbyError::LaneCountMismatch { .. })
+    ));
+
+    // シャード構成では各シャードのスケッチを統合し、同じ値を二重に数えない
+    let sharded = Orby::builder("test_distinct_and_approx_distinct_sharded")
+        .ring_buffer_lane_item_count(10_000)
+        .ring_buffer_lane_count(2)
+        .approx_distinct_lane(1)
+        .shards(4)
+        .with_storage(SaveMode::MemoryOnly)
+        .build()
+        .await
+        .unwrap();
+    let rows: Vec<Vec<u128>> = (0..8000u128).map(|i| vec![i, i % 500]).collect();
+    sharded.insert_batch(rows).await.unwrap();
+    let estimate = sharded.approx_distinct(1).unwrap();
+    assert!((450..=550).contains(&estimate), "estimate {estimate}");
+    assert_eq!(sharded.distinct(1, &all, 3).unwrap(), vec![0, 1, 2]);
+}
+
+#[tokio::test]
+async fn test_vault_detects_uncommitted_lane_write() {
+    let label = "test_vault_detects_uncommitted_lane_write";
+    let db_path = std::env::temp_dir().join(label);
+    if db_path.exists() {
+        let _ = std::fs::remove_dir_all(&db_path);
+    }
+    let open = |strict: bool| {
+        Orby::builder(label)
+            .ring_buffer_lane_item_count(10)
+            .ring_buffer_lane_count(2)
+            .with_storage(crate::types::SaveMode::Vault(Some(
+                db_path.parent().unwrap().to_path_buf(),
+            )))
+            .autoload(true)
+            .strict_check(strict)
+            .build()
+    };
+    {
+        let engine = open(true).await.unwrap();
+        engine
+            .insert_batch(&[[101u128, 201], [102, 202]])
+            .await
+            .unwrap();
+    }
+
+    // 有効ビットをコミットする前に中断された書き込みを模して、無効なスロットへレーンの値だけを書き込む
+    {
+        use std::os::unix::fs::FileExt;
+        let lane = std::fs::OpenOptions::new()
+            .write(true)
+            .open(db_path.join("lane_1.bin"))
+            .unwrap();
+        lane.write_at(&203u128.to_le_bytes(), 2 * 16).unwrap();
+    }
+    assert!(matches!(
+        open(true).await,
+        Err(OrbyError::InconsistentWrite { .. })
+    ));
+
+    // 厳密な検証を無効にした場合は、コミット済みの行のみをロードする
+    let engine = open(false).await.unwrap();
+    assert_eq!(engine.count_active(), 2);
+
+    let _ = std::fs::remove_dir_all(&db_path);
+}
@@ -46,6 +46,37 @@ pub enum OrbyError {
     #[error("Orby: Storage is full in pool '{pool_name}': capacity is {capacity}.")]
     StorageFull { pool_name: String, capacity: usize },
 
+    /// 一意性のレーン（`unique` / `unique_lane`）の値を重複させる書き込み
+    #[error("Orby: Duplicate value {value} in unique lane {lane} of pool '{pool_name}'.")]
+    DuplicateKey {
+        pool_name: String,
+        lane: usize,
+        value: u128,
+    },
+
+    /// グループ数の上限（`max_groups`）超過
+    #[error("Orby: group_by in pool '{pool_name}' exceeded the limit of {max_groups} groups.")]
+    TooManyGroups {
+        pool_name: String,
+        max_groups: usize,
+    },
+
+    /// 順位外の値（`LogicMode::TopK`）
+    #[error("Orby: Value {value} does not rank in pool '{pool_name}': current threshold is {threshold}.")]
+    RankTooLow {
+        pool_name: String,
+        value: u128,
+        threshold: u128,
+    },
+
+    /// スロット番号が範囲外
+    #[error("Orby: Slot {slot} is out of range in pool '{pool_name}': capacity is {capacity}.")]
+    SlotOutOfRange {
+        pool_name: String,
+        slot: usize,
+        capacity: usize,
+    },
+
     /// IOエラー
     #[error("Orby: I/O Error: {0}")]
     IoError(#[from] std::io::Error),
@@ -0,0 +1,103 @@
+use crate::types::PulseCell;
+
+/// A predicate over lanes that the engine can inspect.
+/// Unlike an opaque closure, a `Filter` can be answered from lane indexes
+/// (e.g. `bitmap_lane`) before falling back to a row-by-row scan,
+/// and it can be logged or sent over the wire through serde.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Filter {
+    /// Matches rows whose `lane` equals `value`.
+    Eq { lane: usize, value: u128 },
+    /// Matches rows whose `lane` equals any of `values`.
+    In { lane: usize, values: Vec<u128> },
+    /// Matches rows whose `lane` lies in `min..=max`.
+    Range { lane: usize, min: u128, max: u128 },
+    /// Matches rows whose `lane` has `value` in the bits selected by `mask`
+    /// (`lane & mask == value`), e.g. an entity-type prefix packed into the top bits.
+    Mask {
+        lane: usize,
+        mask: u128,
+        value: u128,
+    },
+    /// Matches rows that match every sub-filter. An empty list matches every row.
+    And(Vec<Filter>),
+    /// Matches rows that match any sub-filter. An empty list matches no row.
+    Or(Vec<Filter>),
+    /// Matches rows that do not match the sub-filter.
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    /// Returns a mask selecting the top `bits` bits of a lane (all bits from 128 upwards).
+    pub fn prefix_mask(bits: u32) -> u128 {
+        match bits {
+            0 => 0,
+            1..=127 => u128::MAX << (128 - bits),
+            _ => u128::MAX,
+        }
+    }
+
+    /// Matches rows whose top `bits` bits of `lane` equal `prefix`.
+    /// `prefix` is given unshifted, so `Filter::prefix(2, 8, 0x42)` matches `0x42 << 120`.
+    pub fn prefix(lane: usize, bits: u32, prefix: u128) -> Self {
+        let mask = Self::prefix_mask(bits);
+        let value = match bits {
+            0 => 0,
+            1..=127 => prefix << (128 - bits),
+            _ => prefix,
+        };
+        Filter::Mask { lane, mask, value }
+    }
+
+    /// Returns the lanes the filter reads, in first-use order and without duplicates.
+    pub fn lanes(&self) -> Vec<usize> {
+        fn collect(filter: &Filter, out: &mut Vec<usize>) {
+            match filter {
+                Filter::Eq { lane, .. }
+                | Filter::In { lane, .. }
+                | Filter::Range { lane, .. }
+                | Filter::Mask { lane, .. } => {
+                    if !out.contains(lane) {
+                        out.push(*lane);
+                    }
+                }
+                Filter::And(filters) | Filter::Or(filters) => {
+                    filters.iter().for_each(|f| collect(f, out))
+                }
+                Filter::Not(filter) => collect(filter, out),
+            }
+        }
+        let mut out = Vec::new();
+        collect(self, &mut out);
+        out
+    }
+
+    /// Evaluates the filter against a full row. Lanes out of range never match.
+    pub fn matches(&self, row: &[PulseCell]) -> bool {
+        self.matches_by(&|lane| row.get(lane).map(PulseCell::as_u128))
+    }
+
+    /// Evaluates the filter by reading only the lanes it references through `value`,
+    /// which returns `None` for lanes out of range. The engine uses this to test a slot
+    /// straight from the lane buffers without gathering the whole row.
+    pub fn matches_by<V>(&self, value: &V) -> bool
+    where
+        V: Fn(usize) -> Option<u128>,
+    {
+        match self {
+            Filter::Eq { lane, value: want } => value(*lane) == Some(*want),
+            Filter::In { lane, values } => value(*lane).is_some_and(|v| values.contains(&v)),
+            Filter::Range { lane, min, max } => {
+                value(*lane).is_some_and(|v| (*min..=*max).contains(&v))
+            }
+            Filter::Mask {
+                lane,
+                mask,
+                value: want,
+            } => value(*lane).is_some_and(|v| v & mask == *want),
+            Filter::And(filters) => filters.iter().all(|f| f.matches_by(value)),
+            Filter::Or(filters) => filters.iter().any(|f| f.matches_by(value)),
+            Filter::Not(filter) => !filter.matches_by(value),
+        }
+    }
+}
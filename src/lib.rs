@@ -1,6 +1,7 @@
 pub mod builder;
 pub mod engine;
 pub mod error;
+pub mod filter;
 pub mod logic;
 pub mod row;
 pub mod types;
@@ -10,5 +11,9 @@ pub mod utils;
 pub use builder::OrbyBuilder;
 pub use engine::Orby;
 pub use error::OrbyError;
+pub use filter::Filter;
 pub use row::PulseCellPack;
-pub use types::{LogicMode, PulseCell, SaveMode};
+pub use types::{
+    AccessPath, Agg, AggValue, AggregateResult, Histogram, InsertOutcome, LaneZone, LogicMode,
+    PulseCell, QueryPlan, RankOrder, SaveMode,
+};
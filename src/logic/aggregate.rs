@@ -0,0 +1,291 @@
+use crate::error::OrbyError;
+use crate::filter::Filter;
+use crate::logic::planner::{self, Plan};
+use crate::logic::zone::ZONE_BLOCK_SIZE;
+use crate::logic::{ring, OrbyRingBufferSilo};
+use crate::types::{Agg, AggValue, AggregateResult, Histogram};
+use rayon::iter::Either;
+use rayon::prelude::*;
+use std::collections::{BTreeSet, HashMap};
+
+/// ヒストグラムのバケット数の上限。途中結果は並列処理の分割ごとに確保されるため、
+/// 過大なバケット数による確保の膨張を防ぎます。
+pub const MAX_HISTOGRAM_BUCKETS: usize = 4096;
+
+/// レーンが範囲内であることを確認します。
+pub fn check_lane(name: &str, lane_count: usize, lane: usize) -> Result<(), OrbyError> {
+    if lane >= lane_count {
+        return Err(OrbyError::LaneCountMismatch {
+            pool_name: name.to_string(),
+            expected: lane_count,
+            found: lane + 1,
+        });
+    }
+    Ok(())
+}
+
+/// 集計対象のレーンが範囲内であり、ヒストグラムのバケット幅・数が 0 でなく、
+/// バケット数が `MAX_HISTOGRAM_BUCKETS` 以下であることを確認します。
+pub fn validate(name: &str, lane_count: usize, aggs: &[Agg]) -> Result<(), OrbyError> {
+    for agg in aggs {
+        let lane = match *agg {
+            Agg::Count => continue,
+            Agg::Min(lane) | Agg::Max(lane) | Agg::WrappingSum(lane) | Agg::SaturatingSum(lane) => {
+                lane
+            }
+            Agg::Histogram {
+                lane,
+                width,
+                buckets,
+                ..
+            } => {
+                if width == 0 || buckets == 0 {
+                    return Err(OrbyError::ConfigMismatch {
+                        name: name.to_string(),
+                        reason: "histogram width and bucket count must be non-zero".to_string(),
+                    });
+                }
+                if buckets > MAX_HISTOGRAM_BUCKETS {
+                    return Err(OrbyError::ConfigMismatch {
+                        name: name.to_string(),
+                        reason: format!(
+                            "histogram bucket count {} exceeds the limit of {}",
+                            buckets, MAX_HISTOGRAM_BUCKETS
+                        ),
+                    });
+                }
+                lane
+            }
+        };
+        check_lane(name, lane_count, lane)?;
+    }
+    Ok(())
+}
+
+/// 一致する行がない場合の集計結果。スレッドごとの途中結果の初期値にもなります。
+pub fn identity(aggs: &[Agg]) -> AggregateResult {
+    let values = aggs
+        .iter()
+        .map(|agg| match *agg {
+            Agg::Count => AggValue::Count(0),
+            Agg::Min(_) => AggValue::Min(None),
+            Agg::Max(_) => AggValue::Max(None),
+            Agg::WrappingSum(_) | Agg::SaturatingSum(_) => AggValue::Sum(0),
+            Agg::Histogram {
+                min,
+                width,
+                buckets,
+                ..
+            } => AggValue::Histogram(Histogram {
+                min,
+                width,
+                counts: vec![0; buckets],
+                below: 0,
+                above: 0,
+            }),
+        })
+        .collect();
+    AggregateResult { values }
+}
+
+/// 物理スロットの行を途中結果へ加えます。行を集めずに、集計対象のレーンのみを読みます。
+#[inline]
+fn add(aggs: &[Agg], acc: &mut AggregateResult, store: &OrbyRingBufferSilo, slot: usize) {
+    let value = |lane: usize| store.lanes[lane].buffer[slot].as_u128();
+    for (agg, state) in aggs.iter().zip(acc.values.iter_mut()) {
+        match (*agg, state) {
+            (Agg::Count, AggValue::Count(count)) => *count += 1,
+            (Agg::Min(lane), AggValue::Min(min)) => {
+                let v = value(lane);
+                *min = Some(min.map_or(v, |m| m.min(v)));
+            }
+            (Agg::Max(lane), AggValue::Max(max)) => {
+                let v = value(lane);
+                *max = Some(max.map_or(v, |m| m.max(v)));
+            }
+            (Agg::WrappingSum(lane), AggValue::Sum(sum)) => *sum = sum.wrapping_add(value(lane)),
+            (Agg::SaturatingSum(lane), AggValue::Sum(sum)) => {
+                *sum = sum.saturating_add(value(lane))
+            }
+            (Agg::Histogram { lane, .. }, AggValue::Histogram(histogram)) => {
+                let v = value(lane);
+                if v < histogram.min {
+                    histogram.below += 1;
+                } else {
+                    let bucket = (v - histogram.min) / histogram.width;
+                    let index = usize::try_from(bucket).ok();
+                    match index.and_then(|index| histogram.counts.get_mut(index)) {
+                        Some(count) => *count += 1,
+                        None => histogram.above += 1,
+                    }
+                }
+            }
+            _ => unreachable!("aggregate state follows the requested aggs"),
+        }
+    }
+}
+
+/// 二つの途中結果を統合します。シャードごとの集計結果の統合にも使用します。
+pub fn merge(aggs: &[Agg], mut acc: AggregateResult, other: AggregateResult) -> AggregateResult {
+    for ((agg, state), other) in aggs.iter().zip(acc.values.iter_mut()).zip(other.values) {
+        match (*agg, state, other) {
+            (_, AggValue::Count(count), AggValue::Count(more)) => *count += more,
+            (_, AggValue::Min(min), AggValue::Min(other)) => {
+                *min = match (*min, other) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                }
+            }
+            (_, AggValue::Max(max), AggValue::Max(other)) => *max = (*max).max(other),
+            (Agg::SaturatingSum(_), AggValue::Sum(sum), AggValue::Sum(more)) => {
+                *sum = sum.saturating_add(more)
+            }
+            (_, AggValue::Sum(sum), AggValue::Sum(more)) => *sum = sum.wrapping_add(more),
+            (_, AggValue::Histogram(histogram), AggValue::Histogram(more)) => {
+                for (count, added) in histogram.counts.iter_mut().zip(more.counts) {
+                    *count += added;
+                }
+                histogram.below += more.below;
+                histogram.above += more.above;
+            }
+            _ => unreachable!("aggregate results follow the requested aggs"),
+        }
+    }
+    acc
+}
+
+/// `query` と同じ経路（索引の候補・ブロックの読み飛ばし）で選んだ、フィルタに一致する生存行の
+/// 物理スロットを並列に列挙します。順序は保証されません。
+fn matching_slots<'a>(
+    store: &'a OrbyRingBufferSilo,
+    filter: &'a Filter,
+) -> impl ParallelIterator<Item = usize> + 'a {
+    let cutoff = ring::expiry_cutoff(store);
+    let live = move |slot: usize| ring::is_live(store, slot, cutoff);
+    let keep =
+        match planner::plan(store, filter) {
+            Plan::Candidates {
+                mut slots, exact, ..
+            } => {
+                // `Or` や重複した値の候補は同じスロットを含み得るため、一度だけ数える
+                slots.sort_unstable();
+                slots.dedup();
+                return Either::Left(slots.into_par_iter().with_min_len(1024).filter(
+                    move |&slot| live(slot) && (exact || ring::filter_matches(store, filter, slot)),
+                ));
+            }
+            Plan::Blocks { keep } => keep,
+            Plan::FullScan => vec![true; store.capacity.div_ceil(ZONE_BLOCK_SIZE)],
+        };
+    let ranges: Vec<std::ops::Range<usize>> = keep
+        .iter()
+        .enumerate()
+        .filter(|(_, &kept)| kept)
+        .map(|(block, _)| {
+            block * ZONE_BLOCK_SIZE..((block + 1) * ZONE_BLOCK_SIZE).min(store.capacity)
+        })
+        .collect();
+    Either::Right(
+        ranges
+            .into_par_iter()
+            .flat_map(|range| range.into_par_iter().with_min_len(1024))
+            .filter(move |&slot| live(slot) && ring::filter_matches(store, filter, slot)),
+    )
+}
+
+/// `Filter` に一致する生存行について、`aggs` の集計を並列に計算します。
+/// 各スレッドの途中結果を最後に統合し、行ごとのメモリ確保は行いません。
+/// `aggs` は事前に `validate` で確認しておく必要があります。
+pub fn aggregate(store: &OrbyRingBufferSilo, filter: &Filter, aggs: &[Agg]) -> AggregateResult {
+    if store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
+        return identity(aggs);
+    }
+    matching_slots(store, filter)
+        .fold(
+            || identity(aggs),
+            |mut acc, slot| {
+                add(aggs, &mut acc, store, slot);
+                acc
+            },
+        )
+        .reduce(|| identity(aggs), |a, b| merge(aggs, a, b))
+}
+
+/// キーごとの集計結果の二つの表を統合します。グループ数が `max_groups` を超える場合は `None` を返します。
+pub fn merge_groups(
+    aggs: &[Agg],
+    mut groups: HashMap<u128, AggregateResult>,
+    mut other: HashMap<u128, AggregateResult>,
+    max_groups: usize,
+) -> Option<HashMap<u128, AggregateResult>> {
+    if groups.len() < other.len() {
+        std::mem::swap(&mut groups, &mut other);
+    }
+    for (key, acc) in other {
+        let merged = match groups.remove(&key) {
+            Some(existing) => merge(aggs, existing, acc),
+            None => acc,
+        };
+        groups.insert(key, merged);
+    }
+    (groups.len() <= max_groups).then_some(groups)
+}
+
+/// `Filter` に一致する生存行を `lane` の値ごとにまとめ、グループごとに `aggs` の集計を計算します。
+/// 各スレッドが部分的な表を作成し、最後に統合します。いずれかの段階でグループ数が `max_groups` を
+/// 超えた時点で打ち切り、`None` を返します。
+pub fn group_by(
+    store: &OrbyRingBufferSilo,
+    lane: usize,
+    filter: &Filter,
+    aggs: &[Agg],
+    max_groups: usize,
+) -> Option<HashMap<u128, AggregateResult>> {
+    if store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
+        return Some(HashMap::new());
+    }
+    let keys = &store.lanes[lane].buffer;
+    matching_slots(store, filter)
+        .try_fold(HashMap::new, |mut groups, slot| {
+            let acc = groups
+                .entry(keys[slot].as_u128())
+                .or_insert_with(|| identity(aggs));
+            add(aggs, acc, store, slot);
+            (groups.len() <= max_groups).then_some(groups)
+        })
+        .try_reduce(HashMap::new, |a, b| merge_groups(aggs, a, b, max_groups))
+}
+
+/// 集合を小さい方から `limit` 件に切り詰めます。
+fn keep_smallest(values: &mut BTreeSet<u128>, limit: usize) {
+    while values.len() > limit {
+        values.pop_last();
+    }
+}
+
+/// `Filter` に一致する生存行の `lane` の異なる値を、小さい順に最大 `limit` 件返します。
+/// 各スレッドは小さい方から `limit` 件のみを保持するため、使用するメモリは行数ではなく `limit` に比例します。
+pub fn distinct(
+    store: &OrbyRingBufferSilo,
+    lane: usize,
+    filter: &Filter,
+    limit: usize,
+) -> Vec<u128> {
+    if limit == 0 || store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
+        return Vec::new();
+    }
+    let values = &store.lanes[lane].buffer;
+    matching_slots(store, filter)
+        .fold(BTreeSet::new, |mut acc, slot| {
+            acc.insert(values[slot].as_u128());
+            keep_smallest(&mut acc, limit);
+            acc
+        })
+        .reduce(BTreeSet::new, |mut a, b| {
+            a.extend(b);
+            keep_smallest(&mut a, limit);
+            a
+        })
+        .into_iter()
+        .collect()
+}
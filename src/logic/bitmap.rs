@@ -0,0 +1,142 @@
+/// 物理スロットごとの有効/墓標を表すビットマップ。
+/// ビットが立っているスロットのみが生存行として扱われ、全次元ゼロの行も正当なデータとして保持できます。
+#[derive(Debug, Clone, Default)]
+pub struct SlotBitmap {
+    words: Vec<u64>,
+    capacity: usize,
+}
+
+impl SlotBitmap {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            words: vec![0; capacity.div_ceil(64)],
+            capacity,
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, slot: usize) -> bool {
+        slot < self.capacity && self.words[slot / 64] & (1 << (slot % 64)) != 0
+    }
+
+    #[inline]
+    pub fn set(&mut self, slot: usize) {
+        self.words[slot / 64] |= 1 << (slot % 64);
+    }
+
+    #[inline]
+    pub fn clear(&mut self, slot: usize) {
+        self.words[slot / 64] &= !(1 << (slot % 64));
+    }
+
+    /// 指定スロットの状態を設定します。
+    #[inline]
+    pub fn assign(&mut self, slot: usize, valid: bool) {
+        if valid {
+            self.set(slot);
+        } else {
+            self.clear(slot);
+        }
+    }
+
+    /// すべてのビットを落とします。
+    pub fn clear_all(&mut self) {
+        self.words.fill(0);
+    }
+
+    /// 有効なスロット数を数えます。
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// 他のビットマップとの論理積をとります。
+    pub fn intersect_with(&mut self, other: &SlotBitmap) {
+        for (word, &rhs) in self.words.iter_mut().zip(&other.words) {
+            *word &= rhs;
+        }
+    }
+
+    /// 他のビットマップとの論理和をとります。
+    pub fn union_with(&mut self, other: &SlotBitmap) {
+        for (word, &rhs) in self.words.iter_mut().zip(&other.words) {
+            *word |= rhs;
+        }
+    }
+
+    /// 範囲内の有効なスロット数を、ワード単位で数えます。
+    pub fn count_ones_in(&self, range: std::ops::Range<usize>) -> usize {
+        let end = range.end.min(self.capacity);
+        let start = range.start.min(end);
+        (start / 64..end.div_ceil(64))
+            .map(|idx| {
+                let base = idx * 64;
+                let mut word = self.words[idx];
+                if start > base {
+                    word &= !0u64 << (start - base);
+                }
+                if end - base < 64 {
+                    word &= (1u64 << (end - base)) - 1;
+                }
+                word.count_ones() as usize
+            })
+            .sum()
+    }
+
+    /// 範囲内でビットが立っているスロットを降順に列挙します。
+    /// ゼロのワードは丸ごと読み飛ばします。
+    pub fn ones_desc(&self, range: std::ops::Range<usize>) -> impl Iterator<Item = usize> + '_ {
+        let end = range.end.min(self.capacity);
+        let start = range.start.min(end);
+        (start / 64..end.div_ceil(64)).rev().flat_map(move |idx| {
+            let base = idx * 64;
+            let mut word = self.words[idx];
+            if start > base {
+                word &= !0u64 << (start - base);
+            }
+            if end - base < 64 {
+                word &= (1u64 << (end - base)) - 1;
+            }
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = 63 - word.leading_zeros() as usize;
+                word &= !(1u64 << bit);
+                Some(base + bit)
+            })
+        })
+    }
+
+    /// 永続化用のバイト列（リトルエンディアンの u64 列）へ変換します。
+    /// `from_bytes` と対になるよう、ホストのバイト順に関係なく各ワードをリトルエンディアンで並べます。
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.words
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect()
+    }
+
+    /// 永続化されたバイト列から復元します。長さが一致しない場合は `None` を返します。
+    pub fn from_bytes(capacity: usize, bytes: &[u8]) -> Option<Self> {
+        let mut bitmap = Self::new(capacity);
+        if bytes.len() != bitmap.words.len() * 8 {
+            return None;
+        }
+        for (word, chunk) in bitmap.words.iter_mut().zip(bytes.chunks_exact(8)) {
+            *word = u64::from_le_bytes(chunk.try_into().ok()?);
+        }
+        Some(bitmap)
+    }
+
+    /// 永続化ファイルのバイトサイズを返します。
+    pub fn byte_len(capacity: usize) -> usize {
+        capacity.div_ceil(64) * 8
+    }
+
+    /// 指定スロットを含むワードの（ファイル上のバイトオフセット, 値）を返します。
+    /// 部分的な書き戻しに使用します。
+    pub fn word_at(&self, slot: usize) -> (usize, u64) {
+        let idx = slot / 64;
+        (idx * 8, self.words[idx])
+    }
+}
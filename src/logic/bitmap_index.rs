@@ -0,0 +1,222 @@
+use crate::filter::Filter;
+use crate::logic::bitmap::SlotBitmap;
+use crate::logic::OrbyRingBufferSilo;
+use std::collections::HashMap;
+
+/// ビットマップ索引が保持する値の種類数の上限。値ごとに `capacity / 8` バイトを使用するため、
+/// これを超える高カーディナリティのレーンでは索引を破棄し、走査に任せます。
+pub const MAX_BITMAP_VALUES: usize = 256;
+
+/// 低カーディナリティのレーン（`bitmap_lane`）向けのビットマップ索引。
+/// 値ごとに、その値を持つ生存行のスロットを表すビットマップを保持します。
+///
+/// 値の種類数が `MAX_BITMAP_VALUES` を超えた時点で全てのビットマップを解放して `overflowed` とし、
+/// 以降の検索では使用しません。再構築（コンパクション・再初期化・ロード）の時点で上限内に収まっていれば
+/// 索引は再び有効になります。
+#[derive(Debug, Clone, Default)]
+pub struct BitmapIndex {
+    pub lane: usize,
+    capacity: usize,
+    /// 値 → (スロットのビットマップ, 立っているビット数)
+    values: HashMap<u128, (SlotBitmap, usize)>,
+    overflowed: bool,
+}
+
+impl BitmapIndex {
+    pub fn new(lane: usize, capacity: usize) -> Self {
+        Self {
+            lane,
+            capacity,
+            values: HashMap::new(),
+            overflowed: false,
+        }
+    }
+
+    /// 値の種類数が上限を超え、索引が無効になっているかを返します。
+    pub fn is_overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    #[inline]
+    pub fn insert(&mut self, key: u128, slot: usize) {
+        if self.overflowed {
+            return;
+        }
+        if self.values.len() >= MAX_BITMAP_VALUES && !self.values.contains_key(&key) {
+            self.values = HashMap::new();
+            self.overflowed = true;
+            return;
+        }
+        let capacity = self.capacity;
+        let (bits, count) = self
+            .values
+            .entry(key)
+            .or_insert_with(|| (SlotBitmap::new(capacity), 0));
+        if !bits.get(slot) {
+            bits.set(slot);
+            *count += 1;
+        }
+    }
+
+    /// 値のビットを落とします。ビットがなくなった値はビットマップごと取り除きます。
+    #[inline]
+    pub fn remove(&mut self, key: u128, slot: usize) {
+        if let Some((bits, count)) = self.values.get_mut(&key) {
+            if bits.get(slot) {
+                bits.clear(slot);
+                *count -= 1;
+            }
+            if *count == 0 {
+                self.values.remove(&key);
+            }
+        }
+    }
+
+    /// 値を持つ行のビットマップを返します。
+    pub fn get(&self, key: u128) -> Option<&SlotBitmap> {
+        self.values.get(&key).map(|(bits, _)| bits)
+    }
+
+    /// 索引に登録されている値の種類数を返します。
+    pub fn cardinality(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+        self.overflowed = false;
+    }
+
+    /// 値ごとのビットマップを列挙します。Vault への保存に使用します。
+    pub fn entries(&self) -> impl Iterator<Item = (u128, &SlotBitmap)> {
+        self.values.iter().map(|(&key, (bits, _))| (key, bits))
+    }
+
+    /// Vault から読み込んだビットマップで索引を置き換えます。
+    pub fn restore(&mut self, values: HashMap<u128, SlotBitmap>) {
+        self.values = values
+            .into_iter()
+            .map(|(key, bits)| {
+                let count = bits.count_ones();
+                (key, (bits, count))
+            })
+            .collect();
+    }
+}
+
+/// 指定スロットの生存行を全てのビットマップ索引へ登録します。
+#[inline]
+pub fn remember(store: &mut OrbyRingBufferSilo, slot: usize) {
+    if store.bitmaps.is_empty() || !store.valid.get(slot) {
+        return;
+    }
+    for index in &mut store.bitmaps {
+        index.insert(store.lanes[index.lane].buffer[slot].as_u128(), slot);
+    }
+}
+
+/// 指定スロットの生存行を全てのビットマップ索引から外します。行を上書き・削除する前に呼び出します。
+#[inline]
+pub fn forget(store: &mut OrbyRingBufferSilo, slot: usize) {
+    if store.bitmaps.is_empty() || !store.valid.get(slot) {
+        return;
+    }
+    for index in &mut store.bitmaps {
+        index.remove(store.lanes[index.lane].buffer[slot].as_u128(), slot);
+    }
+}
+
+/// 有効ビットマップとレーンの内容から全てのビットマップ索引を再構築します。
+pub fn rebuild(store: &mut OrbyRingBufferSilo) {
+    if store.bitmaps.is_empty() {
+        return;
+    }
+    let mut bitmaps = std::mem::take(&mut store.bitmaps);
+    for index in &mut bitmaps {
+        index.clear();
+        if store.lanes.is_empty() {
+            continue;
+        }
+        for (slot, cell) in store.lanes[index.lane].buffer.iter().enumerate() {
+            if store.valid.get(slot) {
+                index.insert(cell.as_u128(), slot);
+            }
+        }
+    }
+    store.bitmaps = bitmaps;
+}
+
+/// フィルタをビットマップ演算で評価し、候補スロットのビットマップと、それが厳密な結果かを返します。
+/// `And` の一部の条件のみ索引で評価できる場合は、候補（上位集合）として `false` を返します。
+/// 索引で候補を絞り込めない場合は `None` を返します。
+fn evaluate(store: &OrbyRingBufferSilo, filter: &Filter) -> Option<(SlotBitmap, bool)> {
+    let lane_index = |lane: usize| {
+        store
+            .bitmaps
+            .iter()
+            .find(|index| index.lane == lane && !index.overflowed)
+    };
+    let empty = || SlotBitmap::new(store.capacity);
+    match filter {
+        Filter::Eq { lane, value } => {
+            let index = lane_index(*lane)?;
+            Some((index.get(*value).cloned().unwrap_or_else(empty), true))
+        }
+        Filter::In { lane, values } => {
+            let index = lane_index(*lane)?;
+            let mut bits = empty();
+            for value in values {
+                if let Some(found) = index.get(*value) {
+                    bits.union_with(found);
+                }
+            }
+            Some((bits, true))
+        }
+        // ビットマップは値ごとの索引のため、範囲・マスク条件は評価できない
+        Filter::Range { .. } | Filter::Mask { .. } => None,
+        // 有効ビットマップとの差で求められるが、候補がほぼ全件になるため走査に任せる
+        Filter::Not(_) => None,
+        Filter::And(filters) => {
+            let mut result: Option<SlotBitmap> = None;
+            let mut exact = true;
+            for sub in filters {
+                match evaluate(store, sub) {
+                    Some((bits, sub_exact)) => {
+                        exact &= sub_exact;
+                        match result.as_mut() {
+                            Some(acc) => acc.intersect_with(&bits),
+                            None => result = Some(bits),
+                        }
+                    }
+                    None => exact = false,
+                }
+            }
+            match result {
+                Some(bits) => Some((bits, exact)),
+                // 空の `And` は全ての生存行に一致する
+                None if filters.is_empty() => Some((store.valid.clone(), true)),
+                None => None,
+            }
+        }
+        Filter::Or(filters) => {
+            let mut bits = empty();
+            let mut exact = true;
+            for sub in filters {
+                let (sub_bits, sub_exact) = evaluate(store, sub)?;
+                bits.union_with(&sub_bits);
+                exact &= sub_exact;
+            }
+            Some((bits, exact))
+        }
+    }
+}
+
+/// ビットマップ索引を使用して、フィルタに一致し得る行の物理スロットと、それが厳密な結果かを返します。
+/// 索引で候補を絞り込めない場合は `None` を返し、呼び出し側で全件走査させます。
+pub fn candidates(store: &OrbyRingBufferSilo, filter: &Filter) -> Option<(Vec<usize>, bool)> {
+    if store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
+        return None;
+    }
+    let (bits, exact) = evaluate(store, filter)?;
+    Some((bits.ones_desc(0..store.capacity).collect(), exact))
+}
@@ -0,0 +1,147 @@
+use crate::logic::zone::ZONE_BLOCK_SIZE;
+use crate::logic::OrbyRingBufferSilo;
+
+/// 1 スロットあたりのビット数。7 個のハッシュと組み合わせて偽陽性率は約 1% です。
+const BITS_PER_SLOT: usize = 10;
+const HASH_COUNT: u64 = 7;
+
+/// ブルームフィルタレーン（`bloom_lane`）の値を、ゾーンマップと同じブロック単位で要約するフィルタ。
+/// 「このブロックには値が存在しない」ことを確定できるため、該当しないブロックの走査を省略できます。
+///
+/// ブルームフィルタは値を取り除けないため、上書き・削除されたブロックは `stale` として印を付け、
+/// リングの周回でブロックを書き終えた時点、またはコンパクション後に生存行から作り直します。
+/// それまでの間は古い値による偽陽性が増えるだけで、偽陰性は生じません。
+#[derive(Debug, Clone, Default)]
+pub struct BlockBloom {
+    pub lane: usize,
+    words_per_block: usize,
+    bits: Vec<u64>,
+    stale: Vec<bool>,
+}
+
+impl BlockBloom {
+    pub fn new(lane: usize, capacity: usize) -> Self {
+        let blocks = capacity.div_ceil(ZONE_BLOCK_SIZE);
+        let words_per_block = (ZONE_BLOCK_SIZE * BITS_PER_SLOT).div_ceil(64);
+        Self {
+            lane,
+            words_per_block,
+            bits: vec![0; blocks * words_per_block],
+            stale: vec![false; blocks],
+        }
+    }
+
+    /// 値から `HASH_COUNT` 個のビット位置を二重ハッシュ法で求めます。
+    #[inline]
+    fn positions(words_per_block: usize, key: u128) -> impl Iterator<Item = usize> {
+        let folded = (key as u64) ^ ((key >> 64) as u64);
+        let h1 = folded.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        let h2 = (folded ^ (folded >> 31)).wrapping_mul(0xBF58_476D_1CE4_E5B9) | 1;
+        let bits = (words_per_block * 64) as u64;
+        (0..HASH_COUNT).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % bits) as usize)
+    }
+
+    #[inline]
+    fn insert(&mut self, block: usize, key: u128) {
+        let base = block * self.words_per_block;
+        for pos in Self::positions(self.words_per_block, key) {
+            self.bits[base + pos / 64] |= 1 << (pos % 64);
+        }
+    }
+
+    /// ブロックに値が含まれ得るかを判定します。`false` であれば確実に含まれません。
+    #[inline]
+    pub fn may_contain(&self, block: usize, key: u128) -> bool {
+        let base = block * self.words_per_block;
+        Self::positions(self.words_per_block, key)
+            .all(|pos| self.bits[base + pos / 64] & (1 << (pos % 64)) != 0)
+    }
+
+    fn clear_block(&mut self, block: usize) {
+        let base = block * self.words_per_block;
+        self.bits[base..base + self.words_per_block].fill(0);
+        self.stale[block] = false;
+    }
+}
+
+#[inline]
+fn has_mem(store: &OrbyRingBufferSilo) -> bool {
+    !store.lanes.is_empty() && !store.lanes[0].buffer.is_empty()
+}
+
+/// 指定スロットの生存行の値をフィルタへ登録します。行を書き込んだ後に呼び出します。
+#[inline]
+pub fn remember(store: &mut OrbyRingBufferSilo, slot: usize) {
+    if !has_mem(store) || !store.valid.get(slot) {
+        return;
+    }
+    if let Some(bloom) = store.bloom.as_mut() {
+        let key = store.lanes[bloom.lane].buffer[slot].as_u128();
+        bloom.insert(slot / ZONE_BLOCK_SIZE, key);
+    }
+}
+
+/// 指定スロットの行が上書き・削除されることを記録します。ブロックは作り直しの対象になります。
+#[inline]
+pub fn forget(store: &mut OrbyRingBufferSilo, slot: usize) {
+    if let Some(bloom) = store.bloom.as_mut() {
+        if store.valid.get(slot) {
+            bloom.stale[slot / ZONE_BLOCK_SIZE] = true;
+        }
+    }
+}
+
+/// 書き込みがブロックの末尾に達し、そのブロックに上書き・削除があった場合はフィルタを作り直します。
+/// リングの周回ではブロック単位で古い値が入れ替わるため、一周ごとに一度だけ作り直されます。
+#[inline]
+pub fn settle(store: &mut OrbyRingBufferSilo, slot: usize) {
+    let Some(bloom) = store.bloom.as_ref() else {
+        return;
+    };
+    let block = slot / ZONE_BLOCK_SIZE;
+    let block_end = (slot + 1).is_multiple_of(ZONE_BLOCK_SIZE) || slot + 1 == store.capacity;
+    if block_end && bloom.stale[block] {
+        rebuild_block(store, block);
+    }
+}
+
+/// ブロックのフィルタを生存行から作り直します。
+fn rebuild_block(store: &mut OrbyRingBufferSilo, block: usize) {
+    let Some(mut bloom) = store.bloom.take() else {
+        return;
+    };
+    bloom.clear_block(block);
+    if has_mem(store) {
+        let start = block * ZONE_BLOCK_SIZE;
+        let end = (start + ZONE_BLOCK_SIZE).min(store.capacity);
+        let buffer = &store.lanes[bloom.lane].buffer[start..end];
+        for (slot, cell) in (start..end).zip(buffer) {
+            if store.valid.get(slot) {
+                bloom.insert(block, cell.as_u128());
+            }
+        }
+    }
+    store.bloom = Some(bloom);
+}
+
+/// 全ブロックのフィルタを作り直します。
+/// コンパクションやロード後など、行がブロックをまたいで移動した後に使用します。
+pub fn rebuild(store: &mut OrbyRingBufferSilo) {
+    let blocks = match store.bloom.as_ref() {
+        Some(bloom) => bloom.stale.len(),
+        None => return,
+    };
+    for block in 0..blocks {
+        rebuild_block(store, block);
+    }
+}
+
+/// `lane` にフィルタがあれば、`keys` のいずれかを含み得るブロックかを判定する述語を返します。
+pub fn block_filter<'a>(
+    store: &'a OrbyRingBufferSilo,
+    lane: usize,
+    keys: &'a [u128],
+) -> Option<impl Fn(usize) -> bool + 'a> {
+    let bloom = store.bloom.as_ref().filter(|bloom| bloom.lane == lane)?;
+    Some(move |block: usize| keys.iter().any(|&key| bloom.may_contain(block, key)))
+}
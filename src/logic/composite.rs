@@ -0,0 +1,173 @@
+use crate::filter::Filter;
+use crate::logic::OrbyRingBufferSilo;
+use std::collections::{BTreeMap, HashMap};
+
+/// 複数レーンの値の組（宣言順）から物理スロットを引く複合索引。
+/// キーを辞書順に保持するため、完全一致に加えて先頭レーンのみを指定した前方一致検索ができます。
+#[derive(Debug, Clone, Default)]
+pub struct CompositeIndex {
+    pub lanes: Vec<usize>,
+    slots: BTreeMap<Vec<u128>, Vec<usize>>,
+}
+
+impl CompositeIndex {
+    pub fn new(lanes: Vec<usize>) -> Self {
+        Self {
+            lanes,
+            slots: BTreeMap::new(),
+        }
+    }
+
+    #[inline]
+    pub fn insert(&mut self, key: Vec<u128>, slot: usize) {
+        self.slots.entry(key).or_default().push(slot);
+    }
+
+    #[inline]
+    pub fn remove(&mut self, key: &[u128], slot: usize) {
+        if let Some(slots) = self.slots.get_mut(key) {
+            if let Some(pos) = slots.iter().position(|&s| s == slot) {
+                slots.swap_remove(pos);
+            }
+            if slots.is_empty() {
+                self.slots.remove(key);
+            }
+        }
+    }
+
+    /// 先頭レーンの値 `prefix` に一致する行の物理スロットを返します。
+    /// `prefix` が全レーン分あれば完全一致検索になります。順序は保証されません。
+    pub fn lookup_prefix(&self, prefix: &[u128]) -> Vec<usize> {
+        if prefix.len() == self.lanes.len() {
+            return self.slots.get(prefix).cloned().unwrap_or_default();
+        }
+        // 辞書順では、前方一致するキーは `prefix` 自身の直後に連続して並ぶ
+        self.slots
+            .range(prefix.to_vec()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .flat_map(|(_, slots)| slots.iter().copied())
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+    }
+
+    /// 索引の内容を（キー, 物理スロット）の組で列挙します。Vault への保存に使用します。
+    pub fn entries(&self) -> impl Iterator<Item = (&[u128], &[usize])> {
+        self.slots
+            .iter()
+            .map(|(key, slots)| (key.as_slice(), slots.as_slice()))
+    }
+
+    /// Vault から読み込んだ内容で索引を置き換えます。
+    pub fn restore(&mut self, slots: BTreeMap<Vec<u128>, Vec<usize>>) {
+        self.slots = slots;
+    }
+
+    fn key_at(&self, store: &OrbyRingBufferSilo, slot: usize) -> Vec<u128> {
+        self.lanes
+            .iter()
+            .map(|&lane| store.lanes[lane].buffer[slot].as_u128())
+            .collect()
+    }
+}
+
+/// 指定スロットの生存行を全ての複合索引へ登録します。
+#[inline]
+pub fn remember(store: &mut OrbyRingBufferSilo, slot: usize) {
+    if store.composites.is_empty() || !store.valid.get(slot) {
+        return;
+    }
+    let mut composites = std::mem::take(&mut store.composites);
+    for index in &mut composites {
+        index.insert(index.key_at(store, slot), slot);
+    }
+    store.composites = composites;
+}
+
+/// 指定スロットの生存行を全ての複合索引から外します。行を上書き・削除する前に呼び出します。
+#[inline]
+pub fn forget(store: &mut OrbyRingBufferSilo, slot: usize) {
+    if store.composites.is_empty() || !store.valid.get(slot) {
+        return;
+    }
+    let mut composites = std::mem::take(&mut store.composites);
+    for index in &mut composites {
+        let key = index.key_at(store, slot);
+        index.remove(&key, slot);
+    }
+    store.composites = composites;
+}
+
+/// 有効ビットマップとレーンの内容から全ての複合索引を再構築します。
+pub fn rebuild(store: &mut OrbyRingBufferSilo) {
+    if store.composites.is_empty() {
+        return;
+    }
+    let mut composites = std::mem::take(&mut store.composites);
+    for index in &mut composites {
+        index.clear();
+        if store.lanes.is_empty() {
+            continue;
+        }
+        for slot in 0..store.capacity {
+            if store.valid.get(slot) {
+                index.insert(index.key_at(store, slot), slot);
+            }
+        }
+    }
+    store.composites = composites;
+}
+
+/// フィルタからレーンごとの等値条件を取り出します。
+/// 等値条件以外を含む場合は、取り出した条件だけでは厳密でないことを `false` で示します。
+fn equalities(filter: &Filter) -> (HashMap<usize, u128>, bool) {
+    let mut eqs = HashMap::new();
+    let mut exact = true;
+    let mut push = |lane: usize, value: u128, exact: &mut bool| {
+        // 同じレーンに異なる値を求める条件は索引では表せないため、検証に回す
+        if eqs.insert(lane, value).is_some_and(|prev| prev != value) {
+            *exact = false;
+        }
+    };
+    match filter {
+        Filter::Eq { lane, value } => push(*lane, *value, &mut exact),
+        Filter::And(filters) => {
+            for sub in filters {
+                match sub {
+                    Filter::Eq { lane, value } => push(*lane, *value, &mut exact),
+                    _ => exact = false,
+                }
+            }
+        }
+        _ => exact = false,
+    }
+    (eqs, exact)
+}
+
+/// 複合索引を使用して、フィルタの等値条件に一致し得る行の物理スロットを返します。
+/// 条件が最も多くの先頭レーンを覆う索引を選び、索引の条件だけで厳密に判定できたかを併せて返します。
+/// 先頭レーンに等値条件がある索引がない場合は `None` を返します。
+pub fn candidates(store: &OrbyRingBufferSilo, filter: &Filter) -> Option<(Vec<usize>, bool)> {
+    if store.composites.is_empty() {
+        return None;
+    }
+    let (eqs, exact) = equalities(filter);
+    let (index, prefix) = store
+        .composites
+        .iter()
+        .map(|index| {
+            let prefix: Vec<u128> = index
+                .lanes
+                .iter()
+                .map_while(|lane| eqs.get(lane).copied())
+                .collect();
+            (index, prefix)
+        })
+        .filter(|(_, prefix)| !prefix.is_empty())
+        .max_by_key(|(_, prefix)| prefix.len())?;
+    // 索引の先頭レーン以外にも条件が残る場合は、候補の行で確認が必要
+    let covered = prefix.len() == eqs.len();
+    Some((index.lookup_prefix(&prefix), exact && covered))
+}
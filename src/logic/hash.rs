@@ -0,0 +1,429 @@
+use crate::error::OrbyError;
+use crate::filter::Filter;
+use crate::logic::{planner, zone, OrbyRingBufferSilo, PersistenceChanges, RingOperation};
+use crate::types::{LogicMode, PulseCell};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// キーからホームスロットを算出します。
+/// 配置は Vault にそのまま永続化されるため、プロセスをまたいで決定的なハッシュ関数を使用します。
+/// シャードの振り分けにも同じ関数を使用します。
+#[inline]
+pub(crate) fn home_slot(key: u128, capacity: usize) -> usize {
+    let folded = (key as u64) ^ ((key >> 64) as u64);
+    let mixed = folded.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    ((mixed as u128 * capacity as u128) >> 64) as usize
+}
+
+/// キーレーン上の値を取得します。
+#[inline]
+fn key_at(store: &OrbyRingBufferSilo, slot: usize) -> u128 {
+    store.lanes[store.key_lane].buffer[slot].as_u128()
+}
+
+/// キーを線形探索で探します。
+/// 見つかった場合は `Ok(スロット)`、見つからない場合は `Err(最初の空きスロット)` を返します。
+/// テーブルが満杯で見つからない場合は `Err(None)` です。
+fn probe(store: &OrbyRingBufferSilo, key: u128) -> Result<usize, Option<usize>> {
+    let cap = store.capacity;
+    if cap == 0 {
+        return Err(None);
+    }
+    let mut slot = home_slot(key, cap);
+    for _ in 0..cap {
+        if !store.valid.get(slot) {
+            return Err(Some(slot));
+        }
+        if key_at(store, slot) == key {
+            return Ok(slot);
+        }
+        slot = (slot + 1) % cap;
+    }
+    Err(None)
+}
+
+/// 行データを物理スロットへ垂直に焼き付けます。
+fn write_row(store: &mut OrbyRingBufferSilo, slot: usize, row: &[u128]) {
+    for (lane, &val) in store.lanes.iter_mut().zip(row.iter()) {
+        lane.buffer[slot] = PulseCell::new(val);
+    }
+    store.valid.set(slot);
+    zone::note_row(store, slot);
+}
+
+/// 行を読み出します。
+fn read_row(store: &OrbyRingBufferSilo, slot: usize) -> Vec<u128> {
+    store
+        .lanes
+        .iter()
+        .map(|lane| lane.buffer[slot].as_u128())
+        .collect()
+}
+
+/// 行をキーのスロットへ配置します。既存のキーであれば上書きします。
+/// 空きが必要な場合の容量チェックは呼び出し側で行います。
+fn place_row(store: &mut OrbyRingBufferSilo, row: &[u128]) -> Option<usize> {
+    let slot = match probe(store, row[store.key_lane]) {
+        Ok(slot) => slot,
+        Err(Some(slot)) => {
+            store.len += 1;
+            store.cursor = store.cursor.max(slot + 1);
+            slot
+        }
+        Err(None) => return None,
+    };
+    write_row(store, slot, row);
+    Some(slot)
+}
+
+/// 指定スロットの行を取り除き、後続のクラスタを後方シフト削除で詰めます。
+/// 墓標を残さないため、探索長は削除後も増えません。
+/// 再配置された物理範囲を `Shift` 操作として `changes` に記録します。
+fn remove_slot(store: &mut OrbyRingBufferSilo, slot: usize, changes: &mut PersistenceChanges) {
+    let cap = store.capacity;
+    let mut hole = slot;
+    let mut next = (slot + 1) % cap;
+
+    while next != slot {
+        if !store.valid.get(next) {
+            break;
+        }
+        // ホームスロットが (hole, next] に含まれる行は、穴へ移動すると探索不能になる
+        let home = home_slot(key_at(store, next), cap);
+        let stays = if hole <= next {
+            hole < home && home <= next
+        } else {
+            hole < home || home <= next
+        };
+        if !stays {
+            let row = read_row(store, next);
+            write_row(store, hole, &row);
+            hole = next;
+        }
+        next = (next + 1) % cap;
+    }
+
+    for lane in &mut store.lanes {
+        lane.buffer[hole] = PulseCell::new(0);
+    }
+    store.valid.clear(hole);
+    if store.len > 0 {
+        store.len -= 1;
+    }
+
+    // 再配置の範囲（折り返す場合は二つ）を記録
+    if hole >= slot {
+        changes.push(RingOperation::Shift {
+            start: slot,
+            end: hole + 1,
+        });
+    } else {
+        changes.push(RingOperation::Shift {
+            start: slot,
+            end: cap,
+        });
+        changes.push(RingOperation::Shift {
+            start: 0,
+            end: hole + 1,
+        });
+    }
+}
+
+/// 次元数を検証します。
+fn validate_row(store: &OrbyRingBufferSilo, row: &[u128]) -> Result<(), OrbyError> {
+    let dim = store.ring_buffer_lane_count;
+    if row.len() != dim {
+        return Err(OrbyError::LaneCountMismatch {
+            pool_name: store.name.clone(),
+            expected: dim,
+            found: row.len(),
+        });
+    }
+    Ok(())
+}
+
+/// ハッシュ配置に基づくバッチ挿入ロジック。
+/// 既存のキーを持つ行は上書きされます。使用したスロット番号を挿入順に返します。
+/// 空きが足りない場合は一件も書き込まずに `StorageFull` を返します。
+pub fn insert_batch<T, I>(
+    store: &mut OrbyRingBufferSilo,
+    items: I,
+) -> Result<(Vec<usize>, PersistenceChanges), OrbyError>
+where
+    I: Iterator<Item = T>,
+    T: AsRef<[u128]>,
+{
+    let mut changes = PersistenceChanges::new();
+
+    let raw_rows: Vec<Vec<u128>> = items.map(|item| item.as_ref().to_vec()).collect();
+    if raw_rows.is_empty() {
+        return Ok((Vec::new(), changes));
+    }
+
+    // 1. 事前検証（次元数と空き容量）
+    for row in &raw_rows {
+        validate_row(store, row)?;
+    }
+    let key_lane = store.key_lane;
+    let new_keys: HashSet<u128> = raw_rows
+        .iter()
+        .map(|row| row[key_lane])
+        .filter(|&key| probe(store, key).is_err())
+        .collect();
+    if store.len + new_keys.len() > store.capacity {
+        return Err(OrbyError::StorageFull {
+            pool_name: store.name.clone(),
+            capacity: store.capacity,
+        });
+    }
+
+    // 2. スロット配置と書き込み
+    let mut slots = Vec::with_capacity(raw_rows.len());
+    for row in raw_rows {
+        // 事前検証済みのため必ず配置できる
+        let slot = place_row(store, &row).expect("slot availability was checked");
+        slots.push(slot);
+
+        // AOF 再生時は同じ状態から同じスロットへ再配置される
+        changes.push(RingOperation::Insert {
+            cursor: slot,
+            row_count: 1,
+            data: vec![row],
+        });
+    }
+
+    changes.push(RingOperation::HeaderUpdate {
+        len: store.len,
+        cursor: store.cursor,
+    });
+
+    Ok((slots, changes))
+}
+
+/// キーに一致する行を O(1) で取得します。
+pub fn get(store: &OrbyRingBufferSilo, key: u128) -> Option<Arc<[u128]>> {
+    if store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
+        return None;
+    }
+    probe(store, key)
+        .ok()
+        .map(|slot| Arc::from(read_row(store, slot)))
+}
+
+/// `LogicMode::HashSlot` のキーレーンに対する `Eq` / `In` 条件をハッシュ探索で評価し、
+/// 一致する行の物理スロットと、それが厳密な結果かを返します。キーレーンで絞り込めない場合は `None` を返します。
+pub fn candidates(store: &OrbyRingBufferSilo, filter: &Filter) -> Option<(Vec<usize>, bool)> {
+    if !matches!(store.logic_mode, LogicMode::HashSlot)
+        || store.lanes.is_empty()
+        || store.lanes[0].buffer.is_empty()
+    {
+        return None;
+    }
+    let key_lane = store.key_lane;
+    planner::combine(filter, &|leaf| match leaf {
+        Filter::Eq { lane, value } if *lane == key_lane => {
+            Some((probe(store, *value).ok().into_iter().collect(), true))
+        }
+        Filter::In { lane, values } if *lane == key_lane => {
+            let slots = values
+                .iter()
+                .filter_map(|&value| probe(store, value).ok())
+                .collect();
+            Some((slots, true))
+        }
+        _ => None,
+    })
+}
+
+/// 指定したスロットの行を削除します。
+pub fn delete(store: &mut OrbyRingBufferSilo, slot: usize) -> (bool, PersistenceChanges) {
+    let mut changes = PersistenceChanges::new();
+    if !store.valid.get(slot) {
+        return (false, changes);
+    }
+
+    changes.push(RingOperation::Delete {
+        physical_index: slot,
+    });
+    remove_slot(store, slot, &mut changes);
+    changes.push(RingOperation::HeaderUpdate {
+        len: store.len,
+        cursor: store.cursor,
+    });
+
+    (true, changes)
+}
+
+/// 特定のカラム値に一致するレコードを削除します。
+/// キーレーンであればハッシュ探索、それ以外は全スロットを走査します。
+pub fn purge_by_id(store: &mut OrbyRingBufferSilo, index: usize, id: u128) -> PersistenceChanges {
+    let mut changes = PersistenceChanges::new();
+    if index >= store.ring_buffer_lane_count {
+        return changes;
+    }
+
+    let keys = matching_keys(store, index, id);
+    if keys.is_empty() {
+        return changes;
+    }
+
+    let mut removed = Vec::with_capacity(keys.len());
+    for key in keys {
+        if let Ok(slot) = probe(store, key) {
+            remove_slot(store, slot, &mut changes);
+            removed.push(slot);
+        }
+    }
+
+    changes.push(RingOperation::Purge {
+        physical_indices: removed,
+        id,
+        logical_column: index,
+    });
+    changes.push(RingOperation::HeaderUpdate {
+        len: store.len,
+        cursor: store.cursor,
+    });
+
+    changes
+}
+
+/// 指定した ID を持つ行を更新します。
+/// キーレーンの値が変わる場合は、新しいキーのスロットへ移動します。
+/// 新しいキーを別の行が既に持つ場合や、複数の行が同じキーへ集約される場合は何も書き込まずに `false` を返します。
+pub fn update_by_id(
+    store: &mut OrbyRingBufferSilo,
+    index: usize,
+    id: u128,
+    new_data: &[u128],
+) -> (bool, PersistenceChanges) {
+    let mut changes = PersistenceChanges::new();
+    if index >= store.ring_buffer_lane_count || validate_row(store, new_data).is_err() {
+        return (false, changes);
+    }
+
+    let keys = matching_keys(store, index, id);
+    if keys.is_empty() {
+        return (false, changes);
+    }
+
+    // キーは行ごとに一意なため、別の行を上書きせずに書き換えられるのは
+    // 対象が一行で、新しいキーが自身のキーか未使用の場合に限られる
+    let new_key = new_data[store.key_lane];
+    let collides = match keys.as_slice() {
+        [key] => *key != new_key && probe(store, new_key).is_ok(),
+        _ => keys.iter().any(|&key| key != new_key),
+    };
+    if collides {
+        return (false, changes);
+    }
+
+    for key in keys {
+        if key != new_key {
+            if let Ok(slot) = probe(store, key) {
+                remove_slot(store, slot, &mut changes);
+            }
+        }
+        // 同じキーであればその場で上書きし、変わる場合は旧キーの削除で生じた空きへ配置する
+        if let Some(slot) = place_row(store, new_data) {
+            changes.push(RingOperation::Update {
+                physical_index: slot,
+                id,
+                new_data: new_data.to_vec(),
+                logical_column: index,
+            });
+        }
+    }
+
+    changes.push(RingOperation::HeaderUpdate {
+        len: store.len,
+        cursor: store.cursor,
+    });
+
+    (true, changes)
+}
+
+/// 指定した ID があれば更新、なければキーのスロットへ挿入します。
+pub fn upsert(
+    store: &mut OrbyRingBufferSilo,
+    index: usize,
+    id: u128,
+    data: &[u128],
+) -> Result<PersistenceChanges, OrbyError> {
+    let (found, changes) = update_by_id(store, index, id, data);
+    if found {
+        return Ok(changes);
+    }
+    // 一致する行がありながら更新されなかった場合は、新しいキーが別の行と衝突している
+    if validate_row(store, data).is_ok()
+        && index < store.ring_buffer_lane_count
+        && !matching_keys(store, index, id).is_empty()
+    {
+        return Err(OrbyError::DuplicateKey {
+            pool_name: store.name.clone(),
+            lane: store.key_lane,
+            value: data[store.key_lane],
+        });
+    }
+    insert_batch(store, std::iter::once(data)).map(|(_, changes)| changes)
+}
+
+/// 指定レーンの値が `id` に一致する行のキー一覧を返します。
+fn matching_keys(store: &OrbyRingBufferSilo, index: usize, id: u128) -> Vec<u128> {
+    if index == store.key_lane {
+        return match probe(store, id) {
+            Ok(_) => vec![id],
+            Err(_) => Vec::new(),
+        };
+    }
+    (0..store.cursor.min(store.capacity))
+        .filter(|&slot| store.valid.get(slot))
+        .filter(|&slot| store.lanes[index].buffer[slot].as_u128() == id)
+        .map(|slot| key_at(store, slot))
+        .collect()
+}
+
+/// プールの状態をリセットし、指定されたデータをハッシュ配置で再初期化します。
+pub fn truncate<T, I>(
+    store: &mut OrbyRingBufferSilo,
+    items: I,
+) -> Result<PersistenceChanges, OrbyError>
+where
+    I: Iterator<Item = T>,
+    T: AsRef<[u128]>,
+{
+    let rows: Vec<Vec<u128>> = items.map(|item| item.as_ref().to_vec()).collect();
+    for row in &rows {
+        validate_row(store, row)?;
+    }
+
+    for lane in &mut store.lanes {
+        lane.buffer.fill(PulseCell::new(0));
+    }
+    store.valid.clear_all();
+    store.len = 0;
+    store.cursor = 0;
+
+    for row in rows.iter().take(store.capacity) {
+        place_row(store, row);
+    }
+    zone::rebuild(store);
+
+    let mut changes = PersistenceChanges::new();
+    changes.push(RingOperation::Truncate { new_rows: rows });
+    changes.push(RingOperation::HeaderUpdate {
+        len: store.len,
+        cursor: store.cursor,
+    });
+    Ok(changes)
+}
+
+/// すべての行がキーの探索で到達可能な位置に配置されているかを検証します。
+pub fn is_consistent(store: &OrbyRingBufferSilo) -> bool {
+    if store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
+        return true;
+    }
+    (0..store.capacity)
+        .filter(|&slot| store.valid.get(slot))
+        .all(|slot| probe(store, key_at(store, slot)) == Ok(slot))
+}
@@ -0,0 +1,174 @@
+use crate::filter::Filter;
+use crate::logic::{planner, OrbyRingBufferSilo};
+use std::collections::HashMap;
+
+/// 索引レーン（`index_lane`）上の値から、その値を持つ生存行の物理スロットを引くハッシュ索引。
+/// `mask_index` / `prefix_index` の索引は、値をマスクした上位ビット等をキーとして保持します。
+#[derive(Debug, Clone, Default)]
+pub struct LaneIndex {
+    pub lane: usize,
+    /// キーとして使用するビット。`index_lane` の索引では全ビット（`u128::MAX`）です。
+    pub mask: u128,
+    slots: HashMap<u128, Vec<usize>>,
+}
+
+impl LaneIndex {
+    pub fn new(lane: usize) -> Self {
+        Self::masked(lane, u128::MAX)
+    }
+
+    pub fn masked(lane: usize, mask: u128) -> Self {
+        Self {
+            lane,
+            mask,
+            slots: HashMap::new(),
+        }
+    }
+
+    /// 値全体をキーとする索引（`index_lane`）かを返します。
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.mask == u128::MAX
+    }
+
+    /// 値を持つ行の物理スロットを返します。順序は保証されません。
+    #[inline]
+    pub fn get(&self, key: u128) -> &[usize] {
+        self.slots.get(&key).map_or(&[], |slots| slots.as_slice())
+    }
+
+    /// レーンの値をマスクしてキーとして登録します。
+    #[inline]
+    pub fn insert(&mut self, value: u128, slot: usize) {
+        self.slots.entry(value & self.mask).or_default().push(slot);
+    }
+
+    #[inline]
+    pub fn remove(&mut self, value: u128, slot: usize) {
+        let key = value & self.mask;
+        if let Some(slots) = self.slots.get_mut(&key) {
+            if let Some(pos) = slots.iter().position(|&s| s == slot) {
+                slots.swap_remove(pos);
+            }
+            if slots.is_empty() {
+                self.slots.remove(&key);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+    }
+
+    /// 索引の内容を（キー, 物理スロット）の組で列挙します。Vault への保存に使用します。
+    pub fn entries(&self) -> impl Iterator<Item = (u128, &[usize])> {
+        self.slots
+            .iter()
+            .map(|(&key, slots)| (key, slots.as_slice()))
+    }
+
+    /// Vault から読み込んだ内容で索引を置き換えます。
+    pub fn restore(&mut self, slots: HashMap<u128, Vec<usize>>) {
+        self.slots = slots;
+    }
+}
+
+/// 指定スロットの生存行を全ての索引へ登録します。
+#[inline]
+pub fn remember(store: &mut OrbyRingBufferSilo, slot: usize) {
+    if store.indexes.is_empty() || !store.valid.get(slot) {
+        return;
+    }
+    for index in &mut store.indexes {
+        index.insert(store.lanes[index.lane].buffer[slot].as_u128(), slot);
+    }
+}
+
+/// 指定スロットの生存行を全ての索引から外します。行を上書き・削除する前に呼び出します。
+#[inline]
+pub fn forget(store: &mut OrbyRingBufferSilo, slot: usize) {
+    if store.indexes.is_empty() || !store.valid.get(slot) {
+        return;
+    }
+    for index in &mut store.indexes {
+        index.remove(store.lanes[index.lane].buffer[slot].as_u128(), slot);
+    }
+}
+
+/// 有効ビットマップとレーンの内容から全ての索引を再構築します。
+/// コンパクションによる再配置やロード後など、スロット単位で追跡できない変更の後に使用します。
+pub fn rebuild(store: &mut OrbyRingBufferSilo) {
+    if store.indexes.is_empty() {
+        return;
+    }
+    let mut indexes = std::mem::take(&mut store.indexes);
+    for index in &mut indexes {
+        index.clear();
+        if store.lanes.is_empty() {
+            continue;
+        }
+        for (slot, cell) in store.lanes[index.lane].buffer.iter().enumerate() {
+            if store.valid.get(slot) {
+                index.insert(cell.as_u128(), slot);
+            }
+        }
+    }
+    store.indexes = indexes;
+}
+
+/// `lane` の値全体をキーとする索引を返します。
+fn full_index(store: &OrbyRingBufferSilo, lane: usize) -> Option<&LaneIndex> {
+    store
+        .indexes
+        .iter()
+        .find(|index| index.lane == lane && index.is_full())
+}
+
+/// `lane` に索引があれば、値 `id` を持つ生存行の物理スロットを昇順で返します。
+/// 索引がない場合は `None` を返し、呼び出し側で全件走査させます。
+pub fn lookup(store: &OrbyRingBufferSilo, lane: usize, id: u128) -> Option<Vec<usize>> {
+    let index = full_index(store, lane)?;
+    let mut slots = index.get(id).to_vec();
+    slots.sort_unstable();
+    Some(slots)
+}
+
+/// 索引を使用して、フィルタに一致し得る行の物理スロットと、それが厳密な結果かを返します。
+/// `Eq` / `In` は値全体の索引で、`Mask` はそのマスクのビットを全て含むマスク索引
+/// （複数あれば最も多くのビットを持つもの）で評価します。
+/// 索引で候補を絞り込めない場合は `None` を返します。
+pub fn candidates(store: &OrbyRingBufferSilo, filter: &Filter) -> Option<(Vec<usize>, bool)> {
+    if store.indexes.is_empty() || store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
+        return None;
+    }
+    planner::combine(filter, &|leaf| leaf_candidates(store, leaf))
+}
+
+fn leaf_candidates(store: &OrbyRingBufferSilo, filter: &Filter) -> Option<(Vec<usize>, bool)> {
+    match filter {
+        Filter::Eq { lane, value } => Some((full_index(store, *lane)?.get(*value).to_vec(), true)),
+        Filter::In { lane, values } => {
+            let index = full_index(store, *lane)?;
+            let slots = values
+                .iter()
+                .flat_map(|&value| index.get(value).iter().copied())
+                .collect();
+            Some((slots, true))
+        }
+        Filter::Mask { lane, mask, value } => {
+            // 索引のキーのビットがフィルタのマスクに含まれていれば、候補の上位集合を引ける
+            let index = store
+                .indexes
+                .iter()
+                .filter(|index| index.lane == *lane && index.mask & mask == index.mask)
+                .max_by_key(|index| index.mask.count_ones())?;
+            // マスク外のビットを持つ値は、どの行の `v & mask` とも一致しない
+            if value & !mask != 0 {
+                return Some((Vec::new(), true));
+            }
+            let slots = index.get(value & index.mask).to_vec();
+            Some((slots, index.mask == *mask))
+        }
+        _ => None,
+    }
+}
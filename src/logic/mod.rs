@@ -1,6 +1,29 @@
+pub mod aggregate;
+pub mod bitmap;
+pub mod bitmap_index;
+pub mod bloom;
+pub mod composite;
+pub mod hash;
+pub mod index;
+pub mod planner;
 pub mod ring;
+pub mod sketch;
+pub mod slot;
+pub mod sorted;
+pub mod topk;
+pub mod unique;
+pub mod zone;
 
+use crate::logic::bitmap::SlotBitmap;
+use crate::logic::bitmap_index::BitmapIndex;
+use crate::logic::bloom::BlockBloom;
+use crate::logic::composite::CompositeIndex;
+use crate::logic::index::LaneIndex;
+use crate::logic::sketch::BlockSketch;
+use crate::logic::unique::UniqueKeys;
+use crate::logic::zone::ZoneMap;
 use crate::types::{LogicMode, PulseCell, SaveMode};
+use std::collections::BTreeSet;
 
 /// 各次元（カラム）のデータを保持する独立したリングバッファ。
 pub struct OrbyRingBuffer {
@@ -15,21 +38,54 @@ impl OrbyRingBuffer {
     }
 }
 
+/// ミラーファイルへの書き込み要求（オフセット, バイト列）を送るチャネル。
+pub(crate) type MirrorSender = tokio::sync::mpsc::Sender<Vec<(u64, Vec<u8>)>>;
+
 /// `Orby` の内部状態を保持する構造体。
 pub struct OrbyRingBufferSilo {
     pub name: String,
     pub lanes: Vec<OrbyRingBuffer>,
     pub cursor: usize,
+    /// 最古の行の物理位置。`pop_oldest` やリングの周回によって前進します。
+    pub tail: usize,
     pub len: usize,
     pub capacity: usize,
     pub ring_buffer_lane_count: usize,
     pub compaction: bool,
     pub logic_mode: LogicMode,
+    /// 並び順やハッシュの基準となるキーレーン（`LogicMode::Sorted` 等で使用）。
+    pub key_lane: usize,
     pub storage_mode: SaveMode,
+    /// 行の保持期間。設定時は `key_lane` の UUIDv7 タイムスタンプを基準に期限切れを判定します。
+    pub retention: Option<std::time::Duration>,
+    /// 一意性のレーン（`unique_lane` / `unique`）ごとの値の索引。
+    /// 生存行の値を重複させる書き込みを、レーンの `DuplicatePolicy` に従って除外または拒否します。
+    pub constraints: Vec<UniqueKeys>,
+    /// 索引レーン（`index_lane`）ごとのハッシュ索引。ID による検索・更新・削除で全件走査を避けます。
+    pub indexes: Vec<LaneIndex>,
+    /// ブロックごとのレーン値の最小・最大。範囲検索で一致し得ないブロックを読み飛ばします。
+    pub zones: ZoneMap,
+    /// ブルームフィルタレーン（`bloom_lane`）のブロックごとのフィルタ。存在しない値の検索でブロックを読み飛ばします。
+    pub bloom: Option<BlockBloom>,
+    /// 基数推定レーン（`approx_distinct_lane`）ごとの、ブロック単位の HyperLogLog スケッチ。
+    pub sketches: Vec<BlockSketch>,
+    /// ビットマップレーン（`bitmap_lane`）ごとの、値 → スロットのビットマップ索引。
+    pub bitmaps: Vec<BitmapIndex>,
+    /// 複合索引（`composite_index`）。宣言されたレーンの値の組から物理スロットを引きます。
+    pub composites: Vec<CompositeIndex>,
+    /// `group_by` が一度に作成できるグループ数の上限（`max_groups`）。
+    pub max_groups: usize,
+    /// `LogicMode::StaticSlot` で再利用可能な解放済みスロット（cursor 未満）。
+    pub free_slots: BTreeSet<usize>,
+    /// 物理スロットごとの有効ビット。ゼロ値ではなく、このビットで生存行と墓標を区別します。
+    pub valid: SlotBitmap,
     pub(crate) aof_sender: Option<tokio::sync::mpsc::Sender<Vec<u8>>>,
-    pub(crate) mirror_sender: Option<tokio::sync::mpsc::Sender<Vec<(u64, Vec<u8>)>>>,
+    pub(crate) mirror_sender: Option<MirrorSender>,
     pub(crate) mirror_path: Option<std::path::PathBuf>,
     pub(crate) vault_path: Option<std::path::PathBuf>,
+    /// ヘッダの現在の世代番号で保存された索引ファイルが Vault に残っている可能性があるか。
+    /// `sleep()` で索引ファイルを書き出すと立ち、その後の最初のコミットで世代番号を進めると下ります。
+    pub(crate) index_files_current: std::sync::atomic::AtomicBool,
 }
 
 pub const AOF_OP_INSERT: u8 = 0x01;
@@ -37,6 +93,29 @@ pub const AOF_OP_PURGE: u8 = 0x02;
 pub const AOF_OP_UPDATE: u8 = 0x03;
 pub const AOF_OP_TRUNCATE: u8 = 0x04;
 pub const AOF_OP_LANE_BATCH: u8 = 0x05;
+pub const AOF_OP_INSERT_AT: u8 = 0x06;
+pub const AOF_OP_DELETE: u8 = 0x07;
+pub const AOF_OP_POP_OLDEST: u8 = 0x08;
+pub const AOF_OP_POP_NEWEST: u8 = 0x09;
+
+/// 派生データ（一意性の索引、検索用の索引、ビットマップ索引、複合索引、ゾーンマップ、ブルームフィルタ）を、
+/// 有効ビットマップとレーンの内容から作り直します。
+/// レーン単位の一括書き込みや再初期化、コンパクションの後に使用します。
+pub fn rebuild_derived(store: &mut OrbyRingBufferSilo) {
+    index::rebuild(store);
+    bitmap_index::rebuild(store);
+    composite::rebuild(store);
+    rebuild_summaries(store);
+}
+
+/// 派生データのうち、Vault に保存されないもの（一意性の索引、ゾーンマップ、ブルームフィルタ、基数推定のスケッチ）を作り直します。
+/// 検索用の索引は Vault の索引ファイルから復元されるため、ロード時はこちらのみを使用します。
+pub fn rebuild_summaries(store: &mut OrbyRingBufferSilo) {
+    unique::rebuild(store);
+    zone::rebuild(store);
+    bloom::rebuild(store);
+    sketch::rebuild(store);
+}
 
 /// リングバッファで発生した操作を表現する列挙型。
 /// これにより、ロジック層が物理的な永続化フォーマット（AOFのバイナリ等）に依存しなくなります。
@@ -47,6 +126,10 @@ pub enum RingOperation {
         row_count: usize,
         data: Vec<Vec<u128>>,
     },
+    InsertAt {
+        physical_index: usize,
+        data: Vec<u128>,
+    },
     Update {
         physical_index: usize,
         id: u128,
@@ -64,6 +147,11 @@ pub enum RingOperation {
     Truncate {
         new_rows: Vec<Vec<u128>>,
     },
+    /// 両端から取り除かれた行。`oldest` が true なら tail 側、false なら cursor 側。
+    Pop {
+        physical_indices: Vec<usize>,
+        oldest: bool,
+    },
     LaneBatch {
         lane_idx: usize,
         start_cursor: usize,
@@ -73,6 +161,11 @@ pub enum RingOperation {
         len: usize,
         cursor: usize,
     },
+    /// 物理範囲 `[start, end)` の行が再配置（シフト）されたことを示します。
+    Shift {
+        start: usize,
+        end: usize,
+    },
 }
 
 /// 内部ロジック実行によって発生した変更内容。
@@ -94,6 +187,58 @@ impl PersistenceChanges {
         self.ops.is_empty()
     }
 
+    /// `Shift` 操作で再配置された物理範囲をすべて含む最小の範囲を返します。
+    pub fn shifted_range(&self) -> Option<std::ops::Range<usize>> {
+        self.ops
+            .iter()
+            .filter_map(|op| match op {
+                RingOperation::Shift { start, end } => Some(*start..*end),
+                _ => None,
+            })
+            .reduce(|a, b| a.start.min(b.start)..a.end.max(b.end))
+    }
+
+    /// `InsertAt` 操作で書き込まれた（物理スロット, 行データ）の一覧を返します。
+    pub fn inserted_rows(&self) -> Vec<(usize, Vec<u128>)> {
+        self.ops
+            .iter()
+            .filter_map(|op| match op {
+                RingOperation::InsertAt {
+                    physical_index,
+                    data,
+                } => Some((*physical_index, data.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// 操作によって書き換えられた物理スロットを昇順・重複なしで返します。
+    /// `Insert` は折り返しのない連続領域（`cursor..cursor + row_count`）として扱います。
+    pub fn touched_slots(&self) -> Vec<usize> {
+        let mut slots = BTreeSet::new();
+        for op in &self.ops {
+            match op {
+                RingOperation::Insert {
+                    cursor, row_count, ..
+                } => slots.extend(*cursor..*cursor + *row_count),
+                RingOperation::InsertAt { physical_index, .. }
+                | RingOperation::Update { physical_index, .. }
+                | RingOperation::Delete { physical_index } => {
+                    slots.insert(*physical_index);
+                }
+                RingOperation::Purge {
+                    physical_indices, ..
+                }
+                | RingOperation::Pop {
+                    physical_indices, ..
+                } => slots.extend(physical_indices.iter().copied()),
+                RingOperation::Shift { start, end } => slots.extend(*start..*end),
+                _ => {}
+            }
+        }
+        slots.into_iter().collect()
+    }
+
     /// セマンティックな操作リストを、物理的な AOF/Mirror 用バイナリデータに変換します。
     pub fn flatten(&self, lane_count: usize) -> (Vec<u8>, Vec<(u64, Vec<u8>)>) {
         let mut aof_data = Vec::new();
@@ -124,6 +269,26 @@ impl PersistenceChanges {
                         mirror_data.push((offset, row_bytes));
                     }
                 }
+                RingOperation::InsertAt {
+                    physical_index,
+                    data,
+                } => {
+                    // AOF
+                    aof_data.push(AOF_OP_INSERT_AT);
+                    aof_data.extend_from_slice(&(*physical_index as u64).to_le_bytes());
+                    for &val in data {
+                        aof_data.extend_from_slice(&val.to_le_bytes());
+                    }
+
+                    // Mirror
+                    let offset = crate::types::HEADER_SIZE
+                        + (*physical_index as u64 * lane_count as u64 * 16);
+                    let mut row_bytes = Vec::with_capacity(lane_count * 16);
+                    for &val in data {
+                        row_bytes.extend_from_slice(&val.to_le_bytes());
+                    }
+                    mirror_data.push((offset, row_bytes));
+                }
                 RingOperation::Update {
                     physical_index,
                     id,
@@ -148,9 +313,10 @@ impl PersistenceChanges {
                     mirror_data.push((offset, row_bytes));
                 }
                 RingOperation::Delete { physical_index } => {
-                    // AOF: Delete doesn't have a specific AOF op in current impl,
-                    // usually handled by Purge or just left as is if not needed.
-                    // But in ring.rs delete, we zero out.
+                    // AOF
+                    aof_data.push(AOF_OP_DELETE);
+                    aof_data.extend_from_slice(&(*physical_index as u64).to_le_bytes());
+
                     // Mirror
                     let offset = crate::types::HEADER_SIZE
                         + (*physical_index as u64 * lane_count as u64 * 16);
@@ -175,6 +341,26 @@ impl PersistenceChanges {
                         mirror_data.push((offset, row_bytes));
                     }
                 }
+                RingOperation::Pop {
+                    physical_indices,
+                    oldest,
+                } => {
+                    // AOF
+                    aof_data.push(if *oldest {
+                        AOF_OP_POP_OLDEST
+                    } else {
+                        AOF_OP_POP_NEWEST
+                    });
+                    aof_data.extend_from_slice(&(physical_indices.len() as u64).to_le_bytes());
+
+                    // Mirror
+                    for &idx in physical_indices {
+                        let offset =
+                            crate::types::HEADER_SIZE + (idx as u64 * lane_count as u64 * 16);
+                        let row_bytes = vec![0u8; lane_count * 16];
+                        mirror_data.push((offset, row_bytes));
+                    }
+                }
                 RingOperation::Truncate { new_rows } => {
                     // AOF
                     aof_data.push(AOF_OP_TRUNCATE);
@@ -223,6 +409,10 @@ impl PersistenceChanges {
                         mirror_data.push((offset, row));
                     }
                 }
+                RingOperation::Shift { .. } => {
+                    // AOF: 再配置は Insert/Delete 等の論理操作の再実行によって再現される。
+                    // Mirror: 対象行のデータは各論理操作側で反映される。
+                }
                 RingOperation::HeaderUpdate { len, cursor } => {
                     // AOF: Header is usually not logged as Op, but recalculated.
                     // Mirror
@@ -0,0 +1,191 @@
+use crate::filter::Filter;
+use crate::logic::zone::ZONE_BLOCK_SIZE;
+use crate::logic::{bitmap_index, composite, hash, index, sorted, OrbyRingBufferSilo};
+use crate::types::{AccessPath, QueryPlan};
+
+/// フィルタの検索に使用するアクセス経路。
+pub enum Plan {
+    /// 索引から得た候補スロットのみを読みます。`exact` でなければ候補の行でフィルタを確認します。
+    Candidates {
+        path: AccessPath,
+        slots: Vec<usize>,
+        exact: bool,
+    },
+    /// `keep[block]` が `true` のブロックのみを走査します。
+    Blocks { keep: Vec<bool> },
+    /// 全ての行を走査します。
+    FullScan,
+}
+
+#[inline]
+fn has_mem(store: &OrbyRingBufferSilo) -> bool {
+    !store.lanes.is_empty() && !store.lanes[0].buffer.is_empty()
+}
+
+fn block_count(store: &OrbyRingBufferSilo) -> usize {
+    if has_mem(store) {
+        store.capacity.div_ceil(ZONE_BLOCK_SIZE)
+    } else {
+        0
+    }
+}
+
+fn block_range(store: &OrbyRingBufferSilo, block: usize) -> std::ops::Range<usize> {
+    block * ZONE_BLOCK_SIZE..((block + 1) * ZONE_BLOCK_SIZE).min(store.capacity)
+}
+
+/// ゾーンマップとブルームフィルタから、ブロックにフィルタと一致する行があり得るかを判定します。
+/// `false` であれば確実に一致する行はありません。要約で判定できない条件は `true` とします。
+fn may_match(store: &OrbyRingBufferSilo, filter: &Filter, block: usize) -> bool {
+    let lanes = store.lanes.len();
+    let may_hold = |lane: usize, value: u128| {
+        store.zones.may_overlap(lane, block, value, value)
+            && store
+                .bloom
+                .as_ref()
+                .filter(|bloom| bloom.lane == lane)
+                .is_none_or(|bloom| bloom.may_contain(block, value))
+    };
+    match filter {
+        Filter::Eq { lane, value } => *lane < lanes && may_hold(*lane, *value),
+        Filter::In { lane, values } => {
+            *lane < lanes && values.iter().any(|&value| may_hold(*lane, value))
+        }
+        Filter::Range { lane, min, max } => {
+            *lane < lanes && min <= max && store.zones.may_overlap(*lane, block, *min, *max)
+        }
+        Filter::Mask { lane, .. } => *lane < lanes,
+        // 要約は「含まない」ことしか確定できないため、否定はブロックの判定に使えない
+        Filter::Not(_) => true,
+        Filter::And(filters) => filters.iter().all(|sub| may_match(store, sub, block)),
+        Filter::Or(filters) => filters.iter().any(|sub| may_match(store, sub, block)),
+    }
+}
+
+/// 生存行を含み、かつフィルタと一致する行があり得るブロックに印を付けます。
+fn kept_blocks(store: &OrbyRingBufferSilo, filter: &Filter) -> Vec<bool> {
+    (0..block_count(store))
+        .map(|block| {
+            store.valid.count_ones_in(block_range(store, block)) > 0
+                && may_match(store, filter, block)
+        })
+        .collect()
+}
+
+fn rows_in(store: &OrbyRingBufferSilo, keep: &[bool]) -> usize {
+    keep.iter()
+        .enumerate()
+        .filter(|(_, &kept)| kept)
+        .map(|(block, _)| store.valid.count_ones_in(block_range(store, block)))
+        .sum()
+}
+
+/// `And` / `Or` を組み合わせて、`leaf` で評価できる条件から候補スロットを求めます。
+/// `And` は最も候補の少ない条件で絞り込み、残りの条件は候補の行で確認します。
+/// `Or` は全ての条件が評価できる場合に限り、候補の和集合を返します。
+pub fn combine<L>(filter: &Filter, leaf: &L) -> Option<(Vec<usize>, bool)>
+where
+    L: Fn(&Filter) -> Option<(Vec<usize>, bool)>,
+{
+    match filter {
+        Filter::And(filters) => filters
+            .iter()
+            .filter_map(|sub| combine(sub, leaf))
+            .min_by_key(|(slots, _)| slots.len())
+            .map(|(slots, exact)| (slots, exact && filters.len() == 1)),
+        Filter::Or(filters) => {
+            let mut slots = Vec::new();
+            let mut exact = true;
+            for sub in filters {
+                let (sub_slots, sub_exact) = combine(sub, leaf)?;
+                slots.extend(sub_slots);
+                exact &= sub_exact;
+            }
+            Some((slots, exact))
+        }
+        _ => leaf(filter),
+    }
+}
+
+/// ソート済み・ハッシュスロットのキーレーン、ビットマップ索引・複合索引・レーン索引のうち、フィルタの候補を最も少なく絞り込めるものを返します。
+fn best_index(
+    store: &OrbyRingBufferSilo,
+    filter: &Filter,
+) -> Option<(AccessPath, Vec<usize>, bool)> {
+    [
+        (
+            AccessPath::KeyLookup,
+            sorted::candidates(store, filter).or_else(|| hash::candidates(store, filter)),
+        ),
+        (
+            AccessPath::BitmapIndex,
+            bitmap_index::candidates(store, filter),
+        ),
+        (
+            AccessPath::CompositeIndex,
+            composite::candidates(store, filter),
+        ),
+        (AccessPath::LaneIndex, index::candidates(store, filter)),
+    ]
+    .into_iter()
+    .filter_map(|(path, found)| found.map(|(slots, exact)| (path, slots, exact)))
+    .min_by_key(|(_, slots, _)| slots.len())
+}
+
+/// フィルタの検索に使用するアクセス経路を選びます。
+/// 索引の候補数が、ブロックの読み飛ばし後に走査する行数以下であれば索引を使用します。
+/// 読み飛ばせるブロックがなければ全件走査とします。
+pub fn plan(store: &OrbyRingBufferSilo, filter: &Filter) -> Plan {
+    if !has_mem(store) {
+        return Plan::FullScan;
+    }
+    let keep = kept_blocks(store, filter);
+    if let Some((path, slots, exact)) = best_index(store, filter) {
+        if slots.len() <= rows_in(store, &keep) {
+            return Plan::Candidates { path, slots, exact };
+        }
+    }
+    if keep.iter().all(|&kept| kept) {
+        Plan::FullScan
+    } else {
+        Plan::Blocks { keep }
+    }
+}
+
+/// `plan` が選ぶアクセス経路と、その見積もり（読む行数・読み飛ばすブロック数）を返します。
+pub fn explain(store: &OrbyRingBufferSilo, filter: &Filter) -> QueryPlan {
+    let blocks_total = block_count(store);
+    match plan(store, filter) {
+        Plan::Candidates { path, slots, exact } => {
+            let mut touched = vec![false; blocks_total];
+            for &slot in &slots {
+                touched[slot / ZONE_BLOCK_SIZE] = true;
+            }
+            QueryPlan {
+                path,
+                estimated_rows: slots.len(),
+                blocks_total,
+                blocks_skipped: touched.iter().filter(|&&hit| !hit).count(),
+                recheck: !exact,
+            }
+        }
+        Plan::Blocks { keep } => QueryPlan {
+            path: AccessPath::BlockScan,
+            estimated_rows: rows_in(store, &keep),
+            blocks_total,
+            blocks_skipped: keep.iter().filter(|&&kept| !kept).count(),
+            recheck: true,
+        },
+        Plan::FullScan => QueryPlan {
+            path: AccessPath::FullScan,
+            estimated_rows: if has_mem(store) {
+                store.valid.count_ones()
+            } else {
+                store.len
+            },
+            blocks_total,
+            blocks_skipped: 0,
+            recheck: true,
+        },
+    }
+}
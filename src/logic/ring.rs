@@ -1,10 +1,142 @@
 use crate::error::OrbyError;
-use crate::logic::{OrbyRingBufferSilo, PersistenceChanges, RingOperation};
+use crate::filter::Filter;
+use crate::logic::zone::ZONE_BLOCK_SIZE;
+use crate::logic::{
+    bitmap_index, bloom, composite, index, rebuild_derived, sketch, unique, zone,
+    OrbyRingBufferSilo, PersistenceChanges, RingOperation,
+};
 use crate::row::PulseCellPack;
-use crate::types::PulseCell;
+use crate::types::{LogicMode, PulseCell};
 use rayon::prelude::*;
 use std::sync::Arc;
 
+/// 行を上書き・削除する前に、一意性の索引・各種索引・ゾーンマップ・ブルームフィルタ・基数推定のスケッチからその行を外します。
+#[inline]
+pub(crate) fn forget_row(store: &mut OrbyRingBufferSilo, slot: usize) {
+    unique::forget(store, slot);
+    index::forget(store, slot);
+    bitmap_index::forget(store, slot);
+    composite::forget(store, slot);
+    zone::forget(store, slot);
+    bloom::forget(store, slot);
+    sketch::forget(store, slot);
+}
+
+/// 書き込んだ行を、一意性の索引・各種索引・ゾーンマップ・ブルームフィルタ・基数推定のスケッチへ反映します。
+#[inline]
+pub(crate) fn remember_row(store: &mut OrbyRingBufferSilo, slot: usize) {
+    unique::remember(store, slot);
+    index::remember(store, slot);
+    bitmap_index::remember(store, slot);
+    composite::remember(store, slot);
+    zone::note_row(store, slot);
+    bloom::remember(store, slot);
+    sketch::remember(store, slot);
+}
+
+/// `count` 件の書き込みが可能かを論理モードに応じて検証します。
+/// `LogicMode::Table` では、末尾を超える（＝古いデータを上書きする）書き込みを拒否します。
+fn ensure_capacity(store: &OrbyRingBufferSilo, count: usize) -> Result<(), OrbyError> {
+    let bounded = matches!(
+        store.logic_mode,
+        LogicMode::Table
+            | LogicMode::StaticSlot
+            | LogicMode::Sorted
+            | LogicMode::HashSlot
+            | LogicMode::TopK(_)
+    );
+    if bounded && store.cursor + count > store.capacity {
+        return Err(OrbyError::StorageFull {
+            pool_name: store.name.clone(),
+            capacity: store.capacity,
+        });
+    }
+    Ok(())
+}
+
+/// `insert_batch` が `rows` を拒否するか（容量不足・一意性制約の違反）を、書き込まずに確認します。
+/// 一意性レーンで除外される行は数えません。シャードをまたぐ書き込みで、全シャードを事前に検証するために使用します。
+pub fn check_insert_batch<T: AsRef<[u128]>>(
+    store: &OrbyRingBufferSilo,
+    rows: &[T],
+) -> Result<(), OrbyError> {
+    let admitted: Vec<&T> = rows
+        .iter()
+        .zip(unique::admit(store, rows))
+        .filter_map(|(row, ok)| ok.then_some(row))
+        .collect();
+    ensure_capacity(store, admitted.len())?;
+    unique::check_insert(store, &admitted)
+}
+
+/// `count` 件の書き込みが最古の行（tail）を追い越す場合、tail を書き込み後のカーソル位置へ進めます。
+/// 書き込み前（`len` 更新前）に呼び出します。
+fn advance_tail(store: &mut OrbyRingBufferSilo, count: usize) {
+    if store.len == 0 {
+        store.tail = store.cursor;
+        return;
+    }
+    if store.logic_mode == LogicMode::RingBuffer {
+        let cap = store.capacity;
+        let free = (store.tail + cap - store.cursor) % cap;
+        if count > free {
+            store.tail = (store.cursor + count) % cap;
+        }
+    }
+}
+
+/// 生存ウィンドウ（tail から cursor まで）が物理末尾を跨いでいるかを判定します。
+pub fn is_wrapped(store: &OrbyRingBufferSilo) -> bool {
+    store.len == store.capacity || (store.len > 0 && store.tail >= store.cursor)
+}
+
+/// UUIDv7 の先頭 48bit（Unix エポックからのミリ秒）を取り出します。
+#[inline]
+pub fn uuid_v7_millis(val: u128) -> u64 {
+    (val >> 80) as u64
+}
+
+/// 保持期間に基づく期限の境界（ミリ秒）を返します。これより古いタイムスタンプの行は期限切れです。
+/// 保持期間が設定されていない場合は `None` を返します。
+pub fn expiry_cutoff(store: &OrbyRingBufferSilo) -> Option<u64> {
+    let retention = store.retention?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    Some(now.saturating_sub(retention.as_millis() as u64))
+}
+
+/// キーレーンの値（UUIDv7）が期限切れかを判定します。
+#[inline]
+pub fn is_expired(key: u128, cutoff: Option<u64>) -> bool {
+    cutoff.is_some_and(|cutoff| uuid_v7_millis(key) < cutoff)
+}
+
+/// 物理スロットの行が生存（有効ビットあり）かつ期限内かを判定します。
+/// `cutoff` は保持期間（`retention`）が設定されている場合のみ `Some` になるため、
+/// 未設定であればキーレーンを読まず、索引や参照レーンのみで判定する検索が他のレーンに触れません。
+#[inline]
+pub fn is_live(store: &OrbyRingBufferSilo, slot: usize, cutoff: Option<u64>) -> bool {
+    store.valid.get(slot)
+        && cutoff.is_none_or(|cutoff| {
+            uuid_v7_millis(store.lanes[store.key_lane].buffer[slot].as_u128()) >= cutoff
+        })
+}
+
+/// カーソルを `count` 件分進めます。
+/// `LogicMode::RingBuffer` 以外では折り返さず、`capacity` の位置で停止します。
+fn advance_cursor(store: &mut OrbyRingBufferSilo, count: usize) {
+    store.cursor = match store.logic_mode {
+        LogicMode::RingBuffer => (store.cursor + count) % store.capacity,
+        LogicMode::Table
+        | LogicMode::StaticSlot
+        | LogicMode::Sorted
+        | LogicMode::HashSlot
+        | LogicMode::TopK(_) => store.cursor + count,
+    };
+}
+
 /// リングバッファ戦略に基づくバッチ挿入ロジック。
 /// Parallel Arrays（次元ごとの独立した配列）の cursor 位置へ、データを垂直に焼き付けます。
 pub fn insert_batch<T, I>(
@@ -25,6 +157,8 @@ where
     if raw_rows.is_empty() {
         return Ok(changes);
     }
+    ensure_capacity(store, raw_rows.len())?;
+    unique::check_insert(store, &raw_rows)?;
 
     // 1. メモリバッファが有効（非 StorageOnly）かチェック
     let has_mem = !store.lanes.is_empty() && !store.lanes[0].buffer.is_empty();
@@ -41,12 +175,20 @@ where
         }
 
         // 2. メモリへの書き込み (Parallel Arrays 構造)
+        advance_tail(store, 1);
         if has_mem {
             let cursor = store.cursor;
-            let is_overwrite = store.lanes[0].buffer[cursor].as_u128() != 0;
+            let is_overwrite = store.valid.get(cursor);
+            // 周回で上書きされる行の値は再び挿入可能になる
+            forget_row(store, cursor);
             for (lane, &val) in store.lanes.iter_mut().zip(row.iter()) {
                 lane.buffer[cursor] = PulseCell::new(val);
             }
+            store.valid.set(cursor);
+            remember_row(store, cursor);
+            zone::settle(store, cursor);
+            bloom::settle(store, cursor);
+            sketch::settle(store, cursor);
             if !is_overwrite && store.len < cap {
                 store.len += 1;
             }
@@ -55,7 +197,7 @@ where
         }
 
         // カーソルを進める（リングバッファ）
-        store.cursor = (store.cursor + 1) % cap;
+        advance_cursor(store, 1);
     }
 
     // イベントの記録
@@ -92,6 +234,14 @@ pub fn insert_fixed<const N: usize>(
     let has_mem = !store.lanes.is_empty() && !store.lanes[0].buffer.is_empty();
     let start_cursor = store.cursor;
     let row_count = items.len();
+    ensure_capacity(store, row_count)?;
+    if !store.constraints.is_empty() {
+        let rows: Vec<Vec<u128>> = items
+            .iter()
+            .map(|item| item.values.iter().map(|v| v.as_u128()).collect())
+            .collect();
+        unique::check_insert(store, &rows)?;
+    }
 
     let mut raw_data = Vec::with_capacity(row_count);
 
@@ -99,13 +249,20 @@ pub fn insert_fixed<const N: usize>(
         let row: Vec<u128> = item.values.iter().map(|v| v.as_u128()).collect();
         raw_data.push(row.clone());
 
+        advance_tail(store, 1);
         if has_mem {
             let cursor = store.cursor;
-            let is_overwrite = store.lanes[0].buffer[cursor].as_u128() != 0;
+            let is_overwrite = store.valid.get(cursor);
+            forget_row(store, cursor);
 
             for (lane, &val) in store.lanes.iter_mut().zip(item.values.iter()) {
                 lane.buffer[cursor] = val;
             }
+            store.valid.set(cursor);
+            remember_row(store, cursor);
+            zone::settle(store, cursor);
+            bloom::settle(store, cursor);
+            sketch::settle(store, cursor);
 
             if !is_overwrite && store.len < cap {
                 store.len += 1;
@@ -114,7 +271,7 @@ pub fn insert_fixed<const N: usize>(
             store.len += 1;
         }
 
-        store.cursor = (store.cursor + 1) % cap;
+        advance_cursor(store, 1);
     }
 
     changes.push(RingOperation::Insert {
@@ -157,6 +314,19 @@ pub fn insert_lane_batch(
             capacity: cap,
         });
     }
+    ensure_capacity(store, count)?;
+    if !store.constraints.is_empty() {
+        // 対象外のレーンはゼロで書き込まれる
+        let rows: Vec<Vec<u128>> = values
+            .iter()
+            .map(|&val| {
+                let mut row = vec![0u128; dim];
+                row[lane_idx] = val;
+                row
+            })
+            .collect();
+        unique::check_insert(store, &rows)?;
+    }
 
     let start_cursor = store.cursor;
 
@@ -206,8 +376,17 @@ pub fn insert_lane_batch(
         }
     }
 
-    store.len = (store.len + count).min(cap);
-    store.cursor = (store.cursor + count) % cap;
+    advance_tail(store, count);
+    // 既存の生存行を上書きした分は件数に含めない
+    for i in 0..count {
+        let slot = (start_cursor + i) % cap;
+        if !store.valid.get(slot) {
+            store.valid.set(slot);
+            store.len += 1;
+        }
+    }
+    advance_cursor(store, count);
+    rebuild_derived(store);
 
     // 2. イベント記録
     changes.push(RingOperation::LaneBatch {
@@ -236,6 +415,8 @@ where
 
     let dim = store.ring_buffer_lane_count;
     let cap = store.capacity;
+    let items: Vec<T> = items.take(cap).collect();
+    unique::check_replace(store, &items)?;
 
     // 1. メモリバッファをゼロクリア
     if !store.lanes.is_empty() && !store.lanes[0].buffer.is_empty() {
@@ -245,12 +426,15 @@ where
     }
     store.len = 0;
     store.cursor = 0;
+    store.tail = 0;
+    store.free_slots.clear();
+    store.valid.clear_all();
 
     // 2. 新しいデータの挿入
     let has_mem = !store.lanes.is_empty() && !store.lanes[0].buffer.is_empty();
     let mut new_rows = Vec::new();
 
-    for item in items.take(cap) {
+    for item in items {
         let slice = item.as_ref();
         if slice.len() != dim {
             return Err(OrbyError::LaneCountMismatch {
@@ -266,13 +450,15 @@ where
             for (lane, &val) in store.lanes.iter_mut().zip(slice.iter()) {
                 lane.buffer[cursor] = PulseCell::new(val);
             }
+            store.valid.set(cursor);
             store.len += 1;
         } else {
             store.len += 1;
         }
 
-        store.cursor = (store.cursor + 1) % cap;
+        advance_cursor(store, 1);
     }
+    rebuild_derived(store);
 
     // 3. イベント記録
     changes.push(RingOperation::Truncate { new_rows });
@@ -296,14 +482,16 @@ pub fn delete(store: &mut OrbyRingBufferSilo, index: usize) -> (bool, Persistenc
         return (false, changes);
     }
 
-    if store.lanes[0].buffer[index].as_u128() == 0 {
+    if !store.valid.get(index) {
         return (false, changes);
     }
 
-    // 1. インデックス位置のゼロクリア
+    // 1. インデックス位置のゼロクリアと墓標化
+    forget_row(store, index);
     for lane in &mut store.lanes {
         lane.buffer[index] = PulseCell::new(0);
     }
+    store.valid.clear(index);
     if store.len > 0 {
         store.len -= 1;
     }
@@ -313,15 +501,49 @@ pub fn delete(store: &mut OrbyRingBufferSilo, index: usize) -> (bool, Persistenc
     });
 
     // 2. コンパクション（前方シフト）
+    // 生存ウィンドウ（tail から cursor まで）のうち削除位置より新しい行を、折り返しを考慮して一つずつ前へ詰める
     if store.compaction {
         let cap = store.capacity;
-        if index < cap - 1 {
+        let end = store.cursor % cap;
+        let mut hole = index;
+        let mut next = (index + 1) % cap;
+        while next != end {
             for lane in &mut store.lanes {
-                lane.buffer.copy_within(index + 1..cap, index);
-                lane.buffer[cap - 1] = PulseCell::new(0);
+                lane.buffer[hole] = lane.buffer[next];
             }
+            let valid = store.valid.get(next);
+            store.valid.assign(hole, valid);
+            hole = next;
+            next = (next + 1) % cap;
+        }
+        for lane in &mut store.lanes {
+            lane.buffer[hole] = PulseCell::new(0);
         }
-        store.cursor = store.len;
+        store.valid.clear(hole);
+
+        // 再配置の範囲（折り返す場合は二つ）を記録
+        if hole >= index {
+            changes.push(RingOperation::Shift {
+                start: index,
+                end: hole + 1,
+            });
+        } else {
+            changes.push(RingOperation::Shift {
+                start: index,
+                end: cap,
+            });
+            changes.push(RingOperation::Shift {
+                start: 0,
+                end: hole + 1,
+            });
+        }
+
+        // 後続の行が一つ前のスロットへ移動したため、索引と要約を作り直す
+        rebuild_derived(store);
+        store.cursor = match store.logic_mode {
+            LogicMode::RingBuffer => (store.cursor + cap - 1) % cap,
+            _ => store.cursor - 1,
+        };
     }
 
     changes.push(RingOperation::HeaderUpdate {
@@ -332,6 +554,43 @@ pub fn delete(store: &mut OrbyRingBufferSilo, index: usize) -> (bool, Persistenc
     (true, changes)
 }
 
+/// `index` レーンの値が `id` に一致する生存行の物理スロットを昇順で返します。
+/// レーンに索引があれば索引を引き、なければ全スロットを走査します。
+/// ブルームフィルタがあれば、値を含まないことが確定したブロックは走査しません。
+fn find_targets(store: &OrbyRingBufferSilo, index: usize, id: u128) -> Vec<usize> {
+    if let Some(slots) = index::lookup(store, index, id) {
+        return slots;
+    }
+    let search_lane = &store.lanes[index];
+    let matches = |i: usize| store.valid.get(i) && search_lane.buffer[i].as_u128() == id;
+    let keys = [id];
+    let targets = match bloom::block_filter(store, index, &keys) {
+        Some(may_contain) => (0..store.capacity.div_ceil(ZONE_BLOCK_SIZE))
+            .filter(|&block| may_contain(block))
+            .flat_map(|block| {
+                block * ZONE_BLOCK_SIZE..((block + 1) * ZONE_BLOCK_SIZE).min(store.capacity)
+            })
+            .filter(|&i| matches(i))
+            .collect(),
+        None => (0..store.capacity).filter(|&i| matches(i)).collect(),
+    };
+    targets
+}
+
+/// `update_by_id` による書き換えが一意性のレーン（`unique_lane` / `unique`）に違反しないかを確認します。書き込みの前に呼び出します。
+pub fn check_update(
+    store: &OrbyRingBufferSilo,
+    index: usize,
+    id: u128,
+    new_data: &[u128],
+) -> Result<(), OrbyError> {
+    let dim = store.ring_buffer_lane_count;
+    if store.constraints.is_empty() || index >= dim || new_data.len() != dim {
+        return Ok(());
+    }
+    unique::check_update(store, &find_targets(store, index, id), new_data)
+}
+
 /// 指定した ID を持つ行を、メモリ上の物理位置を変えずに更新します。
 pub fn update_by_id(
     store: &mut OrbyRingBufferSilo,
@@ -340,7 +599,7 @@ pub fn update_by_id(
     new_data: &[u128],
 ) -> (bool, PersistenceChanges) {
     let mut changes = PersistenceChanges::new();
-    if id == 0 || new_data.len() != store.ring_buffer_lane_count {
+    if new_data.len() != store.ring_buffer_lane_count {
         return (false, changes);
     }
     if index >= store.ring_buffer_lane_count {
@@ -348,15 +607,7 @@ pub fn update_by_id(
     }
 
     let mut found_any = false;
-    let mut targets = Vec::new();
-    {
-        let search_lane = &store.lanes[index];
-        for physical_idx in 0..store.capacity {
-            if search_lane.buffer[physical_idx].as_u128() == id {
-                targets.push(physical_idx);
-            }
-        }
-    }
+    let targets = find_targets(store, index, id);
 
     if targets.is_empty() {
         return (false, changes);
@@ -364,9 +615,11 @@ pub fn update_by_id(
 
     for physical_idx in targets {
         // 垂直書き込み
+        forget_row(store, physical_idx);
         for (lane, &val) in store.lanes.iter_mut().zip(new_data.iter()) {
             lane.buffer[physical_idx] = PulseCell::new(val);
         }
+        remember_row(store, physical_idx);
 
         changes.push(RingOperation::Update {
             physical_index: physical_idx,
@@ -387,6 +640,15 @@ pub fn upsert(
     id: u128,
     data: &[u128],
 ) -> Result<PersistenceChanges, OrbyError> {
+    let dim = store.ring_buffer_lane_count;
+    if !store.constraints.is_empty() && index < dim && data.len() == dim {
+        let targets = find_targets(store, index, id);
+        if targets.is_empty() {
+            unique::check_insert(store, &[data])?;
+        } else {
+            unique::check_update(store, &targets, data)?;
+        }
+    }
     let (found, mut changes) = update_by_id(store, index, id, data);
     if !found {
         changes = insert_batch(store, std::iter::once(data))?;
@@ -394,32 +656,25 @@ pub fn upsert(
     Ok(changes)
 }
 
-/// 特定のカラム値に一致するレコードを検索し、その場でゼロ埋め（削除）します。
+/// 特定のカラム値に一致するレコードを検索し、その場で墓標化（ゼロ埋め）します。
 pub fn purge_by_id(store: &mut OrbyRingBufferSilo, index: usize, id: u128) -> PersistenceChanges {
     let mut changes = PersistenceChanges::new();
-    if id == 0 || index >= store.ring_buffer_lane_count {
+    if index >= store.ring_buffer_lane_count {
         return changes;
     }
 
-    let cap = store.capacity;
-    let mut targets = Vec::new();
-    {
-        let search_lane = &store.lanes[index];
-        for physical_idx in 0..cap {
-            if search_lane.buffer[physical_idx].as_u128() == id {
-                targets.push(physical_idx);
-            }
-        }
-    }
+    let targets = find_targets(store, index, id);
 
     if targets.is_empty() {
         return changes;
     }
 
     for &physical_idx in &targets {
+        forget_row(store, physical_idx);
         for lane in &mut store.lanes {
             lane.buffer[physical_idx] = PulseCell::new(0);
         }
+        store.valid.clear(physical_idx);
 
         if store.len > 0 {
             store.len -= 1;
@@ -439,47 +694,181 @@ pub fn purge_by_id(store: &mut OrbyRingBufferSilo, index: usize, id: u128) -> Pe
     changes
 }
 
-/// 実際にメモリ配列を走査し、非ゼロのパルス数をカウントします。
-/// リングバッファの性質上、有効データは 0 から cursor/capacity の範囲に存在します。
-pub fn count_active(store: &OrbyRingBufferSilo) -> usize {
-    if store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
-        return store.len;
+/// 最古の行から最大 `n` 件を取り除き、古い順に返します（FIFO）。
+/// tail を前進させ、取り除いた位置はゼロクリアされます。
+pub fn pop_oldest(
+    store: &mut OrbyRingBufferSilo,
+    n: usize,
+) -> (Vec<Arc<[u128]>>, PersistenceChanges) {
+    let mut changes = PersistenceChanges::new();
+    let mut rows = Vec::new();
+    if n == 0 || store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
+        return (rows, changes);
     }
 
-    // カーソルが周回している場合は全域、そうでない場合はカーソル位置までを走査範囲とする
-    let limit = if store.cursor == 0 && store.len > 0 {
-        store.capacity
-    } else {
-        store.cursor
-    };
+    let cap = store.capacity;
+    let mut popped = Vec::new();
+    // 墓標（スパース削除の跡）を読み飛ばしつつ、最大一周分だけ走査する
+    for _ in 0..cap {
+        if rows.len() >= n || store.len == 0 {
+            break;
+        }
+        let idx = store.tail;
+        if let Some(row) = take_row(store, idx) {
+            rows.push(row);
+            popped.push(idx);
+        }
+        store.tail = match store.logic_mode {
+            LogicMode::RingBuffer => (idx + 1) % cap,
+            _ => (idx + 1).min(store.cursor),
+        };
+    }
+    if store.len == 0 {
+        store.tail = store.cursor;
+    }
+
+    if !popped.is_empty() {
+        changes.push(RingOperation::Pop {
+            physical_indices: popped,
+            oldest: true,
+        });
+        changes.push(RingOperation::HeaderUpdate {
+            len: store.len,
+            cursor: store.cursor,
+        });
+    }
+    (rows, changes)
+}
+
+/// 最新の行から最大 `n` 件を取り除き、新しい順に返します（LIFO）。
+/// cursor を後退させ、取り除いた位置はゼロクリアされます。
+pub fn pop_newest(
+    store: &mut OrbyRingBufferSilo,
+    n: usize,
+) -> (Vec<Arc<[u128]>>, PersistenceChanges) {
+    let mut changes = PersistenceChanges::new();
+    let mut rows = Vec::new();
+    if n == 0 || store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
+        return (rows, changes);
+    }
 
-    let mut count = 0;
-    // 第1レーン（プライマリ次元）を指標としてアクティブセルを走査
-    for cell in store.lanes[0].buffer.iter().take(limit) {
-        if cell.as_u128() != 0 {
-            count += 1;
+    let cap = store.capacity;
+    let mut popped = Vec::new();
+    for _ in 0..cap {
+        if rows.len() >= n || store.len == 0 {
+            break;
+        }
+        let idx = (store.cursor + cap - 1) % cap;
+        if let Some(row) = take_row(store, idx) {
+            rows.push(row);
+            popped.push(idx);
         }
+        store.cursor = idx;
+    }
+    if store.len == 0 {
+        store.tail = store.cursor;
+    }
+
+    if !popped.is_empty() {
+        changes.push(RingOperation::Pop {
+            physical_indices: popped,
+            oldest: false,
+        });
+        changes.push(RingOperation::HeaderUpdate {
+            len: store.len,
+            cursor: store.cursor,
+        });
+    }
+    (rows, changes)
+}
+
+/// 最古の行から連続する期限切れの行を取り除き、tail を最初の有効な行まで進めます。
+/// 取り除いた行数を返します。AOF には `pop_oldest` と同じ操作として記録されます。
+pub fn purge_expired(store: &mut OrbyRingBufferSilo, cutoff: u64) -> (usize, PersistenceChanges) {
+    if store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
+        return (0, PersistenceChanges::new());
+    }
+
+    let cap = store.capacity;
+    let key_lane = store.key_lane;
+    let mut idx = store.tail;
+    let mut remaining = store.len;
+    let mut expired = 0;
+    // 墓標を読み飛ばしつつ、期限内の行に到達するまで数える
+    for _ in 0..cap {
+        if remaining == 0 {
+            break;
+        }
+        if store.valid.get(idx) {
+            if !is_expired(store.lanes[key_lane].buffer[idx].as_u128(), Some(cutoff)) {
+                break;
+            }
+            expired += 1;
+            remaining -= 1;
+        }
+        idx = match store.logic_mode {
+            LogicMode::RingBuffer => (idx + 1) % cap,
+            _ => idx + 1,
+        };
+        if idx >= cap {
+            break;
+        }
+    }
+
+    if expired == 0 {
+        return (0, PersistenceChanges::new());
+    }
+    let (rows, changes) = pop_oldest(store, expired);
+    (rows.len(), changes)
+}
+
+/// 指定位置の行を読み出して墓標化します。墓標の場合は `None` を返します。
+fn take_row(store: &mut OrbyRingBufferSilo, idx: usize) -> Option<Arc<[u128]>> {
+    if !store.valid.get(idx) {
+        return None;
+    }
+    let row: Vec<u128> = store
+        .lanes
+        .iter()
+        .map(|lane| lane.buffer[idx].as_u128())
+        .collect();
+    forget_row(store, idx);
+    for lane in &mut store.lanes {
+        lane.buffer[idx] = PulseCell::new(0);
+    }
+    store.valid.clear(idx);
+    store.len -= 1;
+    Some(Arc::from(row))
+}
+
+/// 有効ビットマップを走査し、生存行の数をカウントします。
+pub fn count_active(store: &OrbyRingBufferSilo) -> usize {
+    if store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
+        return store.len;
     }
-    count
+    store.valid.count_ones()
 }
 
-/// 論理インデックス（最新順）から物理オフセットを計算し、生データを取得します。
+/// 論理インデックス（最新順）の行を取得します。
+/// メモリ上では `query_raw` と同じスキャン順序で墓標と期限切れの行を読み飛ばして数えるため、
+/// 固定スロット・ハッシュ配置・ソート済みの各配置でも `take` の `logical_index` 番目と一致します。
 pub fn get_at(store: &OrbyRingBufferSilo, logical_index: usize) -> Option<Arc<[u128]>> {
     if logical_index >= store.len {
         return None;
     }
-    let cursor = store.cursor;
-    let cap = store.capacity;
-
-    // カーソル位置を最新パルスとみなし、逆算して物理インデックスを特定
-    let physical_idx = if cursor > logical_index {
-        cursor - 1 - logical_index
-    } else {
-        cap + cursor - 1 - logical_index
-    };
 
     // メモリバッファが空の場合はミラーファイルから直接読み出し
     if store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
+        let cursor = store.cursor;
+        let cap = store.capacity;
+
+        // カーソル位置を最新パルスとみなし、逆算して物理インデックスを特定
+        let physical_idx = if cursor > logical_index {
+            cursor - 1 - logical_index
+        } else {
+            cap + cursor - 1 - logical_index
+        };
+
         if let Some(path) = &store.mirror_path {
             use std::io::{Read, Seek};
             let mut f = std::fs::File::open(path).ok()?;
@@ -503,14 +892,49 @@ pub fn get_at(store: &OrbyRingBufferSilo, logical_index: usize) -> Option<Arc<[u
         return None;
     }
 
-    // SoA 構造から一括取得して Arc 配列にパッケージ化
-    let row_data: Vec<u128> = store
-        .lanes
-        .iter()
-        .map(|lane| lane.buffer[physical_idx].as_u128())
-        .collect();
+    // `scan_order` と同じ順序（cursor の直前から先頭へ、折り返していれば末尾から cursor へ）を遅延評価で辿る
+    let cutoff = expiry_cutoff(store);
+    let cursor = store.cursor.min(store.capacity);
+    let wrapped_end = if is_wrapped(store) {
+        store.capacity
+    } else {
+        cursor
+    };
+    let physical_idx = (0..cursor)
+        .rev()
+        .chain((cursor..wrapped_end).rev())
+        .filter(|&slot| is_live(store, slot, cutoff))
+        .nth(logical_index)?;
+
+    Some(row_at(store, physical_idx))
+}
 
-    Some(Arc::from(row_data))
+/// スキャン順序（最新から順）に物理インデックスを並べます。
+/// `keep_block` が `false` を返すゾーンマップのブロックは丸ごと読み飛ばします。
+fn scan_order<B>(store: &OrbyRingBufferSilo, keep_block: B) -> Vec<usize>
+where
+    B: Fn(usize) -> bool,
+{
+    let cursor = store.cursor;
+    let mut order = Vec::with_capacity(store.len);
+    let mut push_desc = |range: std::ops::Range<usize>| {
+        if range.is_empty() {
+            return;
+        }
+        for block in (range.start / ZONE_BLOCK_SIZE..=(range.end - 1) / ZONE_BLOCK_SIZE).rev() {
+            if !keep_block(block) {
+                continue;
+            }
+            let start = range.start.max(block * ZONE_BLOCK_SIZE);
+            let end = range.end.min((block + 1) * ZONE_BLOCK_SIZE);
+            order.extend((start..end).rev());
+        }
+    };
+    push_desc(0..cursor);
+    if is_wrapped(store) {
+        push_desc(cursor..store.capacity);
+    }
+    order
 }
 
 /// Rayon を使用した並列 SIMD 風スキャンを実行します。
@@ -519,51 +943,293 @@ pub fn query_raw<F>(store: &OrbyRingBufferSilo, filter: F, limit: usize) -> Vec<
 where
     F: Fn(&[PulseCell]) -> bool + Sync + Send,
 {
-    let len = store.len;
-    let cursor = store.cursor;
-    let cap = store.capacity;
-
     if store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
         return Vec::new(); // ストレージ直接クエリはイテレータ側で処理
     }
+    collect_matches(
+        store,
+        scan_order(store, |_| true),
+        |slot| filter(&gather(store, slot)),
+        limit,
+    )
+}
+
+/// `find_range_monotonic` の探索点から、墓標を読み飛ばして生存行を探す最大のスロット数。
+const MONOTONIC_PROBE_LIMIT: usize = 64;
+
+/// 挿入順（tail から cursor まで）に単調増加するレーンについて、値が `min` 以上 `max` 以下の行を
+/// 二分探索で取得します。リングの周回による折り返し点を挟んだ二つの整列済み区間を、一つの論理区間として探索します。
+/// 結果は `query_raw` と同様に最新順で返されます。
+///
+/// 全件は検証しないため、単調でないことは探索中に見つかった場合のみ `ConfigMismatch` として報告されます。
+/// 探索点から `MONOTONIC_PROBE_LIMIT` 個以上の墓標が続く場合は二分探索を諦め、ウィンドウ全体を走査します。
+pub fn find_range_monotonic(
+    store: &OrbyRingBufferSilo,
+    lane: usize,
+    min: u128,
+    max: u128,
+) -> Result<Vec<Arc<[u128]>>, OrbyError> {
+    if lane >= store.ring_buffer_lane_count {
+        return Err(OrbyError::LaneCountMismatch {
+            pool_name: store.name.clone(),
+            expected: store.ring_buffer_lane_count,
+            found: lane + 1,
+        });
+    }
+    if min > max || store.len == 0 || store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
+        return Ok(Vec::new());
+    }
 
-    // キャッシュサイズ等に基づく最適な並列単位
-    let min_len = 1024;
+    let cap = store.capacity;
+    let tail = store.tail;
+    let cursor = store.cursor;
+    // 最古の行（論理位置 0）から最新の行までの、墓標を含むウィンドウの長さ
+    let window = if tail < cursor {
+        cursor - tail
+    } else {
+        cap - tail + cursor
+    };
+    let physical = |pos: usize| (tail + pos) % cap;
+    let buffer = &store.lanes[lane].buffer;
+    // 墓標は値を持たないため、後続の最初の生存行の値で代用する（末尾を超えた場合は最大値）。
+    // 墓標が上限を超えて続く場合は `None` を返し、探索の各段が O(n) になるのを避ける
+    let value_from = |pos: usize| {
+        let limit = window.min(pos + MONOTONIC_PROBE_LIMIT);
+        match (pos..limit)
+            .map(physical)
+            .find(|&slot| store.valid.get(slot))
+        {
+            Some(slot) => Some(buffer[slot].as_u128()),
+            None if limit == window => Some(u128::MAX),
+            None => None,
+        }
+    };
+    let partition = |pred: &dyn Fn(u128) -> bool| {
+        let (mut lo, mut hi) = (0, window);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if pred(value_from(mid)?) {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        Some(lo)
+    };
+    let bounds = partition(&|v| v < min)
+        .and_then(|start| Some((start, partition(&|v| v <= max)?.max(start))));
+    // 墓標の多いウィンドウでは全体を走査し、範囲外の値は読み飛ばす
+    let (start, end, scan) = match bounds {
+        Some((start, end)) => (start, end, false),
+        None => (0, window, true),
+    };
 
-    // スキャン順序を決定（最新から順に取得するため、インデックス計算の順序を整理）
-    let mut order = Vec::with_capacity(len);
-    for i in (0..cursor).rev() {
-        order.push(i);
+    let not_monotonic = |slot: usize, prev: u128, val: u128| OrbyError::ConfigMismatch {
+        name: store.name.clone(),
+        reason: format!(
+            "lane {} is not monotonic in insertion order: slot {} holds {} after {}",
+            lane, slot, val, prev
+        ),
+    };
+    // 両端の生存行が逆順であれば、探索の前提が崩れている（墓標の先で見つからない端は確認しない）
+    let first = value_from(0).unwrap_or(u128::MAX);
+    if let Some(last_slot) = (window.saturating_sub(MONOTONIC_PROBE_LIMIT)..window)
+        .rev()
+        .map(physical)
+        .find(|&slot| store.valid.get(slot))
+    {
+        let last = buffer[last_slot].as_u128();
+        if last < first {
+            return Err(not_monotonic(last_slot, first, last));
+        }
     }
-    if len == cap {
-        for i in (cursor..cap).rev() {
-            order.push(i);
+
+    let cutoff = expiry_cutoff(store);
+    let mut results = Vec::new();
+    let mut prev = None;
+    for slot in (start..end).map(physical) {
+        if !store.valid.get(slot) {
+            continue;
+        }
+        let val = buffer[slot].as_u128();
+        // 範囲内の行が整列していない、または範囲外の値が混ざっている場合も報告する
+        if let Some(prev) = prev.filter(|&prev| val < prev) {
+            return Err(not_monotonic(slot, prev, val));
         }
+        let in_range = (min..=max).contains(&val);
+        if !in_range && !scan {
+            return Err(not_monotonic(slot, prev.unwrap_or(min), val));
+        }
+        prev = Some(val);
+        if !in_range || !is_live(store, slot, cutoff) {
+            continue;
+        }
+        let row: Vec<u128> = store
+            .lanes
+            .iter()
+            .map(|lane| lane.buffer[slot].as_u128())
+            .collect();
+        results.push(Arc::from(row));
     }
+    results.reverse();
+    Ok(results)
+}
+
+/// 物理スロットの行の全レーンを集めます。
+#[inline]
+fn gather(store: &OrbyRingBufferSilo, slot: usize) -> Vec<PulseCell> {
+    store.lanes.iter().map(|lane| lane.buffer[slot]).collect()
+}
 
-    let matches: Vec<usize> = order
+/// 物理スロットの行を結果の形式で取り出します。
+#[inline]
+fn row_at(store: &OrbyRingBufferSilo, slot: usize) -> Arc<[u128]> {
+    store
+        .lanes
+        .iter()
+        .map(|lane| lane.buffer[slot].as_u128())
+        .collect()
+}
+
+/// フィルタが参照するレーンのみを読んで、物理スロットの行が一致するかを判定します。
+#[inline]
+pub fn filter_matches(store: &OrbyRingBufferSilo, filter: &Filter, slot: usize) -> bool {
+    filter.matches_by(&|lane| store.lanes.get(lane).map(|l| l.buffer[slot].as_u128()))
+}
+
+/// 索引から得た候補スロットを最新順に並べ、生存かつ期限内の行を最大 `limit` 件取得します。
+/// `verify` を指定した場合は、候補の行がフィルタに一致するかも確認します。
+pub fn collect_candidates(
+    store: &OrbyRingBufferSilo,
+    slots: Vec<usize>,
+    verify: Option<&Filter>,
+    limit: usize,
+) -> Vec<Arc<[u128]>> {
+    candidate_order(store, slots, verify)
+        .take(limit)
+        .map(|(_, slot)| row_at(store, slot))
+        .collect()
+}
+
+/// `collect_candidates` と同様に候補を絞り込み、行の代わりに論理インデックス（最新順）を返します。
+pub fn candidate_indices(
+    store: &OrbyRingBufferSilo,
+    slots: Vec<usize>,
+    verify: Option<&Filter>,
+    limit: usize,
+) -> Vec<usize> {
+    candidate_order(store, slots, verify)
+        .take(limit)
+        .map(|(logical, _)| logical)
+        .collect()
+}
+
+/// 候補スロットを論理インデックス（cursor の直前が 0）の昇順に並べ、条件を満たす行の
+/// （論理インデックス, 物理スロット）を列挙します。
+fn candidate_order<'a>(
+    store: &'a OrbyRingBufferSilo,
+    mut slots: Vec<usize>,
+    verify: Option<&'a Filter>,
+) -> impl Iterator<Item = (usize, usize)> + 'a {
+    let cap = store.capacity;
+    let cursor = store.cursor;
+    let logical = move |slot: usize| (cursor + cap - 1 - slot) % cap;
+    slots.sort_unstable_by_key(|&slot| logical(slot));
+    slots.dedup();
+
+    let cutoff = expiry_cutoff(store);
+    slots.into_iter().filter_map(move |slot| {
+        if !is_live(store, slot, cutoff) {
+            return None;
+        }
+        if verify.is_some_and(|filter| !filter_matches(store, filter, slot)) {
+            return None;
+        }
+        Some((logical(slot), slot))
+    })
+}
+
+/// スキャン順序に従って並列に判定し、生存かつ期限内で `predicate` を満たす物理スロットを順序どおりに返します。
+/// `init` はスレッドごとに一度だけ呼ばれ、その戻り値を判定用の作業領域として使い回します。
+fn matching_slots<T, I, P>(
+    store: &OrbyRingBufferSilo,
+    order: Vec<usize>,
+    init: I,
+    predicate: P,
+) -> Vec<usize>
+where
+    I: Fn() -> T + Sync + Send,
+    P: Fn(&mut T, usize) -> bool + Sync + Send,
+{
+    // キャッシュサイズ等に基づく最適な並列単位
+    let min_len = 1024;
+
+    let cutoff = expiry_cutoff(store);
+    order
         .into_par_iter()
         .with_min_len(min_len)
-        .filter(|&i| {
-            let row_cells: Vec<PulseCell> = store.lanes.iter().map(|lane| lane.buffer[i]).collect();
-            // 墓標（全次元ゼロ）はスキップ
-            if row_cells.iter().all(|&v| v.as_u128() == 0) {
-                return false;
-            }
-            filter(&row_cells)
+        .map_init(init, |scratch, i| {
+            // 墓標（有効ビットなし）と期限切れの行はスキップ
+            (is_live(store, i, cutoff) && predicate(scratch, i)).then_some(i)
         })
-        .collect();
+        .flatten()
+        .collect()
+}
 
-    let mut results = Vec::with_capacity(limit);
-    for &i in matches.iter().take(limit) {
-        let row_vals: Vec<u128> = store
-            .lanes
-            .iter()
-            .map(|lane| lane.buffer[i].as_u128())
-            .collect();
-        results.push(Arc::from(row_vals));
+/// スキャン順序に従って並列に判定し、`predicate` を満たす行を最大 `limit` 件取得します。
+/// 行の全レーンは一致したスロットについてのみ集めます。
+fn collect_matches<P>(
+    store: &OrbyRingBufferSilo,
+    order: Vec<usize>,
+    predicate: P,
+    limit: usize,
+) -> Vec<Arc<[u128]>>
+where
+    P: Fn(usize) -> bool + Sync + Send,
+{
+    matching_slots(store, order, || (), |_, slot| predicate(slot))
+        .into_iter()
+        .take(limit)
+        .map(|slot| row_at(store, slot))
+        .collect()
+}
+
+/// `lanes` に束縛した述語で検索し、一致した行を最新順に最大 `limit` 件取得します。
+/// 述語には `lanes` の順に並べた値のみが渡されるため、走査では指定したレーンのバッファだけを読みます。
+pub fn query_lanes<P>(
+    store: &OrbyRingBufferSilo,
+    lanes: &[usize],
+    predicate: P,
+    limit: usize,
+) -> Vec<Arc<[u128]>>
+where
+    P: Fn(&[u128]) -> bool + Sync + Send,
+{
+    if store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
+        return Vec::new();
     }
-    results
+    if lanes.iter().any(|&lane| lane >= store.lanes.len()) {
+        return Vec::new();
+    }
+    let buffers: Vec<&[PulseCell]> = lanes
+        .iter()
+        .map(|&lane| store.lanes[lane].buffer.as_slice())
+        .collect();
+    let order = scan_order(store, |_| true);
+    matching_slots(
+        store,
+        order,
+        || Vec::with_capacity(buffers.len()),
+        |values: &mut Vec<u128>, slot| {
+            values.clear();
+            values.extend(buffers.iter().map(|buffer| buffer[slot].as_u128()));
+            predicate(values)
+        },
+    )
+    .into_iter()
+    .take(limit)
+    .map(|slot| row_at(store, slot))
+    .collect()
 }
 
 /// 条件に合致するレコードの論理インデックスリストを取得します。
@@ -571,46 +1237,70 @@ pub fn find_indices<F>(store: &OrbyRingBufferSilo, filter: F, limit: usize) -> V
 where
     F: Fn(&[PulseCell]) -> bool + Sync + Send,
 {
-    let len = store.len;
-    let cursor = store.cursor;
-    let cap = store.capacity;
+    slot_indices(store, |_| true, |slot| filter(&gather(store, slot)), limit)
+}
 
+/// `keep_block` が `true` を返すブロックのみを走査して、フィルタに一致した行を最新順に最大 `limit` 件取得します。
+/// フィルタが参照するレーンのみを読み、行の全レーンは一致した行についてのみ集めます。
+pub fn query_blocks<B>(
+    store: &OrbyRingBufferSilo,
+    keep_block: B,
+    filter: &Filter,
+    limit: usize,
+) -> Vec<Arc<[u128]>>
+where
+    B: Fn(usize) -> bool,
+{
     if store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
         return Vec::new();
     }
+    collect_matches(
+        store,
+        scan_order(store, keep_block),
+        |slot| filter_matches(store, filter, slot),
+        limit,
+    )
+}
 
-    let min_len = 1024;
-    let mut order = Vec::with_capacity(len);
-    for i in (0..cursor).rev() {
-        order.push(i);
-    }
-    if len == cap {
-        for i in (cursor..cap).rev() {
-            order.push(i);
-        }
-    }
+/// `keep_block` が `true` を返すブロックのみを走査して、フィルタに一致した行の論理インデックスを取得します。
+pub fn find_indices_in_blocks<B>(
+    store: &OrbyRingBufferSilo,
+    keep_block: B,
+    filter: &Filter,
+    limit: usize,
+) -> Vec<usize>
+where
+    B: Fn(usize) -> bool,
+{
+    slot_indices(
+        store,
+        keep_block,
+        |slot| filter_matches(store, filter, slot),
+        limit,
+    )
+}
 
-    let mut indices: Vec<usize> = order
-        .into_par_iter()
-        .enumerate()
-        .with_min_len(min_len)
-        .filter_map(|(logical_idx, physical_idx)| {
-            let row_cells: Vec<PulseCell> = store
-                .lanes
-                .iter()
-                .map(|lane| lane.buffer[physical_idx])
-                .collect();
-
-            if row_cells.iter().all(|&v| v.as_u128() == 0) {
-                return None;
-            }
+/// `predicate` を満たす行の論理インデックスを昇順（最新順）に最大 `limit` 件取得します。
+fn slot_indices<B, P>(
+    store: &OrbyRingBufferSilo,
+    keep_block: B,
+    predicate: P,
+    limit: usize,
+) -> Vec<usize>
+where
+    B: Fn(usize) -> bool,
+    P: Fn(usize) -> bool + Sync + Send,
+{
+    if store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
+        return Vec::new();
+    }
 
-            if filter(&row_cells) {
-                Some(logical_idx)
-            } else {
-                None
-            }
-        })
+    let cap = store.capacity;
+    let cursor = store.cursor;
+    let order = scan_order(store, keep_block);
+    let mut indices: Vec<usize> = matching_slots(store, order, || (), |_, slot| predicate(slot))
+        .into_iter()
+        .map(|slot| (cursor + cap - 1 - slot) % cap)
         .collect();
 
     // 安定ソートを行い、上位 limit 件を抽出
@@ -0,0 +1,174 @@
+use crate::logic::zone::ZONE_BLOCK_SIZE;
+use crate::logic::OrbyRingBufferSilo;
+
+/// レジスタ数の対数。1024 個のレジスタで標準誤差は約 3.3% です。
+const PRECISION: u32 = 10;
+/// ブロックあたりのレジスタ数（1 レジスタ 1 バイト）。
+pub const REGISTERS: usize = 1 << PRECISION;
+
+/// 基数推定レーン（`approx_distinct_lane`）の値を、ゾーンマップと同じブロック単位で要約する
+/// HyperLogLog スケッチ。全ブロックのレジスタの最大値を取ると、生存行全体のスケッチになります。
+///
+/// HyperLogLog は値を取り除けないため、ブルームフィルタと同様に上書き・削除されたブロックは
+/// `stale` として印を付け、リングの周回でブロックを書き終えた時点、またはコンパクション後に
+/// 生存行から作り直します。それまでの間は、取り除かれた値の分だけ推定値が大きくなり得ます。
+#[derive(Debug, Clone, Default)]
+pub struct BlockSketch {
+    pub lane: usize,
+    registers: Vec<u8>,
+    stale: Vec<bool>,
+}
+
+impl BlockSketch {
+    pub fn new(lane: usize, capacity: usize) -> Self {
+        let blocks = capacity.div_ceil(ZONE_BLOCK_SIZE);
+        Self {
+            lane,
+            registers: vec![0; blocks * REGISTERS],
+            stale: vec![false; blocks],
+        }
+    }
+
+    /// 値を 64 ビットへ畳み込み、上位 `PRECISION` ビットをレジスタ番号、
+    /// 残りのビットの先頭の 0 の数 + 1 をレジスタへ書き込む値とします。
+    #[inline]
+    fn register_of(key: u128) -> (usize, u8) {
+        let hash = mix((key as u64) ^ mix((key >> 64) as u64 ^ 0x9E37_79B9_7F4A_7C15));
+        let index = (hash >> (64 - PRECISION)) as usize;
+        let rank = ((hash << PRECISION).leading_zeros()).min(64 - PRECISION) + 1;
+        (index, rank as u8)
+    }
+
+    #[inline]
+    fn insert(&mut self, block: usize, key: u128) {
+        let (index, rank) = Self::register_of(key);
+        let register = &mut self.registers[block * REGISTERS + index];
+        *register = (*register).max(rank);
+    }
+
+    fn clear_block(&mut self, block: usize) {
+        let base = block * REGISTERS;
+        self.registers[base..base + REGISTERS].fill(0);
+        self.stale[block] = false;
+    }
+
+    /// 全ブロックのレジスタを統合した、生存行全体のスケッチを返します。
+    pub fn merged(&self) -> Vec<u8> {
+        let mut merged = vec![0u8; REGISTERS];
+        for block in self.registers.chunks_exact(REGISTERS) {
+            merge_into(&mut merged, block);
+        }
+        merged
+    }
+}
+
+/// 64 ビットの値を攪拌します（MurmurHash3 の最終処理）。
+#[inline]
+fn mix(mut x: u64) -> u64 {
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xC4CE_B9FE_1A85_EC53);
+    x ^ (x >> 33)
+}
+
+/// `other` のレジスタを `registers` へ統合します。シャードごとのスケッチの統合にも使用します。
+pub fn merge_into(registers: &mut [u8], other: &[u8]) {
+    for (register, &value) in registers.iter_mut().zip(other) {
+        *register = (*register).max(value);
+    }
+}
+
+/// レジスタから異なる値の数を推定します。推定値が小さい間は線形計数で補正します。
+pub fn estimate(registers: &[u8]) -> u64 {
+    let m = registers.len() as f64;
+    let alpha = 0.7213 / (1.0 + 1.079 / m);
+    let sum: f64 = registers.iter().map(|&r| 2f64.powi(-i32::from(r))).sum();
+    let raw = alpha * m * m / sum;
+    let zeros = registers.iter().filter(|&&r| r == 0).count();
+    let estimate = if raw <= 2.5 * m && zeros > 0 {
+        m * (m / zeros as f64).ln()
+    } else {
+        raw
+    };
+    estimate.round() as u64
+}
+
+#[inline]
+fn has_mem(store: &OrbyRingBufferSilo) -> bool {
+    !store.lanes.is_empty() && !store.lanes[0].buffer.is_empty()
+}
+
+/// 指定スロットの生存行の値をスケッチへ登録します。行を書き込んだ後に呼び出します。
+#[inline]
+pub fn remember(store: &mut OrbyRingBufferSilo, slot: usize) {
+    if store.sketches.is_empty() || !has_mem(store) || !store.valid.get(slot) {
+        return;
+    }
+    let block = slot / ZONE_BLOCK_SIZE;
+    for sketch in &mut store.sketches {
+        let key = store.lanes[sketch.lane].buffer[slot].as_u128();
+        sketch.insert(block, key);
+    }
+}
+
+/// 指定スロットの行が上書き・削除されることを記録します。ブロックは作り直しの対象になります。
+#[inline]
+pub fn forget(store: &mut OrbyRingBufferSilo, slot: usize) {
+    if store.sketches.is_empty() || !store.valid.get(slot) {
+        return;
+    }
+    for sketch in &mut store.sketches {
+        sketch.stale[slot / ZONE_BLOCK_SIZE] = true;
+    }
+}
+
+/// 書き込みがブロックの末尾に達し、そのブロックに上書き・削除があった場合はスケッチを作り直します。
+#[inline]
+pub fn settle(store: &mut OrbyRingBufferSilo, slot: usize) {
+    let block = slot / ZONE_BLOCK_SIZE;
+    let block_end = (slot + 1).is_multiple_of(ZONE_BLOCK_SIZE) || slot + 1 == store.capacity;
+    if block_end && store.sketches.iter().any(|sketch| sketch.stale[block]) {
+        rebuild_block(store, block);
+    }
+}
+
+/// ブロックのスケッチを生存行から作り直します。
+fn rebuild_block(store: &mut OrbyRingBufferSilo, block: usize) {
+    let mut sketches = std::mem::take(&mut store.sketches);
+    let start = block * ZONE_BLOCK_SIZE;
+    let end = (start + ZONE_BLOCK_SIZE).min(store.capacity);
+    for sketch in &mut sketches {
+        sketch.clear_block(block);
+        if has_mem(store) {
+            let buffer = &store.lanes[sketch.lane].buffer[start..end];
+            for (slot, cell) in (start..end).zip(buffer) {
+                if store.valid.get(slot) {
+                    sketch.insert(block, cell.as_u128());
+                }
+            }
+        }
+    }
+    store.sketches = sketches;
+}
+
+/// 全ブロックのスケッチを作り直します。
+/// コンパクションやロード後など、行がブロックをまたいで移動した後に使用します。
+pub fn rebuild(store: &mut OrbyRingBufferSilo) {
+    let blocks = match store.sketches.first() {
+        Some(sketch) => sketch.stale.len(),
+        None => return,
+    };
+    for block in 0..blocks {
+        rebuild_block(store, block);
+    }
+}
+
+/// `lane` にスケッチがあれば、生存行全体のレジスタを返します。
+pub fn registers(store: &OrbyRingBufferSilo, lane: usize) -> Option<Vec<u8>> {
+    store
+        .sketches
+        .iter()
+        .find(|sketch| sketch.lane == lane)
+        .map(BlockSketch::merged)
+}
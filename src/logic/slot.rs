@@ -0,0 +1,226 @@
+use crate::error::OrbyError;
+use crate::logic::{ring, zone, OrbyRingBufferSilo, PersistenceChanges, RingOperation};
+use crate::types::PulseCell;
+use std::sync::Arc;
+
+/// 指定した物理スロットが空（有効ビットなし）かどうかを判定します。
+fn is_vacant(store: &OrbyRingBufferSilo, slot: usize) -> bool {
+    !store.valid.get(slot)
+}
+
+/// 空きスロットを一つ確保します。
+/// 解放済みスロットを番号の小さい順に再利用し、なければ未使用領域（cursor 以降）から払い出します。
+fn allocate(store: &mut OrbyRingBufferSilo) -> Option<usize> {
+    if let Some(slot) = store.free_slots.pop_first() {
+        return Some(slot);
+    }
+    if store.cursor < store.capacity {
+        let slot = store.cursor;
+        store.cursor += 1;
+        return Some(slot);
+    }
+    None
+}
+
+/// 行データを物理スロットへ垂直に焼き付けます。
+fn write_row(store: &mut OrbyRingBufferSilo, slot: usize, row: &[u128]) {
+    for (lane, &val) in store.lanes.iter_mut().zip(row.iter()) {
+        lane.buffer[slot] = PulseCell::new(val);
+    }
+    store.valid.set(slot);
+    zone::note_row(store, slot);
+}
+
+/// 固定スロット戦略に基づくバッチ挿入ロジック。
+/// 各行に空きスロットを割り当て、使用したスロット番号を挿入順に返します。
+/// 空きが足りない場合は一件も書き込まずに `StorageFull` を返します。
+pub fn insert_batch<T, I>(
+    store: &mut OrbyRingBufferSilo,
+    items: I,
+) -> Result<(Vec<usize>, PersistenceChanges), OrbyError>
+where
+    I: Iterator<Item = T>,
+    T: AsRef<[u128]>,
+{
+    let mut changes = PersistenceChanges::new();
+    let dim = store.ring_buffer_lane_count;
+
+    let raw_rows: Vec<Vec<u128>> = items.map(|item| item.as_ref().to_vec()).collect();
+    if raw_rows.is_empty() {
+        return Ok((Vec::new(), changes));
+    }
+
+    // 1. 事前検証（次元数と空き容量）
+    if let Some(row) = raw_rows.iter().find(|row| row.len() != dim) {
+        return Err(OrbyError::LaneCountMismatch {
+            pool_name: store.name.clone(),
+            expected: dim,
+            found: row.len(),
+        });
+    }
+    let available = store.free_slots.len() + (store.capacity - store.cursor);
+    if raw_rows.len() > available {
+        return Err(OrbyError::StorageFull {
+            pool_name: store.name.clone(),
+            capacity: store.capacity,
+        });
+    }
+
+    // 2. スロット割り当てと書き込み
+    let mut slots = Vec::with_capacity(raw_rows.len());
+    for row in raw_rows {
+        // 事前検証済みのため必ず確保できる
+        let slot = allocate(store).expect("slot availability was checked");
+        write_row(store, slot, &row);
+        store.len += 1;
+        slots.push(slot);
+
+        changes.push(RingOperation::InsertAt {
+            physical_index: slot,
+            data: row,
+        });
+    }
+
+    changes.push(RingOperation::HeaderUpdate {
+        len: store.len,
+        cursor: store.cursor,
+    });
+
+    Ok((slots, changes))
+}
+
+/// 指定したスロットへ直接書き込みます。
+/// 使用中のスロットは上書きされ、空きスロットは使用中として扱われるようになります。
+pub fn insert_at(
+    store: &mut OrbyRingBufferSilo,
+    slot: usize,
+    row: &[u128],
+) -> Result<PersistenceChanges, OrbyError> {
+    let mut changes = PersistenceChanges::new();
+    let dim = store.ring_buffer_lane_count;
+
+    if row.len() != dim {
+        return Err(OrbyError::LaneCountMismatch {
+            pool_name: store.name.clone(),
+            expected: dim,
+            found: row.len(),
+        });
+    }
+    if slot >= store.capacity {
+        return Err(OrbyError::SlotOutOfRange {
+            pool_name: store.name.clone(),
+            slot,
+            capacity: store.capacity,
+        });
+    }
+
+    if slot >= store.cursor {
+        // 未使用領域を飛び越える場合、間のスロットは空きとして登録
+        for skipped in store.cursor..slot {
+            store.free_slots.insert(skipped);
+        }
+        store.cursor = slot + 1;
+        store.len += 1;
+    } else if store.free_slots.remove(&slot) {
+        store.len += 1;
+    }
+
+    write_row(store, slot, row);
+
+    changes.push(RingOperation::InsertAt {
+        physical_index: slot,
+        data: row.to_vec(),
+    });
+    changes.push(RingOperation::HeaderUpdate {
+        len: store.len,
+        cursor: store.cursor,
+    });
+
+    Ok(changes)
+}
+
+/// 指定したスロットのデータを取得します。空きスロットの場合は `None` を返します。
+pub fn get_slot(store: &OrbyRingBufferSilo, slot: usize) -> Option<Arc<[u128]>> {
+    if slot >= store.capacity || store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
+        return None;
+    }
+    if is_vacant(store, slot) {
+        return None;
+    }
+    let row_data: Vec<u128> = store
+        .lanes
+        .iter()
+        .map(|lane| lane.buffer[slot].as_u128())
+        .collect();
+    Some(Arc::from(row_data))
+}
+
+/// 指定したスロットを解放します。
+/// コンパクションは行わず、スロット番号は空きリストへ戻され再利用されます。
+pub fn delete(store: &mut OrbyRingBufferSilo, slot: usize) -> (bool, PersistenceChanges) {
+    let mut changes = PersistenceChanges::new();
+    if slot >= store.cursor || is_vacant(store, slot) {
+        return (false, changes);
+    }
+
+    for lane in &mut store.lanes {
+        lane.buffer[slot] = PulseCell::new(0);
+    }
+    store.valid.clear(slot);
+    store.free_slots.insert(slot);
+    if store.len > 0 {
+        store.len -= 1;
+    }
+
+    changes.push(RingOperation::Delete {
+        physical_index: slot,
+    });
+    changes.push(RingOperation::HeaderUpdate {
+        len: store.len,
+        cursor: store.cursor,
+    });
+
+    (true, changes)
+}
+
+/// 特定のカラム値に一致するレコードを削除し、そのスロットを空きリストへ戻します。
+pub fn purge_by_id(store: &mut OrbyRingBufferSilo, index: usize, id: u128) -> PersistenceChanges {
+    let changes = ring::purge_by_id(store, index, id);
+    for op in &changes.ops {
+        if let RingOperation::Purge {
+            physical_indices, ..
+        } = op
+        {
+            store.free_slots.extend(physical_indices.iter().copied());
+        }
+    }
+    changes
+}
+
+/// 指定した ID があれば更新、なければ空きスロットへ挿入します。
+pub fn upsert(
+    store: &mut OrbyRingBufferSilo,
+    index: usize,
+    id: u128,
+    data: &[u128],
+) -> Result<PersistenceChanges, OrbyError> {
+    let (found, changes) = ring::update_by_id(store, index, id, data);
+    if found {
+        return Ok(changes);
+    }
+    insert_batch(store, std::iter::once(data)).map(|(_, changes)| changes)
+}
+
+/// メモリ上のデータから空きリストを再構築します。
+/// Vault からのロード直後など、スロットの使用状況が外部から復元された場合に使用します。
+pub fn rebuild_free_slots(store: &mut OrbyRingBufferSilo) {
+    store.free_slots.clear();
+    if store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
+        return;
+    }
+    let high_water = store.cursor.min(store.capacity);
+    let free: Vec<usize> = (0..high_water)
+        .filter(|&slot| is_vacant(store, slot))
+        .collect();
+    store.free_slots.extend(free);
+}
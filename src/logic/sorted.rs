@@ -0,0 +1,372 @@
+use crate::error::OrbyError;
+use crate::filter::Filter;
+use crate::logic::{planner, ring, zone, OrbyRingBufferSilo, PersistenceChanges, RingOperation};
+use crate::types::{LogicMode, PulseCell};
+use std::sync::Arc;
+
+/// キーレーン上の値を取得します。
+#[inline]
+fn key_at(store: &OrbyRingBufferSilo, physical_idx: usize) -> u128 {
+    store.lanes[store.key_lane].buffer[physical_idx].as_u128()
+}
+
+/// `key` 以上となる最初の位置を二分探索します。
+pub(crate) fn lower_bound(store: &OrbyRingBufferSilo, key: u128) -> usize {
+    let buffer = &store.lanes[store.key_lane].buffer[..store.cursor];
+    buffer.partition_point(|cell| cell.as_u128() < key)
+}
+
+/// `key` より大きくなる最初の位置を二分探索します。
+/// 同一キーの行は挿入順に並ぶよう、既存行の後ろへ配置するために使用します。
+pub(crate) fn upper_bound(store: &OrbyRingBufferSilo, key: u128) -> usize {
+    let buffer = &store.lanes[store.key_lane].buffer[..store.cursor];
+    buffer.partition_point(|cell| cell.as_u128() <= key)
+}
+
+/// 行をキー順の位置へ挿入し、後続の行を後方へシフトします。挿入位置を返します。
+pub(crate) fn insert_row(store: &mut OrbyRingBufferSilo, row: &[u128]) -> usize {
+    let pos = upper_bound(store, row[store.key_lane]);
+    let end = store.cursor;
+    for (lane, &val) in store.lanes.iter_mut().zip(row.iter()) {
+        lane.buffer.copy_within(pos..end, pos + 1);
+        lane.buffer[pos] = PulseCell::new(val);
+    }
+    store.cursor += 1;
+    store.len += 1;
+    // 行は常に先頭から隙間なく並ぶため、末尾の有効ビットを立てるだけでよい
+    store.valid.set(end);
+    zone::note_range(store, pos..end + 1);
+    pos
+}
+
+/// 指定位置の行を取り除き、後続の行を前方へシフトします。
+pub(crate) fn remove_row(store: &mut OrbyRingBufferSilo, pos: usize) {
+    let end = store.cursor;
+    for lane in &mut store.lanes {
+        lane.buffer.copy_within(pos + 1..end, pos);
+        lane.buffer[end - 1] = PulseCell::new(0);
+    }
+    store.valid.clear(end - 1);
+    store.cursor -= 1;
+    store.len -= 1;
+    zone::note_range(store, pos..end - 1);
+}
+
+/// キー順序を維持したバッチ挿入ロジック。
+/// 各行の挿入位置（挿入時点の物理インデックス）を挿入順に返します。
+/// 空きが足りない場合は一件も書き込まずに `StorageFull` を返します。
+pub fn insert_batch<T, I>(
+    store: &mut OrbyRingBufferSilo,
+    items: I,
+) -> Result<(Vec<usize>, PersistenceChanges), OrbyError>
+where
+    I: Iterator<Item = T>,
+    T: AsRef<[u128]>,
+{
+    let mut changes = PersistenceChanges::new();
+    let dim = store.ring_buffer_lane_count;
+
+    let raw_rows: Vec<Vec<u128>> = items.map(|item| item.as_ref().to_vec()).collect();
+    if raw_rows.is_empty() {
+        return Ok((Vec::new(), changes));
+    }
+
+    // 1. 事前検証（次元数と空き容量）
+    if let Some(row) = raw_rows.iter().find(|row| row.len() != dim) {
+        return Err(OrbyError::LaneCountMismatch {
+            pool_name: store.name.clone(),
+            expected: dim,
+            found: row.len(),
+        });
+    }
+    if store.cursor + raw_rows.len() > store.capacity {
+        return Err(OrbyError::StorageFull {
+            pool_name: store.name.clone(),
+            capacity: store.capacity,
+        });
+    }
+
+    // 2. キー順の位置へ挿入
+    let mut positions = Vec::with_capacity(raw_rows.len());
+    for row in &raw_rows {
+        positions.push(insert_row(store, row));
+    }
+
+    let first = positions.iter().copied().min().unwrap_or(store.cursor);
+    changes.push(RingOperation::Insert {
+        cursor: first,
+        row_count: raw_rows.len(),
+        data: raw_rows,
+    });
+    changes.push(RingOperation::Shift {
+        start: first,
+        end: store.cursor,
+    });
+    changes.push(RingOperation::HeaderUpdate {
+        len: store.len,
+        cursor: store.cursor,
+    });
+
+    Ok((positions, changes))
+}
+
+/// 指定したインデックスの行を削除します。キー順序を保つため、常に前方へ詰めます。
+pub fn delete(store: &mut OrbyRingBufferSilo, index: usize) -> (bool, PersistenceChanges) {
+    let mut changes = PersistenceChanges::new();
+    if index >= store.cursor {
+        return (false, changes);
+    }
+
+    let end = store.cursor;
+    remove_row(store, index);
+
+    changes.push(RingOperation::Delete {
+        physical_index: index,
+    });
+    changes.push(RingOperation::Shift { start: index, end });
+    changes.push(RingOperation::HeaderUpdate {
+        len: store.len,
+        cursor: store.cursor,
+    });
+
+    (true, changes)
+}
+
+/// 指定した ID を持つ行を更新します。
+/// キーレーンの値が変わらない行はその場で書き換え、変わる行のみキー順序を保つ位置へ移動します。
+pub fn update_by_id(
+    store: &mut OrbyRingBufferSilo,
+    index: usize,
+    id: u128,
+    new_data: &[u128],
+) -> (bool, PersistenceChanges) {
+    let mut changes = PersistenceChanges::new();
+    if new_data.len() != store.ring_buffer_lane_count {
+        return (false, changes);
+    }
+    if index >= store.ring_buffer_lane_count {
+        return (false, changes);
+    }
+
+    let key_lane = store.key_lane;
+    let targets: Vec<usize> = if index == key_lane {
+        (lower_bound(store, id)..upper_bound(store, id)).collect()
+    } else {
+        (0..store.cursor)
+            .filter(|&i| store.lanes[index].buffer[i].as_u128() == id)
+            .collect()
+    };
+    if targets.is_empty() {
+        return (false, changes);
+    }
+
+    // キーが変わらない行は物理位置を変えずに更新できる
+    let new_key = new_data[key_lane];
+    let (in_place, moved): (Vec<usize>, Vec<usize>) = targets
+        .into_iter()
+        .partition(|&i| key_at(store, i) == new_key);
+    for &physical_idx in &in_place {
+        ring::forget_row(store, physical_idx);
+        for (lane, &val) in store.lanes.iter_mut().zip(new_data.iter()) {
+            lane.buffer[physical_idx] = PulseCell::new(val);
+        }
+        ring::remember_row(store, physical_idx);
+        changes.push(RingOperation::Update {
+            physical_index: physical_idx,
+            id,
+            new_data: new_data.to_vec(),
+            logical_column: index,
+        });
+    }
+
+    if !moved.is_empty() {
+        let end = store.cursor;
+        // その場で書き換えた行も Vault へ書き戻す範囲に含める
+        let mut first = in_place.first().map_or(moved[0], |&i| i.min(moved[0]));
+        // 後方から取り除くことで、未処理のターゲット位置がずれないようにする
+        for &physical_idx in moved.iter().rev() {
+            remove_row(store, physical_idx);
+        }
+        for &physical_idx in &moved {
+            let pos = insert_row(store, new_data);
+            first = first.min(pos);
+            changes.push(RingOperation::Update {
+                physical_index: physical_idx,
+                id,
+                new_data: new_data.to_vec(),
+                logical_column: index,
+            });
+        }
+        changes.push(RingOperation::Shift { start: first, end });
+    }
+
+    changes.push(RingOperation::HeaderUpdate {
+        len: store.len,
+        cursor: store.cursor,
+    });
+    (true, changes)
+}
+
+/// 指定した ID があれば更新、なければキー順の位置へ挿入します。
+pub fn upsert(
+    store: &mut OrbyRingBufferSilo,
+    index: usize,
+    id: u128,
+    data: &[u128],
+) -> Result<PersistenceChanges, OrbyError> {
+    let (found, changes) = update_by_id(store, index, id, data);
+    if found {
+        return Ok(changes);
+    }
+    insert_batch(store, std::iter::once(data)).map(|(_, changes)| changes)
+}
+
+/// 特定のカラム値に一致するレコードを削除し、前方へ詰めます。
+pub fn purge_by_id(store: &mut OrbyRingBufferSilo, index: usize, id: u128) -> PersistenceChanges {
+    let mut changes = PersistenceChanges::new();
+    if index >= store.ring_buffer_lane_count {
+        return changes;
+    }
+
+    // キーレーンであれば二分探索で一致範囲を特定できる
+    let targets: Vec<usize> = if index == store.key_lane {
+        (lower_bound(store, id)..upper_bound(store, id)).collect()
+    } else {
+        (0..store.cursor)
+            .filter(|&i| store.lanes[index].buffer[i].as_u128() == id)
+            .collect()
+    };
+    if targets.is_empty() {
+        return changes;
+    }
+
+    let end = store.cursor;
+    for &physical_idx in targets.iter().rev() {
+        remove_row(store, physical_idx);
+    }
+
+    changes.push(RingOperation::Shift {
+        start: targets[0],
+        end,
+    });
+    changes.push(RingOperation::Purge {
+        physical_indices: targets,
+        id,
+        logical_column: index,
+    });
+    changes.push(RingOperation::HeaderUpdate {
+        len: store.len,
+        cursor: store.cursor,
+    });
+
+    changes
+}
+
+/// プールの状態をリセットし、指定されたデータをキー順に並べて再初期化します。
+pub fn truncate<T, I>(
+    store: &mut OrbyRingBufferSilo,
+    items: I,
+) -> Result<PersistenceChanges, OrbyError>
+where
+    I: Iterator<Item = T>,
+    T: AsRef<[u128]>,
+{
+    let key_lane = store.key_lane;
+    let mut rows: Vec<Vec<u128>> = items
+        .take(store.capacity)
+        .map(|item| item.as_ref().to_vec())
+        .collect();
+    if let Some(row) = rows.iter().find(|row| row.len() <= key_lane) {
+        return Err(OrbyError::LaneCountMismatch {
+            pool_name: store.name.clone(),
+            expected: store.ring_buffer_lane_count,
+            found: row.len(),
+        });
+    }
+    // 安定ソートにより、同一キーの行は与えられた順序を維持する
+    rows.sort_by_key(|row| row[key_lane]);
+    ring::truncate(store, rows.into_iter())
+}
+
+/// キーレーンの値が `min` 以上 `max` 以下の行を、二分探索で絞り込んで取得します。
+/// 結果は `query_raw` と同様に物理位置の降順（キーの降順）で返されます。
+pub fn find_range(
+    store: &OrbyRingBufferSilo,
+    min: u128,
+    max: u128,
+    limit: usize,
+) -> Vec<Arc<[u128]>> {
+    if min > max || store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
+        return Vec::new();
+    }
+    let start = lower_bound(store, min);
+    let end = upper_bound(store, max);
+    collect_rows(store, start..end, limit)
+}
+
+/// `LogicMode::Sorted` / `LogicMode::TopK` のキーレーンに対する `Eq` / `In` / `Range` 条件を二分探索で評価し、
+/// 一致する行の物理スロットと、それが厳密な結果かを返します。キーレーンで絞り込めない場合は `None` を返します。
+pub fn candidates(store: &OrbyRingBufferSilo, filter: &Filter) -> Option<(Vec<usize>, bool)> {
+    if !matches!(store.logic_mode, LogicMode::Sorted | LogicMode::TopK(_))
+        || store.lanes.is_empty()
+        || store.lanes[0].buffer.is_empty()
+    {
+        return None;
+    }
+    let key_lane = store.key_lane;
+    let span = |min: u128, max: u128| lower_bound(store, min)..upper_bound(store, max);
+    planner::combine(filter, &|leaf| match leaf {
+        Filter::Eq { lane, value } if *lane == key_lane => {
+            Some((span(*value, *value).collect(), true))
+        }
+        Filter::In { lane, values } if *lane == key_lane => {
+            let slots = values
+                .iter()
+                .flat_map(|&value| span(value, value))
+                .collect();
+            Some((slots, true))
+        }
+        Filter::Range { lane, min, max } if *lane == key_lane => {
+            let slots = if min <= max {
+                span(*min, *max).collect()
+            } else {
+                Vec::new()
+            };
+            Some((slots, true))
+        }
+        _ => None,
+    })
+}
+
+/// 物理範囲を降順に走査し、墓標を除いた行を最大 `limit` 件取得します。
+fn collect_rows(
+    store: &OrbyRingBufferSilo,
+    range: std::ops::Range<usize>,
+    limit: usize,
+) -> Vec<Arc<[u128]>> {
+    let mut results = Vec::new();
+    for i in range.rev() {
+        if results.len() >= limit {
+            break;
+        }
+        if !store.valid.get(i) {
+            continue;
+        }
+        let row_vals: Vec<u128> = store
+            .lanes
+            .iter()
+            .map(|lane| lane.buffer[i].as_u128())
+            .collect();
+        results.push(Arc::from(row_vals));
+    }
+    results
+}
+
+/// メモリ上の行がキーレーンの昇順に並んでいるかを検証します。
+pub fn is_ordered(store: &OrbyRingBufferSilo) -> bool {
+    if store.lanes.is_empty() || store.lanes[0].buffer.is_empty() {
+        return true;
+    }
+    let end = store.cursor.min(store.capacity);
+    (1..end).all(|i| key_at(store, i - 1) <= key_at(store, i))
+}
@@ -0,0 +1,224 @@
+use crate::error::OrbyError;
+use crate::logic::{ring, sorted, OrbyRingBufferSilo, PersistenceChanges, RingOperation};
+use crate::types::{LogicMode, RankOrder};
+
+/// 論理モードから保持する順位の向きを取得します。
+#[inline]
+fn rank_order(store: &OrbyRingBufferSilo) -> RankOrder {
+    match store.logic_mode {
+        LogicMode::TopK(order) => order,
+        _ => RankOrder::Largest,
+    }
+}
+
+/// `key` が `worst` よりも上位であるかを判定します。
+/// 同値は既存の行を優先し、上位とはみなしません。
+#[inline]
+fn outranks(order: RankOrder, key: u128, worst: u128) -> bool {
+    match order {
+        RankOrder::Largest => key > worst,
+        RankOrder::Smallest => key < worst,
+    }
+}
+
+/// 最下位の行の物理位置を返します。行はキーの昇順に並んでいるため、常にどちらかの端です。
+#[inline]
+fn worst_position(order: RankOrder, len: usize) -> usize {
+    match order {
+        RankOrder::Largest => 0,
+        RankOrder::Smallest => len - 1,
+    }
+}
+
+/// 満杯のプールで最下位の行のキーを返します。空きがある間はどの行も順位に入るため `None` です。
+fn threshold(store: &OrbyRingBufferSilo) -> Option<u128> {
+    (store.capacity > 0 && store.cursor == store.capacity).then(|| {
+        let worst = worst_position(rank_order(store), store.cursor);
+        store.lanes[store.key_lane].buffer[worst].as_u128()
+    })
+}
+
+/// `value` が順位に入らないことを、現在の最下位の行のキーとともに報告します。
+pub fn rank_too_low(store: &OrbyRingBufferSilo, value: u128) -> OrbyError {
+    OrbyError::RankTooLow {
+        pool_name: store.name.clone(),
+        value,
+        threshold: threshold(store).unwrap_or(value),
+    }
+}
+
+/// `rows` を順に挿入した場合に、各行が順位に入るか（書き込まれるか）を返します。
+/// 順位に入らない行はその後の行の追い出しに影響しないため、除外した残りの行を `insert_batch` に渡せます。
+/// 次元数の合わない行は `true` とし、書き込み処理側でエラーとして報告させます。
+pub fn admit<T: AsRef<[u128]>>(store: &OrbyRingBufferSilo, rows: &[T]) -> Vec<bool> {
+    let cap = store.capacity;
+    let order = rank_order(store);
+    let key_lane = store.key_lane;
+    if cap == 0 {
+        return vec![true; rows.len()];
+    }
+    let mut keys: Vec<u128> = store.lanes[key_lane].buffer[..store.cursor]
+        .iter()
+        .map(|cell| cell.as_u128())
+        .collect();
+    rows.iter()
+        .map(|row| {
+            let row = row.as_ref();
+            if row.len() != store.ring_buffer_lane_count {
+                return true;
+            }
+            let key = row[key_lane];
+            if keys.len() == cap {
+                let worst = worst_position(order, keys.len());
+                if !outranks(order, key, keys[worst]) {
+                    return false;
+                }
+                keys.remove(worst);
+            }
+            let pos = keys.partition_point(|&k| k <= key);
+            keys.insert(pos, key);
+            true
+        })
+        .collect()
+}
+
+/// 上位 K 件を保持するバッチ挿入ロジック。
+/// 満杯の場合、最下位の行より上位の行のみが受け入れられ、最下位の行を追い出します。
+/// 呼び出し側は `admit` で順位に入らない行を除外してから渡します。
+/// 残った行が一行でも順位に入らない場合は、一件も書き込まずに `RankTooLow` を返します。
+/// 各行の挿入位置（挿入時点の物理インデックス）を挿入順に返します。
+pub fn insert_batch<T, I>(
+    store: &mut OrbyRingBufferSilo,
+    items: I,
+) -> Result<(Vec<usize>, PersistenceChanges), OrbyError>
+where
+    I: Iterator<Item = T>,
+    T: AsRef<[u128]>,
+{
+    let mut changes = PersistenceChanges::new();
+    let dim = store.ring_buffer_lane_count;
+    let cap = store.capacity;
+    let order = rank_order(store);
+    let key_lane = store.key_lane;
+
+    let raw_rows: Vec<Vec<u128>> = items.map(|item| item.as_ref().to_vec()).collect();
+    if raw_rows.is_empty() {
+        return Ok((Vec::new(), changes));
+    }
+
+    // 1. 事前検証（次元数と順位）
+    if let Some(row) = raw_rows.iter().find(|row| row.len() != dim) {
+        return Err(OrbyError::LaneCountMismatch {
+            pool_name: store.name.clone(),
+            expected: dim,
+            found: row.len(),
+        });
+    }
+    if cap == 0 {
+        return Err(OrbyError::StorageFull {
+            pool_name: store.name.clone(),
+            capacity: cap,
+        });
+    }
+    // キーのみで追い出しを模擬し、バッチ全体が受け入れ可能かを確認する
+    if let Some(pos) = admit(store, &raw_rows).iter().position(|&ok| !ok) {
+        return Err(rank_too_low(store, raw_rows[pos][key_lane]));
+    }
+
+    // 2. 追い出しと挿入
+    // 追い出しは `Delete` として記録し、AOF 再生時に同じ行が取り除かれるようにする
+    let mut first = store.cursor;
+    let mut end = store.cursor;
+    let mut positions = Vec::with_capacity(raw_rows.len());
+    for row in raw_rows {
+        if store.cursor == cap {
+            let evicted = worst_position(order, store.cursor);
+            sorted::remove_row(store, evicted);
+            first = first.min(evicted);
+            changes.push(RingOperation::Delete {
+                physical_index: evicted,
+            });
+        }
+        let pos = sorted::insert_row(store, &row);
+        first = first.min(pos);
+        end = end.max(store.cursor);
+        positions.push(pos);
+        changes.push(RingOperation::Insert {
+            cursor: pos,
+            row_count: 1,
+            data: vec![row],
+        });
+    }
+
+    changes.push(RingOperation::Shift { start: first, end });
+    changes.push(RingOperation::HeaderUpdate {
+        len: store.len,
+        cursor: store.cursor,
+    });
+
+    Ok((positions, changes))
+}
+
+/// 指定した ID を持つ行を更新します。行の並べ替えは `sorted::update_by_id` と同じです。
+/// 満杯のプールでは、キーを最下位の行より下位の値へ変更する更新を、何も書き込まずに `RankTooLow` で拒否します。
+/// 追い出された行はいずれも最下位の行以下の順位のため、これにより保持する行が上位 K 件であり続けます。
+pub fn update_by_id(
+    store: &mut OrbyRingBufferSilo,
+    index: usize,
+    id: u128,
+    new_data: &[u128],
+) -> Result<(bool, PersistenceChanges), OrbyError> {
+    let key_lane = store.key_lane;
+    if let (Some(worst), Some(&new_key)) = (threshold(store), new_data.get(key_lane)) {
+        let demoted = new_data.len() == store.ring_buffer_lane_count
+            && index < store.ring_buffer_lane_count
+            && outranks(rank_order(store), worst, new_key)
+            && (0..store.cursor).any(|i| store.lanes[index].buffer[i].as_u128() == id);
+        if demoted {
+            return Err(rank_too_low(store, new_key));
+        }
+    }
+    Ok(sorted::update_by_id(store, index, id, new_data))
+}
+
+/// 指定した ID があれば更新、なければ順位に応じて挿入します。
+pub fn upsert(
+    store: &mut OrbyRingBufferSilo,
+    index: usize,
+    id: u128,
+    data: &[u128],
+) -> Result<PersistenceChanges, OrbyError> {
+    let (found, changes) = update_by_id(store, index, id, data)?;
+    if found {
+        return Ok(changes);
+    }
+    insert_batch(store, std::iter::once(data)).map(|(_, changes)| changes)
+}
+
+/// プールの状態をリセットし、指定されたデータのうち上位 K 件をキー順に並べて再初期化します。
+pub fn truncate<T, I>(
+    store: &mut OrbyRingBufferSilo,
+    items: I,
+) -> Result<PersistenceChanges, OrbyError>
+where
+    I: Iterator<Item = T>,
+    T: AsRef<[u128]>,
+{
+    let key_lane = store.key_lane;
+    let mut rows: Vec<Vec<u128>> = items.map(|item| item.as_ref().to_vec()).collect();
+    if let Some(row) = rows.iter().find(|row| row.len() <= key_lane) {
+        return Err(OrbyError::LaneCountMismatch {
+            pool_name: store.name.clone(),
+            expected: store.ring_buffer_lane_count,
+            found: row.len(),
+        });
+    }
+    // 安定ソートにより、同一キーの行は先に与えられたものが優先される
+    match rank_order(store) {
+        RankOrder::Largest => rows.sort_by(|a, b| b[key_lane].cmp(&a[key_lane])),
+        RankOrder::Smallest => rows.sort_by_key(|row| row[key_lane]),
+    }
+    rows.truncate(store.capacity);
+    rows.sort_by_key(|row| row[key_lane]);
+    ring::truncate(store, rows.into_iter())
+}
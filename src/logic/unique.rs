@@ -0,0 +1,235 @@
+use crate::error::OrbyError;
+use crate::logic::OrbyRingBufferSilo;
+use crate::types::LogicMode;
+use std::collections::{HashMap, HashSet};
+
+/// 一意性のレーンで、生存行と値が重複する行を挿入しようとした場合の扱い。
+/// 挿入以外の書き込み（更新・`upsert`・`insert_lane_batch`・`purge_all_data`）は、どちらでも `OrbyError::DuplicateKey` で拒否します。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// 重複する行のみを書き込まずに除外し、`InsertOutcome::Duplicate` として報告します（`unique_lane`）。
+    Skip,
+    /// 書き込み全体を `OrbyError::DuplicateKey` で拒否します（`unique`）。
+    Reject,
+}
+
+/// 一意性のレーン（`unique_lane` / `unique`）上の生存行の値と、その出現数を保持する索引。
+/// 出現数で管理するため、更新等で一時的に重複が生じても取り除きの整合性が崩れません。
+#[derive(Debug, Clone)]
+pub struct UniqueKeys {
+    pub lane: usize,
+    pub policy: DuplicatePolicy,
+    counts: HashMap<u128, usize>,
+}
+
+impl UniqueKeys {
+    pub fn new(lane: usize, policy: DuplicatePolicy) -> Self {
+        Self {
+            lane,
+            policy,
+            counts: HashMap::new(),
+        }
+    }
+
+    #[inline]
+    pub fn contains(&self, key: u128) -> bool {
+        self.counts.contains_key(&key)
+    }
+
+    #[inline]
+    pub fn count(&self, key: u128) -> usize {
+        self.counts.get(&key).copied().unwrap_or(0)
+    }
+
+    #[inline]
+    pub fn insert(&mut self, key: u128) {
+        *self.counts.entry(key).or_insert(0) += 1;
+    }
+
+    #[inline]
+    pub fn remove(&mut self, key: u128) {
+        if let Some(count) = self.counts.get_mut(&key) {
+            *count -= 1;
+            if *count == 0 {
+                self.counts.remove(&key);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.counts.clear();
+    }
+}
+
+/// 挿入時に重複する行を除外する（`DuplicatePolicy::Skip` の）レーンを返します。
+pub fn skipping(store: &OrbyRingBufferSilo) -> impl Iterator<Item = &UniqueKeys> {
+    store
+        .constraints
+        .iter()
+        .filter(|keys| keys.policy == DuplicatePolicy::Skip)
+}
+
+/// 指定スロットの生存行の値を索引へ登録します。一意性のレーンが未設定の場合は何もしません。
+#[inline]
+pub fn remember(store: &mut OrbyRingBufferSilo, slot: usize) {
+    if !store.valid.get(slot) {
+        return;
+    }
+    for keys in &mut store.constraints {
+        keys.insert(store.lanes[keys.lane].buffer[slot].as_u128());
+    }
+}
+
+/// 指定スロットの生存行の値を索引から外します。行を上書き・削除する前に呼び出します。
+#[inline]
+pub fn forget(store: &mut OrbyRingBufferSilo, slot: usize) {
+    if !store.valid.get(slot) {
+        return;
+    }
+    for keys in &mut store.constraints {
+        keys.remove(store.lanes[keys.lane].buffer[slot].as_u128());
+    }
+}
+
+/// 有効ビットマップとレーンの内容から索引を再構築します。
+/// レーン単位の一括書き込みや Vault からのロード後など、行単位で追跡できない変更の後に使用します。
+pub fn rebuild(store: &mut OrbyRingBufferSilo) {
+    let mut constraints = std::mem::take(&mut store.constraints);
+    for keys in &mut constraints {
+        keys.clear();
+        if store.lanes.is_empty() {
+            continue;
+        }
+        for (slot, cell) in store.lanes[keys.lane].buffer.iter().enumerate() {
+            if store.valid.get(slot) {
+                keys.insert(cell.as_u128());
+            }
+        }
+    }
+    store.constraints = constraints;
+}
+
+/// 挿入候補の各行が受け入れ可能かを判定します。
+/// `DuplicatePolicy::Skip` のレーンのいずれかで、生存ウィンドウに同じ値が存在する行、
+/// およびバッチ内で受け入れた行と値が重複する行は `false` です。
+pub fn admit<T: AsRef<[u128]>>(store: &OrbyRingBufferSilo, rows: &[T]) -> Vec<bool> {
+    let skipping: Vec<&UniqueKeys> = skipping(store).collect();
+    if skipping.is_empty() {
+        return vec![true; rows.len()];
+    }
+    let mut seen = vec![HashSet::new(); skipping.len()];
+    rows.iter()
+        .map(|row| {
+            // 次元不一致は挿入処理側でエラーとして報告させる
+            let row = row.as_ref();
+            let fresh = skipping.iter().zip(&seen).all(|(keys, seen)| {
+                row.get(keys.lane)
+                    .is_none_or(|key| !keys.contains(*key) && !seen.contains(key))
+            });
+            if fresh {
+                for (keys, seen) in skipping.iter().zip(&mut seen) {
+                    if let Some(&key) = row.get(keys.lane) {
+                        seen.insert(key);
+                    }
+                }
+            }
+            fresh
+        })
+        .collect()
+}
+
+/// 書き込みで取り除かれる行。
+enum Removed<'a> {
+    /// 指定した物理スロットの行（上書き・更新される行）
+    Slots(&'a [usize]),
+    /// 全ての行（再初期化）
+    All,
+}
+
+/// `rows` を書き込んだ後に、一意性のレーンで値を共有する生存行がないかを確認します。
+/// 違反する場合は最初に見つかった値の `OrbyError::DuplicateKey` を返します。
+fn check<T: AsRef<[u128]>>(
+    store: &OrbyRingBufferSilo,
+    rows: &[T],
+    removed: Removed<'_>,
+) -> Result<(), OrbyError> {
+    for keys in &store.constraints {
+        let lane = keys.lane;
+        let violation = |value| {
+            Err(OrbyError::DuplicateKey {
+                pool_name: store.name.clone(),
+                lane,
+                value,
+            })
+        };
+        let mut written = HashSet::with_capacity(rows.len());
+        for row in rows {
+            // 次元不一致は書き込み処理側でエラーとして報告させる
+            if let Some(&value) = row.as_ref().get(lane) {
+                if !written.insert(value) {
+                    return violation(value);
+                }
+            }
+        }
+        let Removed::Slots(slots) = removed else {
+            continue;
+        };
+        let mut freed: HashMap<u128, usize> = HashMap::new();
+        for &slot in slots.iter().filter(|&&slot| store.valid.get(slot)) {
+            *freed
+                .entry(store.lanes[lane].buffer[slot].as_u128())
+                .or_insert(0) += 1;
+        }
+        for &value in &written {
+            if keys.count(value) > freed.get(&value).copied().unwrap_or(0) {
+                return violation(value);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 挿入される行が一意性のレーンに違反しないかを確認します。書き込みの前に呼び出します。
+/// `DuplicatePolicy::Skip` のレーンで除外される行は、呼び出し前に `admit` で取り除きます。
+/// `LogicMode::RingBuffer` で周回により上書きされる行の値は、挿入後には存在しないものとして扱います。
+pub fn check_insert<T: AsRef<[u128]>>(
+    store: &OrbyRingBufferSilo,
+    rows: &[T],
+) -> Result<(), OrbyError> {
+    if store.constraints.is_empty() {
+        return Ok(());
+    }
+    let has_mem = !store.lanes.is_empty() && !store.lanes[0].buffer.is_empty();
+    let overwritten: Vec<usize> = if has_mem && store.logic_mode == LogicMode::RingBuffer {
+        (0..rows.len().min(store.capacity))
+            .map(|i| (store.cursor + i) % store.capacity)
+            .collect()
+    } else {
+        Vec::new()
+    };
+    check(store, rows, Removed::Slots(&overwritten))
+}
+
+/// `targets` の行を `new_data` で書き換えても一意性のレーンに違反しないかを確認します。書き込みの前に呼び出します。
+pub fn check_update(
+    store: &OrbyRingBufferSilo,
+    targets: &[usize],
+    new_data: &[u128],
+) -> Result<(), OrbyError> {
+    if store.constraints.is_empty() || targets.is_empty() {
+        return Ok(());
+    }
+    let rows = vec![new_data; targets.len()];
+    check(store, &rows, Removed::Slots(targets))
+}
+
+/// 全ての行を `rows` で置き換えても一意性のレーンに違反しないかを確認します。書き込みの前に呼び出します。
+pub fn check_replace<T: AsRef<[u128]>>(
+    store: &OrbyRingBufferSilo,
+    rows: &[T],
+) -> Result<(), OrbyError> {
+    if store.constraints.is_empty() {
+        return Ok(());
+    }
+    check(store, rows, Removed::All)
+}
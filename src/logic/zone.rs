@@ -0,0 +1,154 @@
+use crate::logic::OrbyRingBufferSilo;
+use crate::types::LaneZone;
+
+/// ゾーンマップの 1 ブロックあたりのスロット数。
+pub const ZONE_BLOCK_SIZE: usize = 4096;
+
+/// 行を含まないブロックの要約（min > max）。
+const EMPTY_ZONE: (u128, u128) = (u128::MAX, 0);
+
+/// 物理スロットを `ZONE_BLOCK_SIZE` 件ずつのブロックに分け、レーンごとに値の最小・最大を保持する要約。
+/// 書き込みのたびに範囲を広げるだけで、削除では縮めません。
+/// そのため要約は常に生存行の値を包含しますが、実際の範囲より広い場合があります。
+///
+/// リングの周回で上書き・削除されたブロックは `stale` として印を付け、ブルームフィルタと同様に
+/// ブロックを書き終えた時点で生存行から作り直します。これにより、周回後も読み飛ばしが機能します。
+#[derive(Debug, Clone, Default)]
+pub struct ZoneMap {
+    /// `zones[lane][block]` = (min, max)
+    zones: Vec<Vec<(u128, u128)>>,
+    stale: Vec<bool>,
+}
+
+impl ZoneMap {
+    pub fn new(lane_count: usize, capacity: usize) -> Self {
+        let blocks = capacity.div_ceil(ZONE_BLOCK_SIZE);
+        Self {
+            zones: vec![vec![EMPTY_ZONE; blocks]; lane_count],
+            stale: vec![false; blocks],
+        }
+    }
+
+    /// ブロックの値が `[min, max]` と重なり得るかを判定します。
+    #[inline]
+    pub fn may_overlap(&self, lane: usize, block: usize, min: u128, max: u128) -> bool {
+        let (lo, hi) = self.zones[lane][block];
+        lo <= hi && lo <= max && min <= hi
+    }
+
+    #[inline]
+    fn widen(&mut self, lane: usize, block: usize, val: u128) {
+        let zone = &mut self.zones[lane][block];
+        zone.0 = zone.0.min(val);
+        zone.1 = zone.1.max(val);
+    }
+
+    fn reset(&mut self) {
+        for lane in &mut self.zones {
+            lane.fill(EMPTY_ZONE);
+        }
+        self.stale.fill(false);
+    }
+
+    fn clear_block(&mut self, block: usize) {
+        for lane in &mut self.zones {
+            lane[block] = EMPTY_ZONE;
+        }
+        self.stale[block] = false;
+    }
+
+    /// レーンのブロックごとの要約を返します。行を含まないブロックは除きます。
+    pub fn lane_zones(&self, lane: usize, capacity: usize) -> Vec<LaneZone> {
+        let Some(blocks) = self.zones.get(lane) else {
+            return Vec::new();
+        };
+        blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, (lo, hi))| lo <= hi)
+            .map(|(block, &(min, max))| LaneZone {
+                start: block * ZONE_BLOCK_SIZE,
+                end: ((block + 1) * ZONE_BLOCK_SIZE).min(capacity),
+                min,
+                max,
+            })
+            .collect()
+    }
+}
+
+#[inline]
+fn has_mem(store: &OrbyRingBufferSilo) -> bool {
+    !store.lanes.is_empty() && !store.lanes[0].buffer.is_empty()
+}
+
+/// 指定スロットの行の値で、そのブロックの要約を広げます。行を書き込んだ後に呼び出します。
+#[inline]
+pub fn note_row(store: &mut OrbyRingBufferSilo, slot: usize) {
+    if !has_mem(store) {
+        return;
+    }
+    let block = slot / ZONE_BLOCK_SIZE;
+    for (lane_idx, lane) in store.lanes.iter().enumerate() {
+        store
+            .zones
+            .widen(lane_idx, block, lane.buffer[slot].as_u128());
+    }
+}
+
+/// 指定スロットの行が上書き・削除されることを記録します。ブロックは作り直しの対象になります。
+#[inline]
+pub fn forget(store: &mut OrbyRingBufferSilo, slot: usize) {
+    if store.valid.get(slot) {
+        if let Some(stale) = store.zones.stale.get_mut(slot / ZONE_BLOCK_SIZE) {
+            *stale = true;
+        }
+    }
+}
+
+/// 書き込みがブロックの末尾に達し、そのブロックに上書き・削除があった場合は要約を作り直します。
+/// リングの周回ではブロック単位で古い値が入れ替わるため、一周ごとに一度だけ作り直されます。
+#[inline]
+pub fn settle(store: &mut OrbyRingBufferSilo, slot: usize) {
+    let block = slot / ZONE_BLOCK_SIZE;
+    let block_end = (slot + 1).is_multiple_of(ZONE_BLOCK_SIZE) || slot + 1 == store.capacity;
+    if block_end && store.zones.stale.get(block).copied().unwrap_or(false) {
+        rebuild_block(store, block);
+    }
+}
+
+/// ブロックの要約を生存行から作り直します。
+fn rebuild_block(store: &mut OrbyRingBufferSilo, block: usize) {
+    store.zones.clear_block(block);
+    if !has_mem(store) {
+        return;
+    }
+    let start = block * ZONE_BLOCK_SIZE;
+    let end = (start + ZONE_BLOCK_SIZE).min(store.capacity);
+    for slot in start..end {
+        if store.valid.get(slot) {
+            note_row(store, slot);
+        }
+    }
+}
+
+/// 物理範囲の行で要約を広げます。行のシフトなど、範囲単位で行が移動した後に使用します。
+pub fn note_range(store: &mut OrbyRingBufferSilo, range: std::ops::Range<usize>) {
+    for slot in range {
+        if store.valid.get(slot) {
+            note_row(store, slot);
+        }
+    }
+}
+
+/// 有効ビットマップとレーンの内容から要約を作り直し、削除で広がったままの範囲を縮めます。
+pub fn rebuild(store: &mut OrbyRingBufferSilo) {
+    store.zones.reset();
+    if !has_mem(store) {
+        return;
+    }
+    for slot in 0..store.capacity {
+        if store.valid.get(slot) {
+            note_row(store, slot);
+        }
+    }
+}
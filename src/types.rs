@@ -12,24 +12,214 @@ pub enum SaveMode {
 pub const HEADER_SIZE: u64 = 4096;
 pub const STORAGE_MAGIC_V1: &[u8; 16] = b"ORBY_DATA_V1_LE ";
 pub const DEFAULT_VAULT_DIR: &str = "vault_data";
+/// Default upper bound on the number of groups one `group_by` call may build.
+pub const DEFAULT_MAX_GROUPS: usize = 65_536;
 pub const PULSE_SIZE: usize = 16;
 
 /// Defines the physical behavior (data management strategy) of Orby.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum LogicMode {
+    /// Overwrites the oldest rows once the cursor wraps around.
     RingBuffer,
+    /// Append-only. Inserts past `capacity` fail with `OrbyError::StorageFull`.
+    Table,
+    /// Stable slot IDs. Freed slots go on a free list and are reused.
+    StaticSlot,
+    /// Keeps rows ordered by the key lane. Range lookups use binary search.
+    Sorted,
+    /// Places rows by hashing the key lane (open addressing). Point lookups are O(1).
+    HashSlot,
+    /// Keeps only the `capacity` best rows ranked by the key lane, ordered by that lane.
+    /// Rows that do not rank are skipped as `InsertOutcome::RankTooLow`; ranking rows evict the
+    /// current worst row. Once full, a key update may not move a row below the worst row.
+    TopK(RankOrder),
+}
+
+/// Which end of the key lane `LogicMode::TopK` keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum RankOrder {
+    /// Keeps the rows with the largest values (e.g. high-score leaderboards).
+    Largest,
+    /// Keeps the rows with the smallest values (e.g. fastest-time leaderboards).
+    Smallest,
 }
 
 impl LogicMode {
     pub fn as_u8(&self) -> u8 {
         match self {
             LogicMode::RingBuffer => 0,
+            LogicMode::Table => 1,
+            LogicMode::StaticSlot => 2,
+            LogicMode::Sorted => 3,
+            LogicMode::HashSlot => 4,
+            LogicMode::TopK(RankOrder::Largest) => 5,
+            LogicMode::TopK(RankOrder::Smallest) => 6,
         }
     }
 
     pub fn from_u8(v: u8) -> Option<Self> {
         match v {
             0 => Some(LogicMode::RingBuffer),
+            1 => Some(LogicMode::Table),
+            2 => Some(LogicMode::StaticSlot),
+            3 => Some(LogicMode::Sorted),
+            4 => Some(LogicMode::HashSlot),
+            5 => Some(LogicMode::TopK(RankOrder::Largest)),
+            6 => Some(LogicMode::TopK(RankOrder::Smallest)),
+            _ => None,
+        }
+    }
+}
+
+/// Per-row result of `insert_batch` / `insert_fixed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum InsertOutcome {
+    /// The row was written to this physical slot.
+    Inserted(usize),
+    /// The row was skipped because its `unique_lane` value already exists in the live window.
+    Duplicate,
+    /// The row was skipped because its key does not outrank the worst row of a full
+    /// `LogicMode::TopK` pool.
+    RankTooLow,
+}
+
+/// Min/max summary of one lane over a block of physical slots `[start, end)`.
+/// Summaries widen on writes but do not shrink on deletes until they are rebuilt
+/// (ring wraparound finishing the block, vault load, `purge_all_data`, compaction),
+/// so the range always covers the live values but may be wider than them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LaneZone {
+    pub start: usize,
+    pub end: usize,
+    pub min: u128,
+    pub max: u128,
+}
+
+/// How the planner answers a `Filter` query, ordered from cheapest to most expensive.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum AccessPath {
+    /// Candidate slots come from the key lane of a `Sorted` / `TopK` pool (binary search)
+    /// or a `HashSlot` pool (hash probe).
+    KeyLookup,
+    /// Candidate slots come from a `index_lane` / `mask_index` hash index.
+    LaneIndex,
+    /// Candidate slots come from a `composite_index` prefix lookup.
+    CompositeIndex,
+    /// Candidate slots come from `bitmap_lane` bitmap operations.
+    BitmapIndex,
+    /// Rows are scanned, skipping blocks ruled out by zone maps and the Bloom filter.
+    BlockScan,
+    /// Every live row is scanned.
+    FullScan,
+}
+
+/// The plan chosen for a `Filter` query, as reported by `Orby::explain`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct QueryPlan {
+    pub path: AccessPath,
+    /// Upper bound on the rows the plan reads (candidate slots or live rows in scanned blocks).
+    pub estimated_rows: usize,
+    /// Zone-map blocks in the pool (`ZONE_BLOCK_SIZE` slots each).
+    pub blocks_total: usize,
+    /// Blocks the plan never reads.
+    pub blocks_skipped: usize,
+    /// Whether each row read is re-checked against the filter.
+    /// `false` means the index answers the filter exactly.
+    pub recheck: bool,
+}
+
+/// One aggregate computed by `Orby::aggregate` over the rows matching a filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Agg {
+    /// Number of matching rows.
+    Count,
+    /// Smallest value of the lane.
+    Min(usize),
+    /// Largest value of the lane.
+    Max(usize),
+    /// Sum of the lane, wrapping around on overflow.
+    WrappingSum(usize),
+    /// Sum of the lane, clamped to `u128::MAX` on overflow.
+    SaturatingSum(usize),
+    /// Counts of the lane's values in `buckets` buckets of `width` starting at `min`.
+    /// `buckets` may not exceed `MAX_HISTOGRAM_BUCKETS` (4096).
+    Histogram {
+        lane: usize,
+        min: u128,
+        width: u128,
+        buckets: usize,
+    },
+}
+
+/// The result of one `Agg`, in the same position as the request.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum AggValue {
+    Count(usize),
+    /// `None` when no row matched.
+    Min(Option<u128>),
+    /// `None` when no row matched.
+    Max(Option<u128>),
+    Sum(u128),
+    Histogram(Histogram),
+}
+
+/// Fixed-width bucket counts. Bucket `i` covers `min + i * width` up to (excluding) `min + (i + 1) * width`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Histogram {
+    pub min: u128,
+    pub width: u128,
+    pub counts: Vec<usize>,
+    /// Values below `min`.
+    pub below: usize,
+    /// Values at or beyond the end of the last bucket.
+    pub above: usize,
+}
+
+/// Results of `Orby::aggregate`, one `AggValue` per requested `Agg`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AggregateResult {
+    pub values: Vec<AggValue>,
+}
+
+impl AggregateResult {
+    /// The row count at position `i`, if that aggregate is a `Count`.
+    pub fn count(&self, i: usize) -> Option<usize> {
+        match self.values.get(i)? {
+            AggValue::Count(count) => Some(*count),
+            _ => None,
+        }
+    }
+
+    /// The minimum at position `i`, if that aggregate is a `Min` and any row matched.
+    pub fn min(&self, i: usize) -> Option<u128> {
+        match self.values.get(i)? {
+            AggValue::Min(min) => *min,
+            _ => None,
+        }
+    }
+
+    /// The maximum at position `i`, if that aggregate is a `Max` and any row matched.
+    pub fn max(&self, i: usize) -> Option<u128> {
+        match self.values.get(i)? {
+            AggValue::Max(max) => *max,
+            _ => None,
+        }
+    }
+
+    /// The sum at position `i`, if that aggregate is a `WrappingSum` or `SaturatingSum`.
+    pub fn sum(&self, i: usize) -> Option<u128> {
+        match self.values.get(i)? {
+            AggValue::Sum(sum) => Some(*sum),
+            _ => None,
+        }
+    }
+
+    /// The histogram at position `i`, if that aggregate is a `Histogram`.
+    pub fn histogram(&self, i: usize) -> Option<&Histogram> {
+        match self.values.get(i)? {
+            AggValue::Histogram(histogram) => Some(histogram),
             _ => None,
         }
     }